@@ -1,10 +1,19 @@
 mod commands;
+mod config;
+mod errors;
+mod secrets;
 mod utils;
 
+use std::{collections::BTreeMap, net::IpAddr, path::PathBuf, process::ExitCode, time::Duration};
+
 use clap::{Parser, Subcommand};
 use comelit_client_rs::ComelitClientError;
 
-use crate::commands::listen;
+use crate::{
+    commands::{DeviceType, ListOptions, listen},
+    config::Config,
+    utils::{Connections, print_json},
+};
 
 #[derive(Subcommand, Debug, Clone)]
 enum SubCommands {
@@ -14,14 +23,155 @@ enum SubCommands {
         #[arg(long, default_value = "1")]
         toggle: u8,
     },
+    List {
+        #[command(flatten)]
+        options: ListOptions,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum BlindCommands {
+    List {
+        #[command(flatten)]
+        options: ListOptions,
+    },
+    Open {
+        #[arg(long)]
+        id: String,
+        /// Wait until the blind stops moving
+        #[arg(long)]
+        wait: bool,
+    },
+    Close {
+        #[arg(long)]
+        id: String,
+        /// Wait until the blind stops moving
+        #[arg(long)]
+        wait: bool,
+    },
+    Stop {
+        #[arg(long)]
+        id: String,
+    },
+    Position {
+        #[arg(long)]
+        id: String,
+        /// Target position, 0 (closed) or 100 (open): the hub can't stop a blind halfway
+        #[arg(value_parser = commands::parse_position)]
+        position: u8,
+        /// Wait until the blind stops moving
+        #[arg(long)]
+        wait: bool,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum OutletCommands {
+    List {
+        #[command(flatten)]
+        options: ListOptions,
+    },
+    On {
+        #[arg(long)]
+        id: String,
+    },
+    Off {
+        #[arg(long)]
+        id: String,
+    },
+    /// Print the instant power of an outlet, or of all outlets with their total
+    Power {
+        #[arg(long)]
+        id: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum IrrigationCommands {
+    List {
+        #[command(flatten)]
+        options: ListOptions,
+    },
+    /// Start watering a zone, given by id or name
+    Start {
+        zone: String,
+        /// Stop the zone after these minutes, waiting for them
+        #[arg(long)]
+        duration: Option<u64>,
+    },
+    /// Stop watering a zone, given by id or name
+    Stop { zone: String },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum ThermostatCommands {
+    List {
+        #[command(flatten)]
+        options: ListOptions,
+    },
+    Get {
+        #[arg(long)]
+        id: String,
+    },
+    SetTemp {
+        #[arg(long)]
+        id: String,
+        /// Target temperature in °C
+        #[arg(long)]
+        temperature: f32,
+    },
+    SetMode {
+        #[arg(long)]
+        id: String,
+        #[arg(long, value_enum)]
+        mode: commands::Mode,
+    },
+    SetSeason {
+        #[arg(long)]
+        id: String,
+        #[arg(long, value_enum)]
+        season: commands::Season,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum AliasCommands {
+    /// Save a short name for a device id in the configuration file
+    Set {
+        alias: String,
+        id: String,
+    },
+    /// Remove an alias from the configuration file
+    Remove {
+        alias: String,
+    },
     List,
 }
 
 #[derive(Subcommand, Debug, Default, Clone)]
 enum Commands {
-    Scan,
+    /// Find the Comelit devices on the local network, or the one at --host
+    Scan {
+        /// Only show the devices whose model contains this text, e.g. HSrv or "Home server"
+        #[arg(long)]
+        model: Option<String>,
+        /// Seconds to wait for the answers
+        #[arg(long, default_value_t = 5)]
+        timeout: u64,
+        /// Local IP address of the network interface to scan from
+        #[arg(long)]
+        interface: Option<IpAddr>,
+    },
     #[default]
     Listen,
+    /// List the devices of every type as a table
+    List {
+        /// Only show the devices of this type
+        #[arg(long = "type", value_enum, value_name = "TYPE")]
+        device_type: Option<DeviceType>,
+        #[command(flatten)]
+        options: ListOptions,
+    },
     Info {
         #[arg(long)]
         id: String,
@@ -32,38 +182,295 @@ enum Commands {
         #[command(subcommand)]
         command: SubCommands,
     },
+    /// Print the whole index of the hub as JSON, e.g. to attach it to a bug report
+    DumpIndex {
+        /// Print the data as sent by the hub instead of the parsed devices
+        #[arg(long)]
+        raw: bool,
+        /// Depth of the index, 2 also includes the doors and doorbells
+        #[arg(long, default_value_t = 2)]
+        level: u8,
+    },
+    /// Run the actions listed in a YAML or JSON file, in order
+    Batch { file: PathBuf },
+    /// Check the credentials, and save them in the OS keyring with --save
+    Login {
+        /// Save the password, read from the keyring when --password is not given
+        #[arg(long)]
+        save: bool,
+        /// Token of the VIP intercom to save with the password, used by the `viper` CLI
+        #[arg(long, requires_all = ["save", "viper_bridge"])]
+        viper_token: Option<String>,
+        /// Address of the ICONA bridge the token belongs to, as ip or ip:port
+        #[arg(long, requires = "viper_token")]
+        viper_bridge: Option<String>,
+    },
+    /// Print each update pushed by the hub as one JSON line, until interrupted
+    Watch {
+        /// Only print the updates of this device
+        #[arg(long)]
+        device: Option<String>,
+    },
+    Blinds {
+        #[command(subcommand)]
+        command: BlindCommands,
+    },
+    Outlets {
+        #[command(subcommand)]
+        command: OutletCommands,
+    },
+    Thermostat {
+        #[command(subcommand)]
+        command: ThermostatCommands,
+    },
+    Irrigation {
+        #[command(subcommand)]
+        command: IrrigationCommands,
+    },
+    /// Names accepted instead of the device ids by the other commands
+    Alias {
+        #[command(subcommand)]
+        command: AliasCommands,
+    },
 }
 
-#[derive(Parser, Debug)]
+impl Commands {
+    /// Whether the command connects to the hub, needing the password
+    fn logs_in(&self) -> bool {
+        !matches!(self, Commands::Scan { .. } | Commands::Alias { .. })
+    }
+
+    /// Replace the aliases given instead of device ids with their ids
+    fn resolve_aliases(&mut self, aliases: &BTreeMap<String, String>) {
+        let resolve = |id: &mut String| {
+            if let Some(target) = aliases.get(id.as_str()) {
+                *id = target.clone();
+            }
+        };
+        match self {
+            Commands::Info { id, .. }
+            | Commands::Lights {
+                command: SubCommands::Toggle { id, .. },
+            }
+            | Commands::Blinds {
+                command:
+                    BlindCommands::Open { id, .. }
+                    | BlindCommands::Close { id, .. }
+                    | BlindCommands::Stop { id }
+                    | BlindCommands::Position { id, .. },
+            }
+            | Commands::Outlets {
+                command: OutletCommands::On { id } | OutletCommands::Off { id },
+            }
+            | Commands::Thermostat {
+                command:
+                    ThermostatCommands::Get { id }
+                    | ThermostatCommands::SetTemp { id, .. }
+                    | ThermostatCommands::SetMode { id, .. }
+                    | ThermostatCommands::SetSeason { id, .. },
+            }
+            | Commands::Irrigation {
+                command:
+                    IrrigationCommands::Start { zone: id, .. } | IrrigationCommands::Stop { zone: id },
+            } => resolve(id),
+            Commands::Outlets {
+                command: OutletCommands::Power { id: Some(id) },
+            }
+            | Commands::Watch { device: Some(id) } => resolve(id),
+            _ => {}
+        }
+    }
+}
+
+#[derive(Parser, Debug, Clone)]
 struct Params {
-    #[clap(long)]
+    #[clap(long, env = "COMELIT_USER")]
     user: Option<String>,
-    #[clap(long)]
+    #[clap(long, env = "COMELIT_PASSWORD", hide_env_values = true)]
     password: Option<String>,
-    #[clap(long)]
+    #[clap(long, env = "COMELIT_HOST")]
     host: Option<String>,
-    #[clap(long)]
+    #[clap(long, env = "COMELIT_PORT")]
     port: Option<u16>,
+    /// File with the connection options not given as flags or variables
+    /// [default: ~/.config/comelit/config.toml]
+    #[clap(long, env = "COMELIT_CONFIG")]
+    config: Option<PathBuf>,
+    /// Print the results as JSON
+    #[clap(long, global = true)]
+    json: bool,
+    /// Print the errors on stderr as JSON objects with their kind and exit code
+    #[clap(long, global = true)]
+    json_errors: bool,
+    /// Aliases of the device ids, from the configuration file
+    #[clap(skip)]
+    aliases: BTreeMap<String, String>,
+    /// Clients opened by the command, disconnected once it completes
+    #[clap(skip)]
+    connections: Connections,
 
     #[command(subcommand)]
     command: Commands,
 }
 
+impl Params {
+    fn config_path(&self) -> Option<PathBuf> {
+        self.config.clone().or_else(Config::default_path)
+    }
+
+    /// Fill the options not given on the command line from the configuration
+    /// file, and replace the aliases of the device ids
+    fn with_config(mut self) -> Result<Self, ComelitClientError> {
+        let Some(path) = self.config_path() else {
+            return Ok(self);
+        };
+        let config = Config::load(&path)?;
+        self.user = self.user.or(config.user);
+        self.password = self.password.or(config.password);
+        self.host = self.host.or(config.host);
+        self.port = self.port.or(config.port);
+        self.command.resolve_aliases(&config.aliases);
+        self.aliases = config.aliases;
+        Ok(self)
+    }
+}
+
 #[tokio::main]
-async fn main() -> Result<(), ComelitClientError> {
+async fn main() -> ExitCode {
     let params = Params::parse();
+    let json_errors = params.json_errors;
+    match run(params).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => errors::report(&e, json_errors),
+    }
+}
+
+async fn run(params: Params) -> Result<(), ComelitClientError> {
+    let mut params = params.with_config()?;
+    if let Commands::Alias { command } = &params.command {
+        return alias(&params, command);
+    }
+    if params.password.is_none() && params.command.logs_in() {
+        params.password = secrets::load_password(params.user.clone(), params.host.clone()).await;
+    }
+
+    let connections = params.connections.clone();
+    let result = run_command(params).await;
+    // The only disconnection path, also taken when the command failed
+    let disconnected = connections.disconnect_all().await;
+    result.and(disconnected)
+}
 
+async fn run_command(params: Params) -> Result<(), ComelitClientError> {
     match &params.command.clone() {
-        Commands::Scan => commands::scan(params).await?,
+        Commands::Scan {
+            model,
+            timeout,
+            interface,
+        } => {
+            let options = commands::ScanOptions {
+                model: model.clone(),
+                timeout: Duration::from_secs(*timeout),
+                interface: *interface,
+            };
+            commands::scan(params, options).await?
+        }
         Commands::Listen => listen(params).await?,
+        Commands::List {
+            device_type,
+            options,
+        } => commands::list_devices(params, *device_type, options.clone()).await?,
         Commands::Info { id, level } => commands::get_device_info(params, id, level).await?,
         Commands::Lights { command } => match command {
             SubCommands::Toggle { id, toggle } => {
                 commands::toggle_light(params, id, toggle).await?
             }
-            SubCommands::List => commands::list_lights(params).await?,
+            SubCommands::List { options } => commands::list_lights(params, options.clone()).await?,
+        },
+        Commands::DumpIndex { raw, level } => commands::dump_index(params, *level, *raw).await?,
+        Commands::Batch { file } => commands::batch(params, file).await?,
+        Commands::Login {
+            save,
+            viper_token,
+            viper_bridge,
+        } => {
+            let viper = viper_token.clone().zip(viper_bridge.clone());
+            commands::login(params, *save, viper).await?
+        }
+        Commands::Watch { device } => commands::watch(params, device.clone()).await?,
+        Commands::Blinds { command } => match command {
+            BlindCommands::List { options } => {
+                commands::list_blinds(params, options.clone()).await?
+            }
+            BlindCommands::Open { id, wait } => {
+                commands::move_blind(params, id, 100, *wait).await?
+            }
+            BlindCommands::Close { id, wait } => commands::move_blind(params, id, 0, *wait).await?,
+            BlindCommands::Stop { id } => commands::stop_blind(params, id).await?,
+            BlindCommands::Position { id, position, wait } => {
+                commands::move_blind(params, id, *position, *wait).await?
+            }
+        },
+        Commands::Outlets { command } => match command {
+            OutletCommands::List { options } => {
+                commands::list_outlets(params, options.clone()).await?
+            }
+            OutletCommands::On { id } => commands::toggle_outlet(params, id, true).await?,
+            OutletCommands::Off { id } => commands::toggle_outlet(params, id, false).await?,
+            OutletCommands::Power { id } => commands::outlet_power(params, id.as_deref()).await?,
+        },
+        Commands::Thermostat { command } => match command {
+            ThermostatCommands::List { options } => {
+                commands::list_thermostats(params, options.clone()).await?
+            }
+            ThermostatCommands::Get { id } => commands::get_thermostat(params, id).await?,
+            ThermostatCommands::SetTemp { id, temperature } => {
+                commands::set_temperature(params, id, *temperature).await?
+            }
+            ThermostatCommands::SetMode { id, mode } => {
+                commands::set_mode(params, id, *mode).await?
+            }
+            ThermostatCommands::SetSeason { id, season } => {
+                commands::set_season(params, id, *season).await?
+            }
+        },
+        Commands::Alias { .. } => {}
+        Commands::Irrigation { command } => match command {
+            IrrigationCommands::List { options } => {
+                commands::list_irrigation(params, options.clone()).await?
+            }
+            IrrigationCommands::Start { zone, duration } => {
+                commands::start_irrigation(params, zone, *duration).await?
+            }
+            IrrigationCommands::Stop { zone } => commands::stop_irrigation(params, zone).await?,
         },
     }
 
     Ok(())
 }
+
+fn alias(params: &Params, command: &AliasCommands) -> Result<(), ComelitClientError> {
+    let path = params.config_path().ok_or_else(|| {
+        ComelitClientError::Generic("No configuration file, use --config".to_string())
+    })?;
+    match command {
+        AliasCommands::Set { alias, id } => {
+            Config::set_alias(&path, alias, Some(id))?;
+            println!("{} is now an alias of {}", alias, id);
+        }
+        AliasCommands::Remove { alias } => {
+            if !params.aliases.contains_key(alias) {
+                return Err(ComelitClientError::Generic(format!("No alias {}", alias)));
+            }
+            Config::set_alias(&path, alias, None)?;
+            println!("Alias {} removed", alias);
+        }
+        AliasCommands::List if params.json => print_json(&params.aliases),
+        AliasCommands::List => {
+            for (alias, id) in &params.aliases {
+                println!("{} = {}", alias, id);
+            }
+        }
+    }
+    Ok(())
+}
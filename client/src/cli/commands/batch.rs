@@ -0,0 +1,146 @@
+use std::{path::Path, time::Duration};
+
+use comelit_client_rs::{ComelitClient, ComelitClientError};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    Params,
+    commands::{Mode, Season, blinds::full_position},
+    utils::{login, print_json},
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum Action {
+    On,
+    Off,
+    Open,
+    Close,
+    /// Blind position, 0 (closed) or 100 (open)
+    Position,
+    /// Thermostat target temperature, in °C
+    Temperature,
+    Mode,
+    Season,
+}
+
+/// One step of a batch file. The file is a YAML (or JSON) list of steps:
+///
+/// ```yaml
+/// - device: DOM#LT#1.1
+///   action: off
+/// - device: DOM#BL#20.1
+///   action: position
+///   value: 100
+///   delay: 5
+/// ```
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct Step {
+    device: String,
+    action: Action,
+    #[serde(default)]
+    value: Option<Value>,
+    /// Seconds to wait before running the step
+    #[serde(default)]
+    delay: Option<f64>,
+}
+
+#[derive(Serialize, Debug)]
+struct StepResult<'a> {
+    step: usize,
+    device: &'a str,
+    action: Action,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+fn value<T: serde::de::DeserializeOwned>(step: &Step) -> Result<T, ComelitClientError> {
+    step.value
+        .clone()
+        .and_then(|v| serde_json::from_value(v).ok())
+        .ok_or_else(|| {
+            ComelitClientError::Generic(format!(
+                "Missing or invalid value for {:?} on {}",
+                step.action, step.device
+            ))
+        })
+}
+
+async fn run_step(client: &ComelitClient, step: &Step) -> Result<(), ComelitClientError> {
+    let id = step.device.as_str();
+    match step.action {
+        Action::On => client.toggle_device_status(id, true).await,
+        Action::Off => client.toggle_device_status(id, false).await,
+        Action::Open => client.toggle_blind_position(id, 100).await,
+        Action::Close => client.toggle_blind_position(id, 0).await,
+        Action::Position => {
+            let position = full_position(value(step)?).map_err(ComelitClientError::Generic)?;
+            client.toggle_blind_position(id, position).await
+        }
+        Action::Temperature => {
+            let temperature: f64 = value(step)?;
+            client
+                .set_thermostat_temperature(id, (temperature * 10.0).round() as i32)
+                .await
+        }
+        Action::Mode => {
+            let mode: Mode = value(step)?;
+            client.set_thermostat_mode(id, mode.into()).await
+        }
+        Action::Season => {
+            let season: Season = value(step)?;
+            client.set_thermostat_season(id, season.into()).await
+        }
+    }
+}
+
+/// Run the steps of the file in order. A failed step does not stop the
+/// batch, the command fails at the end if any step did.
+pub async fn batch(params: Params, file: &Path) -> Result<(), ComelitClientError> {
+    let content = std::fs::read_to_string(file)
+        .map_err(|e| ComelitClientError::Generic(format!("{}: {}", file.display(), e)))?;
+    let mut steps: Vec<Step> = serde_yaml::from_str(&content)
+        .map_err(|e| ComelitClientError::Generic(format!("{}: {}", file.display(), e)))?;
+    for step in &mut steps {
+        if let Some(id) = params.aliases.get(&step.device) {
+            step.device = id.clone();
+        }
+    }
+
+    let json = params.json;
+    let client = login(params, None).await?;
+    let mut results = Vec::with_capacity(steps.len());
+    for (i, step) in steps.iter().enumerate() {
+        if let Some(delay) = step.delay.filter(|d| *d > 0.0) {
+            tokio::time::sleep(Duration::from_secs_f64(delay)).await;
+        }
+        let error = run_step(&client, step).await.err().map(|e| e.to_string());
+        if !json {
+            match &error {
+                None => println!("{}. {} {:?}: ok", i + 1, step.device, step.action),
+                Some(e) => println!("{}. {} {:?}: {}", i + 1, step.device, step.action, e),
+            }
+        }
+        results.push(StepResult {
+            step: i + 1,
+            device: &step.device,
+            action: step.action,
+            error,
+        });
+    }
+
+    if json {
+        print_json(&results);
+    }
+    let failed = results.iter().filter(|r| r.error.is_some()).count();
+    if failed > 0 {
+        return Err(ComelitClientError::Generic(format!(
+            "{} of {} steps failed",
+            failed,
+            results.len()
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,215 @@
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use comelit_client_rs::{
+    ComelitClient, ComelitClientError, HomeDeviceData, ROOT_ID, StatusUpdate,
+    WindowCoveringDeviceData, WindowCoveringStatus,
+};
+use serde::Serialize;
+use tokio::sync::watch;
+
+use crate::{
+    Params,
+    commands::listing::{DeviceType, ListOptions, device, devices, status},
+    utils::{ActionResult, login, print_json, table},
+};
+
+/// Time allowed for the blind to start moving after the command
+const START_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time allowed for a full movement
+const MOVE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// The hub only moves the blinds all the way, so the only positions that can
+/// be reached are closed (0) and open (100)
+pub fn full_position(position: u8) -> Result<u8, String> {
+    match position {
+        0 | 100 => Ok(position),
+        _ => Err(format!(
+            "the hub can only close (0) or open (100) a blind, not move it to {}%",
+            position
+        )),
+    }
+}
+
+/// Parse the position given on the command line
+pub fn parse_position(position: &str) -> Result<u8, String> {
+    position
+        .parse()
+        .map_err(|e: std::num::ParseIntError| e.to_string())
+        .and_then(full_position)
+}
+
+/// Follows the movement of one blind from the updates pushed by the hub
+struct MovementWatcher {
+    id: String,
+    tx: watch::Sender<WindowCoveringStatus>,
+}
+
+#[async_trait]
+impl StatusUpdate for MovementWatcher {
+    async fn status_update(&self, device: &HomeDeviceData) {
+        if let HomeDeviceData::WindowCovering(blind) = device
+            && blind.id == self.id
+            && let Some(power_status) = &blind.power_status
+        {
+            self.tx.send_replace(power_status.clone());
+        }
+    }
+}
+
+/// Log in, subscribing to the updates of the blind when `wait` is set
+async fn connect(
+    params: Params,
+    id: &str,
+    wait: bool,
+) -> Result<(ComelitClient, Option<watch::Receiver<WindowCoveringStatus>>), ComelitClientError> {
+    if !wait {
+        return Ok((login(params, None).await?, None));
+    }
+    let (tx, rx) = watch::channel(WindowCoveringStatus::Stopped);
+    let watcher = Arc::new(MovementWatcher {
+        id: id.to_string(),
+        tx,
+    });
+    let client = login(params, Some(watcher)).await?;
+    client.subscribe(ROOT_ID).await?;
+    Ok((client, Some(rx)))
+}
+
+/// How a movement followed with `--wait` ended
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum Movement {
+    NotMoved,
+    Stopped,
+    TimedOut,
+}
+
+/// Wait for the blind to start moving, then to stop
+async fn wait_for_stop(mut rx: watch::Receiver<WindowCoveringStatus>) -> Movement {
+    let started = tokio::time::timeout(
+        START_TIMEOUT,
+        rx.wait_for(|s| *s != WindowCoveringStatus::Stopped),
+    )
+    .await
+    .is_ok_and(|r| r.is_ok());
+    if !started {
+        return Movement::NotMoved;
+    }
+    let stopped = tokio::time::timeout(
+        MOVE_TIMEOUT,
+        rx.wait_for(|s| *s == WindowCoveringStatus::Stopped),
+    )
+    .await
+    .is_ok_and(|r| r.is_ok());
+    if stopped {
+        Movement::Stopped
+    } else {
+        Movement::TimedOut
+    }
+}
+
+async fn blinds(
+    client: &ComelitClient,
+) -> Result<Vec<WindowCoveringDeviceData>, ComelitClientError> {
+    let devices = client.fetch_index(1).await?;
+    Ok(devices
+        .into_iter()
+        .filter_map(|(_, device)| match device {
+            HomeDeviceData::WindowCovering(blind) => Some(blind),
+            _ => None,
+        })
+        .collect())
+}
+
+pub async fn list_blinds(params: Params, options: ListOptions) -> Result<(), ComelitClientError> {
+    let json = params.json;
+    let client = login(params, None).await?;
+    let blinds = devices(&client, Some(DeviceType::Blind), &options).await?;
+    if json {
+        let blinds: Vec<WindowCoveringDeviceData> = blinds
+            .into_iter()
+            .filter_map(|device| match device {
+                HomeDeviceData::WindowCovering(blind) => Some(blind),
+                _ => None,
+            })
+            .collect();
+        print_json(&blinds);
+        return Ok(());
+    }
+    let mut table = table(&["ID", "NAME", "MOVEMENT"]);
+    for blind in &blinds {
+        table.add_row([blind.id(), blind.name(), status(blind).to_string()]);
+    }
+    println!("{table}");
+    Ok(())
+}
+
+/// Move the blind to the position, 0 is closed and 100 fully open
+pub async fn move_blind(
+    params: Params,
+    id: &str,
+    position: u8,
+    wait: bool,
+) -> Result<(), ComelitClientError> {
+    let json = params.json;
+    let position = full_position(position).map_err(ComelitClientError::Generic)?;
+    let (client, rx) = connect(params, id, wait).await?;
+    device(&client, id, DeviceType::Blind).await?;
+    client.toggle_blind_position(id, position).await?;
+    if !json {
+        println!("Blind {} moving to {}%", id, position);
+    }
+    let movement = match rx {
+        Some(rx) => Some(wait_for_stop(rx).await),
+        None => None,
+    };
+    if json {
+        print_json(&ActionResult {
+            id,
+            action: "position",
+            value: Some(serde_json::json!({ "position": position, "movement": movement })),
+        });
+    } else if let Some(movement) = movement {
+        match movement {
+            Movement::NotMoved => println!("Blind {} did not move", id),
+            Movement::Stopped => println!("Blind {} stopped", id),
+            Movement::TimedOut => println!("Timed out waiting for blind {} to stop", id),
+        }
+    }
+    Ok(())
+}
+
+pub async fn stop_blind(params: Params, id: &str) -> Result<(), ComelitClientError> {
+    let json = params.json;
+    let client = login(params, None).await?;
+    let blind = blinds(&client)
+        .await?
+        .into_iter()
+        .find(|b| b.id == id)
+        .ok_or_else(|| ComelitClientError::NotFound(format!("blind {}", id)))?;
+    // The movement is stopped with the command of the opposite direction
+    let moving = match blind.power_status {
+        Some(WindowCoveringStatus::GoingUp) => {
+            client.toggle_device_status(id, false).await?;
+            true
+        }
+        Some(WindowCoveringStatus::GoingDown) => {
+            client.toggle_device_status(id, true).await?;
+            true
+        }
+        _ => false,
+    };
+    if json {
+        print_json(&ActionResult {
+            id,
+            action: "stop",
+            value: Some(moving.into()),
+        });
+    } else if moving {
+        println!("Blind {} stopped", id);
+    } else {
+        println!("Blind {} is not moving", id);
+    }
+    Ok(())
+}
@@ -1,24 +1,26 @@
-use comelit_client_rs::{ComelitClientError, State};
+use comelit_client_rs::ComelitClientError;
 use serde_json::Value;
 
-use crate::{Params, utils::create_client};
+use crate::{
+    Params,
+    utils::{login, print_json},
+};
 
 pub async fn get_device_info(
     params: Params,
     id: &str,
     level: &Option<u8>,
 ) -> Result<(), ComelitClientError> {
-    let client = create_client(params, None).await?;
-    if let Err(e) = client.login(State::Disconnected).await {
-        println!("Login failed: {}", e);
-        return Err(e);
+    let json = params.json;
+    let client = login(params, None).await?;
+    let info = client.info::<Value>(id, level.unwrap_or(1)).await?;
+    if json {
+        print_json(&info);
     } else {
-        println!("Login successful");
+        println!(
+            "Device info: {}",
+            serde_json::to_string_pretty(&info).unwrap()
+        );
     }
-    let info = client.info::<Value>(id, level.unwrap_or(1)).await?;
-    println!(
-        "Device info: {}",
-        serde_json::to_string_pretty(&info).unwrap()
-    );
     Ok(())
 }
@@ -0,0 +1,34 @@
+use comelit_client_rs::{ComelitClientError, HomeDeviceData, ROOT_ID};
+use serde_json::Value;
+
+use crate::{
+    Params,
+    utils::{login, print_json},
+};
+
+/// Print the whole index of the hub as JSON, to attach to bug reports. With
+/// `raw` the out_data of the hub is printed as received, otherwise the
+/// devices as parsed by the client.
+pub async fn dump_index(
+    mut params: Params,
+    level: u8,
+    raw: bool,
+) -> Result<(), ComelitClientError> {
+    // The dump is always JSON, so the login is as quiet as with --json
+    params.json = true;
+    let client = login(params, None).await?;
+    if raw {
+        let out_data = client.info::<Value>(ROOT_ID, level).await?;
+        print_json(&out_data);
+    } else {
+        let mut devices: Vec<HomeDeviceData> = client
+            .fetch_index(level)
+            .await?
+            .into_iter()
+            .map(|(_, device)| device)
+            .collect();
+        devices.sort_by_cached_key(|d| d.id());
+        print_json(&devices);
+    }
+    Ok(())
+}
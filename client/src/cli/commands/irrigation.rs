@@ -0,0 +1,151 @@
+use std::time::Duration;
+
+use comelit_client_rs::{ComelitClient, ComelitClientError, HomeDeviceData, IrrigationDeviceData};
+
+use crate::{
+    Params,
+    commands::listing::{DeviceType, ListOptions, SortKey, devices, on_off},
+    utils::{ActionResult, log_in, login, print_json, table},
+};
+
+async fn zones(
+    client: &ComelitClient,
+    options: &ListOptions,
+) -> Result<Vec<IrrigationDeviceData>, ComelitClientError> {
+    Ok(devices(client, Some(DeviceType::Irrigation), options)
+        .await?
+        .into_iter()
+        .filter_map(|device| match device {
+            HomeDeviceData::Irrigation(zone) => Some(zone),
+            _ => None,
+        })
+        .collect())
+}
+
+/// Id of the zone given by id or by name, ignoring case
+async fn zone_id(client: &ComelitClient, zone: &str) -> Result<String, ComelitClientError> {
+    let options = ListOptions {
+        filter: None,
+        sort: SortKey::Id,
+    };
+    let zones = zones(client, &options).await?;
+    zones
+        .iter()
+        .find(|z| z.data.id == zone)
+        .or_else(|| {
+            zones.iter().find(|z| {
+                z.data
+                    .description
+                    .as_deref()
+                    .is_some_and(|d| d.eq_ignore_ascii_case(zone))
+            })
+        })
+        .map(|z| z.data.id.clone())
+        .ok_or_else(|| ComelitClientError::NotFound(format!("irrigation zone {}", zone)))
+}
+
+pub async fn list_irrigation(
+    params: Params,
+    options: ListOptions,
+) -> Result<(), ComelitClientError> {
+    let json = params.json;
+    let client = login(params, None).await?;
+    let zones = zones(&client, &options).await?;
+    if json {
+        print_json(&zones);
+        return Ok(());
+    }
+    let mut table = table(&["ID", "NAME", "STATUS"]);
+    for zone in zones {
+        table.add_row([
+            zone.data.id,
+            zone.data.description.unwrap_or("Unknown".to_string()),
+            on_off(&zone.data.status).to_string(),
+        ]);
+    }
+    println!("{table}");
+    Ok(())
+}
+
+/// Wait for the duration, or until the command is interrupted with Ctrl-C or
+/// killed with SIGTERM. Returns whether it was interrupted.
+async fn wait_or_interrupt(duration: Duration) -> bool {
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => std::future::pending().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = tokio::time::sleep(duration) => false,
+        _ = tokio::signal::ctrl_c() => true,
+        _ = terminate => true,
+    }
+}
+
+/// Start watering the zone. With a duration, the command waits and stops it
+/// at the end, so it can be scheduled as is from cron. The zone is also
+/// stopped when the command is interrupted meanwhile.
+pub async fn start_irrigation(
+    params: Params,
+    zone: &str,
+    duration: Option<u64>,
+) -> Result<(), ComelitClientError> {
+    let json = params.json;
+    let client = login(params, None).await?;
+    let id = zone_id(&client, zone).await?;
+    client.toggle_device_status(&id, true).await?;
+    if !json {
+        println!("Irrigation zone {} started", id);
+    }
+    let Some(minutes) = duration else {
+        if json {
+            print_json(&ActionResult {
+                id: &id,
+                action: "start",
+                value: None,
+            });
+        }
+        return Ok(());
+    };
+
+    let interrupted = wait_or_interrupt(Duration::from_secs(minutes * 60)).await;
+    // The session may have expired while waiting, log in again to stop the zone
+    log_in(&client).await?;
+    client.toggle_device_status(&id, false).await?;
+    if json {
+        print_json(&ActionResult {
+            id: &id,
+            action: "start",
+            value: Some(serde_json::json!({ "duration": minutes, "interrupted": interrupted })),
+        });
+    } else if interrupted {
+        println!("Irrigation zone {} stopped on interruption", id);
+    } else {
+        println!("Irrigation zone {} stopped after {} minutes", id, minutes);
+    }
+    Ok(())
+}
+
+pub async fn stop_irrigation(params: Params, zone: &str) -> Result<(), ComelitClientError> {
+    let json = params.json;
+    let client = login(params, None).await?;
+    let id = zone_id(&client, zone).await?;
+    client.toggle_device_status(&id, false).await?;
+    if json {
+        print_json(&ActionResult {
+            id: &id,
+            action: "stop",
+            value: None,
+        });
+    } else {
+        println!("Irrigation zone {} stopped", id);
+    }
+    Ok(())
+}
@@ -1,42 +1,51 @@
-use comelit_client_rs::{ComelitClientError, DeviceStatus, HomeDeviceData, State};
+use comelit_client_rs::{ComelitClientError, HomeDeviceData, LightDeviceData};
 
-use crate::{Params, utils::create_client};
+use crate::{
+    Params,
+    commands::listing::{DeviceType, ListOptions, device, devices, on_off},
+    utils::{ActionResult, login, print_json, table},
+};
 
 pub async fn toggle_light(params: Params, id: &str, toggle: &u8) -> Result<(), ComelitClientError> {
-    let client = create_client(params, None).await?;
-    if let Err(e) = client.login(State::Disconnected).await {
-        println!("Login failed: {}", e);
-        return Err(e);
+    let json = params.json;
+    let client = login(params, None).await?;
+    device(&client, id, DeviceType::Light).await?;
+    client.toggle_device_status(id, *toggle > 0).await?;
+    if json {
+        print_json(&ActionResult {
+            id,
+            action: "toggle",
+            value: Some((*toggle > 0).into()),
+        });
     } else {
-        println!("Login successful");
+        println!("Device {} status toggled", id);
     }
-    client.toggle_device_status(id, *toggle > 0).await?;
-    println!("Device {} status toggled", id);
     Ok(())
 }
 
-pub async fn list_lights(params: Params) -> Result<(), ComelitClientError> {
-    let client = create_client(params, None).await?;
-    if let Err(e) = client.login(State::Disconnected).await {
-        println!("Login failed: {}", e);
-        return Err(e);
-    } else {
-        println!("Login successful");
+pub async fn list_lights(params: Params, options: ListOptions) -> Result<(), ComelitClientError> {
+    let json = params.json;
+    let client = login(params, None).await?;
+    let lights: Vec<LightDeviceData> = devices(&client, Some(DeviceType::Light), &options)
+        .await?
+        .into_iter()
+        .filter_map(|device| match device {
+            HomeDeviceData::Light(light) => Some(light),
+            _ => None,
+        })
+        .collect();
+    if json {
+        print_json(&lights);
+        return Ok(());
     }
-    let devices = client.fetch_index(1).await?;
-    for (id, device_data) in devices {
-        if let HomeDeviceData::Light(light) = device_data {
-            println!(
-                "Light '{}' ({}) status: {}",
-                light.description.unwrap_or("Unknown".to_string()),
-                id,
-                if light.status.unwrap_or_default() == DeviceStatus::On {
-                    "on"
-                } else {
-                    "off"
-                }
-            );
-        }
+    let mut table = table(&["ID", "NAME", "STATUS"]);
+    for light in lights {
+        table.add_row([
+            light.id,
+            light.description.unwrap_or("Unknown".to_string()),
+            on_off(&light.status).to_string(),
+        ]);
     }
+    println!("{table}");
     Ok(())
 }
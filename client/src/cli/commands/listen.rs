@@ -6,13 +6,16 @@ use std::{
 
 use async_trait::async_trait;
 use comelit_client_rs::{
-    ActionType, ComelitClientError, DeviceStatus, HomeDeviceData, LightDeviceData, ROOT_ID, State,
+    ActionType, ComelitClientError, DeviceStatus, HomeDeviceData, LightDeviceData, ROOT_ID,
     StatusUpdate,
 };
 use crossterm::event::Event::Key;
 use crossterm::{event, terminal};
 
-use crate::{Params, utils::create_client};
+use crate::{
+    Params,
+    utils::{create_client, log_in},
+};
 
 #[derive(Default)]
 struct Updater {
@@ -46,7 +49,7 @@ impl Updater {
 pub async fn listen(params: Params) -> Result<(), ComelitClientError> {
     let updater = Arc::new(Updater::default());
     let client = create_client(params, Some(updater.clone())).await?;
-    if let Err(e) = client.login(State::Disconnected).await {
+    if let Err(e) = log_in(&client).await {
         println!("Login failed: {}", e);
         return Err(e);
     } else {
@@ -99,8 +102,8 @@ pub async fn listen(params: Params) -> Result<(), ComelitClientError> {
                             .lock()
                             .unwrap()
                             .clone()
-                            .into_iter()
-                            .filter_map(|(_, device)| match device {
+                            .into_values()
+                            .filter_map(|device| match device {
                                 HomeDeviceData::Light(l) => Some(l),
                                 _ => None,
                             })
@@ -163,6 +166,5 @@ pub async fn listen(params: Params) -> Result<(), ComelitClientError> {
         }
     }
     terminal::disable_raw_mode().unwrap();
-    client.disconnect().await?;
     Ok(())
 }
@@ -0,0 +1,178 @@
+use clap::{Args, ValueEnum};
+use comelit_client_rs::{
+    ComelitClient, ComelitClientError, DeviceStatus, HomeDeviceData, WindowCoveringStatus,
+};
+
+use crate::{
+    Params,
+    utils::{login, print_json, table},
+};
+
+// Options shared by the list commands, the doc comment would become their help
+#[derive(Args, Debug, Clone)]
+pub struct ListOptions {
+    /// Only show the devices whose name or id contains this text, ignoring case
+    #[arg(long)]
+    pub filter: Option<String>,
+    /// Column to sort the devices by
+    #[arg(long, value_enum, default_value_t = SortKey::Id)]
+    pub sort: SortKey,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Name,
+    Id,
+    Status,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceType {
+    Light,
+    Blind,
+    Outlet,
+    Thermo,
+    Irrigation,
+    Door,
+    Doorbell,
+    Other,
+}
+
+impl DeviceType {
+    fn of(device: &HomeDeviceData) -> DeviceType {
+        match device {
+            HomeDeviceData::Light(_) => DeviceType::Light,
+            HomeDeviceData::WindowCovering(_) => DeviceType::Blind,
+            HomeDeviceData::Outlet(_) => DeviceType::Outlet,
+            HomeDeviceData::Thermostat(_) => DeviceType::Thermo,
+            HomeDeviceData::Irrigation(_) => DeviceType::Irrigation,
+            HomeDeviceData::Door(_) => DeviceType::Door,
+            HomeDeviceData::Doorbell(_) => DeviceType::Doorbell,
+            HomeDeviceData::Agent(_)
+            | HomeDeviceData::Data(_)
+            | HomeDeviceData::Other(_)
+            | HomeDeviceData::Supplier(_) => DeviceType::Other,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            DeviceType::Light => "light",
+            DeviceType::Blind => "blind",
+            DeviceType::Outlet => "outlet",
+            DeviceType::Thermo => "thermo",
+            DeviceType::Irrigation => "irrigation",
+            DeviceType::Door => "door",
+            DeviceType::Doorbell => "doorbell",
+            DeviceType::Other => "other",
+        }
+    }
+}
+
+pub fn on_off(status: &Option<DeviceStatus>) -> &'static str {
+    match status.clone().unwrap_or_default() {
+        DeviceStatus::Off => "off",
+        DeviceStatus::On => "on",
+        DeviceStatus::Running => "running",
+    }
+}
+
+/// Status of a device as shown in the tables
+pub fn status(device: &HomeDeviceData) -> &'static str {
+    match device {
+        HomeDeviceData::Data(d) => on_off(&d.status),
+        HomeDeviceData::Other(d) => on_off(&d.data.status),
+        HomeDeviceData::Light(d) => on_off(&d.status),
+        HomeDeviceData::Outlet(d) => on_off(&d.data.status),
+        HomeDeviceData::Irrigation(d) => on_off(&d.data.status),
+        HomeDeviceData::Thermostat(d) => on_off(&d.status),
+        HomeDeviceData::Supplier(d) => on_off(&d.status),
+        HomeDeviceData::Doorbell(d) => on_off(&d.status),
+        HomeDeviceData::Door(d) => on_off(&d.status),
+        HomeDeviceData::WindowCovering(d) => match d.power_status.clone().unwrap_or_default() {
+            WindowCoveringStatus::Stopped => "stopped",
+            WindowCoveringStatus::GoingUp => "going up",
+            WindowCoveringStatus::GoingDown => "going down",
+        },
+        HomeDeviceData::Agent(_) => "-",
+    }
+}
+
+impl ListOptions {
+    fn matches(&self, device: &HomeDeviceData) -> bool {
+        let Some(filter) = &self.filter else {
+            return true;
+        };
+        let filter = filter.to_lowercase();
+        device.name().to_lowercase().contains(&filter)
+            || device.id().to_lowercase().contains(&filter)
+    }
+
+    fn sort(&self, devices: &mut [HomeDeviceData]) {
+        match self.sort {
+            SortKey::Name => devices.sort_by_cached_key(|d| (d.name().to_lowercase(), d.id())),
+            SortKey::Id => devices.sort_by_cached_key(|d| d.id()),
+            SortKey::Status => devices.sort_by_cached_key(|d| (status(d), d.id())),
+        }
+    }
+}
+
+/// Fetch the devices of the hub of the given type, filtered and sorted as requested
+pub async fn devices(
+    client: &ComelitClient,
+    device_type: Option<DeviceType>,
+    options: &ListOptions,
+) -> Result<Vec<HomeDeviceData>, ComelitClientError> {
+    let mut devices: Vec<HomeDeviceData> = client
+        .fetch_index(1)
+        .await?
+        .into_iter()
+        .map(|(_, device)| device)
+        .filter(|d| device_type.is_none_or(|t| DeviceType::of(d) == t))
+        .filter(|d| options.matches(d))
+        .collect();
+    options.sort(&mut devices);
+    Ok(devices)
+}
+
+/// Fetch the device of the given type with this id. The hub silently ignores
+/// the commands sent to unknown ids, so they are reported as not found.
+pub async fn device(
+    client: &ComelitClient,
+    id: &str,
+    device_type: DeviceType,
+) -> Result<HomeDeviceData, ComelitClientError> {
+    client
+        .fetch_index(1)
+        .await?
+        .into_iter()
+        .map(|(_, device)| device)
+        .find(|d| d.id() == id && DeviceType::of(d) == device_type)
+        .ok_or_else(|| ComelitClientError::NotFound(format!("{} {}", device_type.name(), id)))
+}
+
+/// List the devices of every type
+pub async fn list_devices(
+    params: Params,
+    device_type: Option<DeviceType>,
+    options: ListOptions,
+) -> Result<(), ComelitClientError> {
+    let json = params.json;
+    let client = login(params, None).await?;
+    let devices = devices(&client, device_type, &options).await?;
+    if json {
+        print_json(&devices);
+        return Ok(());
+    }
+    let mut table = table(&["ID", "TYPE", "NAME", "STATUS"]);
+    for device in &devices {
+        table.add_row([
+            device.id(),
+            DeviceType::of(device).name().to_string(),
+            device.name(),
+            status(device).to_string(),
+        ]);
+    }
+    println!("{table}");
+    Ok(())
+}
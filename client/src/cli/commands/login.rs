@@ -0,0 +1,41 @@
+use comelit_client_rs::ComelitClientError;
+
+use crate::{
+    Params, secrets,
+    utils::{ActionResult, login as hub_login, print_json},
+};
+
+/// Check the credentials, saving them in the OS keyring with `save`, with
+/// the token of the intercom and the address of its bridge if given
+pub async fn login(
+    params: Params,
+    save: bool,
+    viper: Option<(String, String)>,
+) -> Result<(), ComelitClientError> {
+    let json = params.json;
+    let (user, host, password) = (
+        params.user.clone(),
+        params.host.clone(),
+        params.password.clone(),
+    );
+    hub_login(params, None).await?;
+    let mut saved = false;
+    if save && let Some(password) = password {
+        secrets::save_password(user, host, password).await?;
+        saved = true;
+    }
+    if let Some((token, bridge)) = viper {
+        secrets::save_viper_token(bridge, token).await?;
+        saved = true;
+    }
+    if json {
+        print_json(&ActionResult {
+            id: "hub",
+            action: "login",
+            value: Some(saved.into()),
+        });
+    } else if saved {
+        println!("Credentials saved in the keyring");
+    }
+    Ok(())
+}
@@ -1,9 +1,29 @@
+mod batch;
+mod blinds;
 mod device_info;
+mod dump_index;
+mod irrigation;
 mod lights;
 mod listen;
+mod listing;
+mod login;
+mod outlets;
 mod scan;
+mod thermostat;
+mod watch;
 
+pub use batch::batch;
+pub use blinds::{list_blinds, move_blind, parse_position, stop_blind};
 pub use device_info::get_device_info;
+pub use dump_index::dump_index;
+pub use irrigation::{list_irrigation, start_irrigation, stop_irrigation};
 pub use lights::{list_lights, toggle_light};
 pub use listen::listen;
-pub use scan::scan;
+pub use listing::{DeviceType, ListOptions, list_devices};
+pub use login::login;
+pub use outlets::{list_outlets, outlet_power, toggle_outlet};
+pub use scan::{ScanOptions, scan};
+pub use thermostat::{
+    Mode, Season, get_thermostat, list_thermostats, set_mode, set_season, set_temperature,
+};
+pub use watch::watch;
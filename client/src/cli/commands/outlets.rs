@@ -0,0 +1,119 @@
+use comelit_client_rs::{ComelitClient, ComelitClientError, HomeDeviceData, OutletDeviceData};
+use comfy_table::CellAlignment;
+
+use crate::{
+    Params,
+    commands::listing::{DeviceType, ListOptions, device, devices, on_off},
+    utils::{ActionResult, login, print_json, table},
+};
+
+async fn outlets(client: &ComelitClient) -> Result<Vec<OutletDeviceData>, ComelitClientError> {
+    let devices = client.fetch_index(1).await?;
+    let mut outlets: Vec<OutletDeviceData> = devices
+        .into_iter()
+        .filter_map(|(_, device)| match device {
+            HomeDeviceData::Outlet(outlet) => Some(outlet),
+            _ => None,
+        })
+        .collect();
+    outlets.sort_by(|a, b| a.data.id.cmp(&b.data.id));
+    Ok(outlets)
+}
+
+fn watts(power: Option<f64>) -> String {
+    power
+        .map(|p| format!("{:.1} W", p))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// Print the outlets as a table with their state and instant power
+fn print_table(outlets: &[OutletDeviceData]) {
+    let mut table = table(&["ID", "NAME", "STATE", "POWER"]);
+    for outlet in outlets {
+        table.add_row([
+            outlet.data.id.clone(),
+            outlet
+                .data
+                .description
+                .clone()
+                .unwrap_or("Unknown".to_string()),
+            on_off(&outlet.data.status).to_string(),
+            watts(outlet.instant_power()),
+        ]);
+    }
+    if let Some(power) = table.column_mut(3) {
+        power.set_cell_alignment(CellAlignment::Right);
+    }
+    println!("{table}");
+}
+
+pub async fn list_outlets(params: Params, options: ListOptions) -> Result<(), ComelitClientError> {
+    let json = params.json;
+    let client = login(params, None).await?;
+    let outlets: Vec<OutletDeviceData> = devices(&client, Some(DeviceType::Outlet), &options)
+        .await?
+        .into_iter()
+        .filter_map(|device| match device {
+            HomeDeviceData::Outlet(outlet) => Some(outlet),
+            _ => None,
+        })
+        .collect();
+    if json {
+        print_json(&outlets);
+    } else {
+        print_table(&outlets);
+    }
+    Ok(())
+}
+
+pub async fn toggle_outlet(params: Params, id: &str, on: bool) -> Result<(), ComelitClientError> {
+    let json = params.json;
+    let client = login(params, None).await?;
+    device(&client, id, DeviceType::Outlet).await?;
+    client.toggle_device_status(id, on).await?;
+    if json {
+        print_json(&ActionResult {
+            id,
+            action: if on { "on" } else { "off" },
+            value: None,
+        });
+    } else {
+        println!("Outlet {} turned {}", id, if on { "on" } else { "off" });
+    }
+    Ok(())
+}
+
+/// Print the instant power of one outlet, or of all of them with their total
+pub async fn outlet_power(params: Params, id: Option<&str>) -> Result<(), ComelitClientError> {
+    let json = params.json;
+    let client = login(params, None).await?;
+    let outlets = outlets(&client).await?;
+    match id {
+        Some(id) => {
+            let power = outlets
+                .iter()
+                .find(|o| o.data.id == id)
+                .map(|o| o.instant_power())
+                .ok_or_else(|| ComelitClientError::NotFound(format!("outlet {}", id)))?;
+            if json {
+                print_json(&serde_json::json!({ "id": id, "instant_power": power }));
+            } else {
+                println!("{}", watts(power));
+            }
+        }
+        None => {
+            let total: f64 = outlets.iter().filter_map(|o| o.instant_power()).sum();
+            if json {
+                let outlets: Vec<_> = outlets
+                    .iter()
+                    .map(|o| serde_json::json!({ "id": o.data.id, "instant_power": o.instant_power() }))
+                    .collect();
+                print_json(&serde_json::json!({ "outlets": outlets, "total": total }));
+            } else {
+                print_table(&outlets);
+                println!("Total: {}", watts(Some(total)));
+            }
+        }
+    }
+    Ok(())
+}
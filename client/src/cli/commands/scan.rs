@@ -1,26 +1,77 @@
-use std::time::Duration;
+use std::{net::IpAddr, time::Duration};
 
-use comelit_client_rs::{ComelitClientError, Scanner};
+use comelit_client_rs::{ComelitClientError, ComelitHUB, Scanner};
+use serde::Serialize;
 
-use crate::Params;
+use crate::{
+    Params,
+    utils::{print_json, table},
+};
 
-pub async fn scan(params: Params) -> Result<(), ComelitClientError> {
-    if let Some(host) = params.host {
-        let hub = Scanner::scan_address(host.as_str(), Some(Duration::from_secs(5)))
+/// Options of the `scan` command
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    /// Only keep the devices whose model id or name contains this text
+    pub model: Option<String>,
+    pub timeout: Duration,
+    /// Local address of the network interface to scan from
+    pub interface: Option<IpAddr>,
+}
+
+/// A device found by the scan, with the name of its model
+#[derive(Serialize, Debug)]
+struct Found<'a> {
+    #[serde(flatten)]
+    hub: &'a ComelitHUB,
+    model: &'a str,
+}
+
+fn matches_model(hub: &ComelitHUB, model: &str) -> bool {
+    let model = model.to_lowercase();
+    hub.model_id().to_lowercase().contains(&model) || hub.model().to_lowercase().contains(&model)
+}
+
+pub async fn scan(params: Params, options: ScanOptions) -> Result<(), ComelitClientError> {
+    let mut hubs = match &params.host {
+        Some(host) => Scanner::scan_address(host.as_str(), Some(options.timeout))
             .await
-            .map_err(|e| ComelitClientError::Scanner(e.to_string()))?;
-        if let Some(hub) = hub {
-            println!("Found hub: {:?}", hub);
-        } else {
-            println!("No hub found at {}", host);
+            .map(|hub| hub.into_iter().collect()),
+        None => match options.interface {
+            Some(interface) => Scanner::scan_interface(interface, Some(options.timeout)).await,
+            None => Scanner::scan(Some(options.timeout)).await,
+        },
+    }
+    .map_err(|e| ComelitClientError::Scanner(e.to_string()))?;
+    if let Some(model) = &options.model {
+        hubs.retain(|hub| matches_model(hub, model));
+    }
+
+    if params.json {
+        let found: Vec<Found> = hubs
+            .iter()
+            .map(|hub| Found {
+                hub,
+                model: hub.model(),
+            })
+            .collect();
+        print_json(&found);
+    } else if hubs.is_empty() {
+        match &params.host {
+            Some(host) => println!("No device found at {}", host),
+            None => println!("No device found"),
         }
     } else {
-        let hubs = Scanner::scan(Some(Duration::from_secs(5)))
-            .await
-            .map_err(|e| ComelitClientError::Scanner(e.to_string()))?;
-        for hub in hubs {
-            println!("Found hub: {:?}", hub);
+        let mut table = table(&["ADDRESS", "MAC", "MODEL", "FIRMWARE", "DESCRIPTION"]);
+        for hub in &hubs {
+            table.add_row([
+                hub.address().unwrap_or("-").to_string(),
+                hub.mac_address().to_string(),
+                hub.model().to_string(),
+                hub.app_version().to_string(),
+                hub.description().to_string(),
+            ]);
         }
+        println!("{table}");
     }
     Ok(())
 }
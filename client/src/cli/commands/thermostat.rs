@@ -0,0 +1,208 @@
+use clap::ValueEnum;
+use comelit_client_rs::{
+    ClimaMode, ComelitClient, ComelitClientError, DeviceStatus, HomeDeviceData, ThermoSeason,
+    ThermostatDeviceData,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Params,
+    commands::listing::{DeviceType, ListOptions, devices, on_off},
+    utils::{ActionResult, login, print_json, table},
+};
+
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    Auto,
+    Manual,
+}
+
+impl From<Mode> for ClimaMode {
+    fn from(value: Mode) -> Self {
+        match value {
+            Mode::Auto => ClimaMode::Auto,
+            Mode::Manual => ClimaMode::Manual,
+        }
+    }
+}
+
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Season {
+    Summer,
+    Winter,
+}
+
+impl From<Season> for ThermoSeason {
+    fn from(value: Season) -> Self {
+        match value {
+            Season::Summer => ThermoSeason::Summer,
+            Season::Winter => ThermoSeason::Winter,
+        }
+    }
+}
+
+async fn thermostats(
+    client: &ComelitClient,
+) -> Result<Vec<ThermostatDeviceData>, ComelitClientError> {
+    let devices = client.fetch_index(1).await?;
+    Ok(devices
+        .into_iter()
+        .filter_map(|(_, device)| match device {
+            HomeDeviceData::Thermostat(thermostat) => Some(thermostat),
+            _ => None,
+        })
+        .collect())
+}
+
+/// Temperatures are sent by the hub in tenths of degree
+fn celsius(value: &Option<String>) -> String {
+    value
+        .as_deref()
+        .and_then(|v| v.parse::<f32>().ok())
+        .map(|v| format!("{:.1}°C", v / 10.0))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+fn print_thermostat(thermostat: &ThermostatDeviceData) {
+    println!(
+        "Thermostat '{}' ({}) status: {}, temperature: {}, target: {}, mode: {:?}, season: {:?}",
+        thermostat
+            .description
+            .clone()
+            .unwrap_or("Unknown".to_string()),
+        thermostat.id,
+        if thermostat.status.clone().unwrap_or_default() == DeviceStatus::On {
+            "on"
+        } else {
+            "off"
+        },
+        celsius(&thermostat.temperature),
+        celsius(&thermostat.active_threshold),
+        thermostat.auto_man.clone().unwrap_or_default(),
+        thermostat.season.clone().unwrap_or_default(),
+    );
+}
+
+pub async fn list_thermostats(
+    params: Params,
+    options: ListOptions,
+) -> Result<(), ComelitClientError> {
+    let json = params.json;
+    let client = login(params, None).await?;
+    let thermostats: Vec<ThermostatDeviceData> =
+        devices(&client, Some(DeviceType::Thermo), &options)
+            .await?
+            .into_iter()
+            .filter_map(|device| match device {
+                HomeDeviceData::Thermostat(thermostat) => Some(thermostat),
+                _ => None,
+            })
+            .collect();
+    if json {
+        print_json(&thermostats);
+        return Ok(());
+    }
+    let mut table = table(&[
+        "ID",
+        "NAME",
+        "STATUS",
+        "TEMPERATURE",
+        "TARGET",
+        "MODE",
+        "SEASON",
+    ]);
+    for thermostat in thermostats {
+        table.add_row([
+            thermostat.id.clone(),
+            thermostat
+                .description
+                .clone()
+                .unwrap_or("Unknown".to_string()),
+            on_off(&thermostat.status).to_string(),
+            celsius(&thermostat.temperature),
+            celsius(&thermostat.active_threshold),
+            format!("{:?}", thermostat.auto_man.clone().unwrap_or_default()),
+            format!("{:?}", thermostat.season.clone().unwrap_or_default()),
+        ]);
+    }
+    println!("{table}");
+    Ok(())
+}
+
+pub async fn get_thermostat(params: Params, id: &str) -> Result<(), ComelitClientError> {
+    let json = params.json;
+    let client = login(params, None).await?;
+    let thermostat = thermostats(&client).await?.into_iter().find(|t| t.id == id);
+    let thermostat =
+        thermostat.ok_or_else(|| ComelitClientError::NotFound(format!("thermostat {}", id)))?;
+    if json {
+        print_json(&thermostat);
+    } else {
+        print_thermostat(&thermostat);
+    }
+    Ok(())
+}
+
+pub async fn set_temperature(
+    params: Params,
+    id: &str,
+    temperature: f32,
+) -> Result<(), ComelitClientError> {
+    let json = params.json;
+    let client = login(params, None).await?;
+    client
+        .set_thermostat_temperature(id, (temperature * 10.0).round() as i32)
+        .await?;
+    if json {
+        print_json(&ActionResult {
+            id,
+            action: "set-temp",
+            value: Some(temperature.into()),
+        });
+    } else {
+        println!(
+            "Thermostat {} target temperature set to {:.1}°C",
+            id, temperature
+        );
+    }
+    Ok(())
+}
+
+pub async fn set_mode(params: Params, id: &str, mode: Mode) -> Result<(), ComelitClientError> {
+    let json = params.json;
+    let client = login(params, None).await?;
+    client.set_thermostat_mode(id, mode.into()).await?;
+    if json {
+        print_json(&ActionResult {
+            id,
+            action: "set-mode",
+            value: serde_json::to_value(mode).ok(),
+        });
+    } else {
+        println!("Thermostat {} mode set to {:?}", id, mode);
+    }
+    Ok(())
+}
+
+pub async fn set_season(
+    params: Params,
+    id: &str,
+    season: Season,
+) -> Result<(), ComelitClientError> {
+    let json = params.json;
+    let client = login(params, None).await?;
+    client.set_thermostat_season(id, season.into()).await?;
+    if json {
+        print_json(&ActionResult {
+            id,
+            action: "set-season",
+            value: serde_json::to_value(season).ok(),
+        });
+    } else {
+        println!("Thermostat {} season set to {:?}", id, season);
+    }
+    Ok(())
+}
@@ -0,0 +1,40 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use comelit_client_rs::{ComelitClientError, HomeDeviceData, ROOT_ID, StatusUpdate};
+
+use crate::{
+    Params,
+    utils::{create_client, log_in},
+};
+
+/// Prints each update pushed by the hub as one JSON line
+struct LinePrinter {
+    device: Option<String>,
+}
+
+#[async_trait]
+impl StatusUpdate for LinePrinter {
+    async fn status_update(&self, device: &HomeDeviceData) {
+        if self.device.as_ref().is_some_and(|id| *id != device.id()) {
+            return;
+        }
+        if let Ok(line) = serde_json::to_string(device) {
+            println!("{}", line);
+        }
+    }
+}
+
+/// Stream the updates of the hub as NDJSON until interrupted. Nothing else is
+/// written to stdout, so the output can be piped as is.
+pub async fn watch(params: Params, device: Option<String>) -> Result<(), ComelitClientError> {
+    let client = create_client(params, Some(Arc::new(LinePrinter { device }))).await?;
+    log_in(&client).await?;
+    client.subscribe(ROOT_ID).await?;
+    eprintln!("Watching the hub updates, press Ctrl-C to stop");
+
+    tokio::signal::ctrl_c()
+        .await
+        .map_err(|e| ComelitClientError::Generic(e.to_string()))?;
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use comelit_client_rs::ComelitClientError;
+use serde::Deserialize;
+use toml_edit::{DocumentMut, Item, Table, value};
+
+/// Connection options read from the configuration file, used for the ones
+/// not given on the command line or in the `COMELIT_*` variables:
+///
+/// ```toml
+/// user = "admin"
+/// password = "admin"
+/// host = "192.168.1.10"
+/// port = 1883
+///
+/// # Names accepted instead of the device ids, set with `alias set`
+/// [aliases]
+/// kitchen = "DOM#LT#19.2"
+/// ```
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub user: Option<String>,
+    pub password: Option<String>,
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub aliases: BTreeMap<String, String>,
+}
+
+impl Config {
+    /// $XDG_CONFIG_HOME/comelit/config.toml, or ~/.config/comelit/config.toml
+    pub fn default_path() -> Option<PathBuf> {
+        let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+        Some(config_dir.join("comelit").join("config.toml"))
+    }
+
+    /// Read the file, a missing file being an empty configuration
+    pub fn load(path: &Path) -> Result<Config, ComelitClientError> {
+        match fs::read_to_string(path) {
+            Ok(content) => toml::from_str(&content).map_err(|e| {
+                ComelitClientError::Generic(format!("Invalid {}: {}", path.display(), e))
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(ComelitClientError::Generic(format!(
+                "Cannot read {}: {}",
+                path.display(),
+                e
+            ))),
+        }
+    }
+
+    /// Set the alias of a device id in the file, or remove it without an id.
+    /// The rest of the file is kept as is, comments included.
+    pub fn set_alias(path: &Path, alias: &str, id: Option<&str>) -> Result<(), ComelitClientError> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => {
+                return Err(ComelitClientError::Generic(format!(
+                    "Cannot read {}: {}",
+                    path.display(),
+                    e
+                )));
+            }
+        };
+        let mut document: DocumentMut = content.parse().map_err(|e| {
+            ComelitClientError::Generic(format!("Invalid {}: {}", path.display(), e))
+        })?;
+        let aliases = document
+            .entry("aliases")
+            .or_insert_with(|| Item::Table(Table::new()))
+            .as_table_mut()
+            .ok_or_else(|| {
+                ComelitClientError::Generic(format!(
+                    "Invalid {}: aliases is not a table",
+                    path.display()
+                ))
+            })?;
+        match id {
+            Some(id) => {
+                aliases.insert(alias, value(id));
+            }
+            None => {
+                aliases.remove(alias);
+            }
+        }
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| {
+                ComelitClientError::Generic(format!("Cannot create {}: {}", dir.display(), e))
+            })?;
+        }
+        fs::write(path, document.to_string()).map_err(|e| {
+            ComelitClientError::Generic(format!("Cannot write {}: {}", path.display(), e))
+        })
+    }
+}
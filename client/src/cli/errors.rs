@@ -0,0 +1,126 @@
+use std::process::ExitCode;
+
+use comelit_client_rs::ComelitClientError;
+use serde::Serialize;
+
+/// Kind of failure of a command, each with its own exit code so that scripts
+/// can tell them apart. Invalid arguments exit with 2, as reported by clap.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    Failure,
+    Auth,
+    Unreachable,
+    NotFound,
+    Timeout,
+}
+
+impl ErrorKind {
+    pub fn of(error: &ComelitClientError) -> ErrorKind {
+        match error {
+            ComelitClientError::Login(_)
+            | ComelitClientError::InvalidToken
+            | ComelitClientError::InvalidState => ErrorKind::Auth,
+            ComelitClientError::Connection(_) => ErrorKind::Unreachable,
+            ComelitClientError::NotFound(_) => ErrorKind::NotFound,
+            ComelitClientError::Timeout => ErrorKind::Timeout,
+            // A scan fails on local errors, like the UDP socket not binding,
+            // not finding the hub is a connection error
+            ComelitClientError::Scanner(_)
+            | ComelitClientError::Generic(_)
+            | ComelitClientError::Publish(_)
+            | ComelitClientError::ReadError(_) => ErrorKind::Failure,
+        }
+    }
+
+    pub fn code(&self) -> u8 {
+        match self {
+            ErrorKind::Failure => 1,
+            ErrorKind::Auth => 3,
+            ErrorKind::Unreachable => 4,
+            ErrorKind::NotFound => 5,
+            ErrorKind::Timeout => 6,
+        }
+    }
+}
+
+/// Error printed on stderr with `--json-errors`
+#[derive(Serialize, Debug)]
+struct ErrorReport {
+    error: ErrorKind,
+    code: u8,
+    message: String,
+}
+
+/// Report the error on stderr, as text or as a JSON object, and return the
+/// exit code of its kind
+pub fn report(error: &ComelitClientError, json: bool) -> ExitCode {
+    let kind = ErrorKind::of(error);
+    if json {
+        let report = ErrorReport {
+            error: kind,
+            code: kind.code(),
+            message: error.to_string(),
+        };
+        eprintln!("{}", serde_json::to_string(&report).unwrap());
+    } else {
+        eprintln!("Error: {}", error);
+    }
+    ExitCode::from(kind.code())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kind_of() {
+        let kinds = [
+            (ComelitClientError::Login("denied".into()), ErrorKind::Auth),
+            (ComelitClientError::InvalidToken, ErrorKind::Auth),
+            (ComelitClientError::InvalidState, ErrorKind::Auth),
+            (
+                ComelitClientError::Connection("refused".into()),
+                ErrorKind::Unreachable,
+            ),
+            (
+                ComelitClientError::NotFound("light".into()),
+                ErrorKind::NotFound,
+            ),
+            (ComelitClientError::Timeout, ErrorKind::Timeout),
+            (
+                ComelitClientError::Scanner("bind".into()),
+                ErrorKind::Failure,
+            ),
+            (
+                ComelitClientError::Generic("failed".into()),
+                ErrorKind::Failure,
+            ),
+            (
+                ComelitClientError::Publish("failed".into()),
+                ErrorKind::Failure,
+            ),
+            (
+                ComelitClientError::ReadError("failed".into()),
+                ErrorKind::Failure,
+            ),
+        ];
+        for (error, kind) in kinds {
+            assert_eq!(ErrorKind::of(&error), kind, "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_codes() {
+        let codes = [
+            ErrorKind::Failure,
+            ErrorKind::Auth,
+            ErrorKind::Unreachable,
+            ErrorKind::NotFound,
+            ErrorKind::Timeout,
+        ]
+        .map(|kind| kind.code());
+        // 2 is left to clap for the invalid arguments
+        assert_eq!(codes, [1, 3, 4, 5, 6]);
+    }
+}
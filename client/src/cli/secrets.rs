@@ -0,0 +1,60 @@
+use comelit_client_rs::ComelitClientError;
+use keyring::Entry;
+
+/// Keyring service of the hub passwords
+const SERVICE: &str = "comelit-hub-cli";
+/// Keyring service of the tokens of the VIP intercom, also read by the `viper` CLI
+const VIPER_SERVICE: &str = "viper";
+/// Port of the ICONA bridge, when the address saved with the token has none
+const ICONA_BRIDGE_PORT: u16 = 64100;
+
+/// Passwords are saved by user and hub address, the hub found on the network
+/// being "default"
+fn password_entry(user: Option<&str>, host: Option<&str>) -> Result<Entry, keyring::Error> {
+    let account = format!("{}@{}", user.unwrap_or_default(), host.unwrap_or("default"));
+    Entry::new(SERVICE, &account)
+}
+
+fn keyring_error(e: keyring::Error) -> ComelitClientError {
+    ComelitClientError::Generic(format!("Keyring error: {}", e))
+}
+
+/// Password saved with `login --save`, if any. The keyring is accessed from
+/// a blocking task, since the platform stores may block.
+pub async fn load_password(user: Option<String>, host: Option<String>) -> Option<String> {
+    tokio::task::spawn_blocking(move || {
+        password_entry(user.as_deref(), host.as_deref())
+            .and_then(|entry| entry.get_password())
+            .ok()
+    })
+    .await
+    .ok()
+    .flatten()
+}
+
+pub async fn save_password(
+    user: Option<String>,
+    host: Option<String>,
+    password: String,
+) -> Result<(), ComelitClientError> {
+    tokio::task::spawn_blocking(move || {
+        password_entry(user.as_deref(), host.as_deref())?.set_password(&password)
+    })
+    .await
+    .map_err(|e| ComelitClientError::Generic(e.to_string()))?
+    .map_err(keyring_error)
+}
+
+/// Tokens are saved by bridge address (ip:port), like in the credentials
+/// file of the `viper` CLI
+pub async fn save_viper_token(bridge: String, token: String) -> Result<(), ComelitClientError> {
+    let account = if bridge.contains(':') {
+        format!("token@{}", bridge)
+    } else {
+        format!("token@{}:{}", bridge, ICONA_BRIDGE_PORT)
+    };
+    tokio::task::spawn_blocking(move || Entry::new(VIPER_SERVICE, &account)?.set_password(&token))
+        .await
+        .map_err(|e| ComelitClientError::Generic(e.to_string()))?
+        .map_err(keyring_error)
+}
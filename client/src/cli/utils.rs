@@ -1,9 +1,53 @@
 use comelit_client_rs::{
-    ComelitClient, ComelitClientError, ComelitObserver, ComelitOptions, get_secrets,
+    ComelitClient, ComelitClientError, ComelitObserver, ComelitOptions, State, get_secrets,
 };
+use comfy_table::{Table, presets};
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+use std::sync::{Arc, Mutex};
 
 use crate::Params;
 
+/// Clients created while running a command, all disconnected by `main` once the
+/// command completes, whether it succeeded or not
+#[derive(Clone, Default)]
+pub struct Connections(Arc<Mutex<Vec<ComelitClient>>>);
+
+impl Connections {
+    fn register(&self, client: &ComelitClient) {
+        if let Ok(mut clients) = self.0.lock() {
+            clients.push(client.clone());
+        }
+    }
+
+    /// Disconnect the clients, returning the first error
+    pub async fn disconnect_all(&self) -> Result<(), ComelitClientError> {
+        let clients = match self.0.lock() {
+            Ok(mut clients) => std::mem::take(&mut *clients),
+            Err(_) => Vec::new(),
+        };
+        let mut result = Ok(());
+        for client in clients {
+            let disconnected = client.disconnect().await;
+            result = result.and(disconnected);
+        }
+        result
+    }
+}
+
+impl fmt::Debug for Connections {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let count = self
+            .0
+            .lock()
+            .map(|clients| clients.len())
+            .unwrap_or_default();
+        f.debug_tuple("Connections").field(&count).finish()
+    }
+}
+
+/// Create the client, disconnected by `main` when the command completes
 pub async fn create_client(
     params: Params,
     observer: Option<ComelitObserver>,
@@ -18,5 +62,55 @@ pub async fn create_client(
         .host(params.host)
         .build()
         .map_err(|e| ComelitClientError::Generic(e.to_string()))?;
-    ComelitClient::new(options, observer).await
+    let client = ComelitClient::new(options, observer).await?;
+    params.connections.register(&client);
+    Ok(client)
+}
+
+/// Create the client and log in to the hub. The success is only printed
+/// without `--json`, so that the output stays valid JSON; a failure is
+/// reported by `main` with its exit code.
+pub async fn login(
+    params: Params,
+    observer: Option<ComelitObserver>,
+) -> Result<ComelitClient, ComelitClientError> {
+    let json = params.json;
+    let client = create_client(params, observer).await?;
+    log_in(&client).await?;
+    if !json {
+        println!("Login successful");
+    }
+    Ok(client)
+}
+
+/// Log the client in. The hub not answering the login is reported as
+/// unreachable, like a failed connection, rather than as a timed out request.
+pub async fn log_in(client: &ComelitClient) -> Result<(), ComelitClientError> {
+    match client.login(State::Disconnected).await {
+        Ok(_) => Ok(()),
+        Err(ComelitClientError::Timeout) => Err(ComelitClientError::Connection(
+            "the hub did not answer the login".to_string(),
+        )),
+        Err(e) => Err(e),
+    }
+}
+
+/// Outcome of a command acting on a device, printed with `--json`
+#[derive(Serialize, Debug)]
+pub struct ActionResult<'a> {
+    pub id: &'a str,
+    pub action: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<Value>,
+}
+
+pub fn print_json<T: Serialize + ?Sized>(value: &T) {
+    println!("{}", serde_json::to_string_pretty(value).unwrap());
+}
+
+/// Table with the given header, with the columns aligned and no borders
+pub fn table(header: &[&str]) -> Table {
+    let mut table = Table::new();
+    table.load_preset(presets::NOTHING).set_header(header);
+    table
 }
@@ -3,4 +3,4 @@ mod protocol;
 pub use protocol::client::*;
 pub use protocol::credentials::get_secrets;
 pub use protocol::out_data_messages::*;
-pub use protocol::scanner::{MacAddress, Scanner};
+pub use protocol::scanner::{ComelitHUB, MacAddress, Scanner};
@@ -7,7 +7,7 @@ use crate::protocol::messages::{
 };
 use crate::protocol::out_data_messages::{
     ActionType, AgentDeviceData, ClimaMode, ClimaOnOff, HomeDeviceData, ThermoSeason,
-    device_data_to_home_device,
+    device_data_to_home_device, device_rooms,
 };
 use crate::protocol::scanner::{ComelitHUB, SCAN_PORT, Scanner};
 use async_trait::async_trait;
@@ -17,6 +17,7 @@ use mac_address::get_mac_address;
 use rumqttc::{
     AsyncClient, ConnectionError, Event, EventLoop, MqttOptions, Packet, QoS, StateError,
 };
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU32, Ordering};
 use std::time::Duration;
@@ -24,7 +25,7 @@ use thiserror::Error;
 use tokio::sync::{Mutex, RwLock};
 use tokio::task::JoinHandle;
 use tokio::time::{Instant, sleep};
-use tracing::{debug, error, info, warn};
+use tracing::{debug, error, info, instrument, warn};
 use uuid::Uuid;
 
 #[async_trait]
@@ -113,6 +114,10 @@ pub enum ComelitClientError {
     ReadError(String),
     #[error("Scanning local network failed: {0}")]
     Scanner(String),
+    #[error("Request timed out")]
+    Timeout,
+    #[error("Device not found: {0}")]
+    NotFound(String),
 }
 
 #[derive(Clone)]
@@ -134,6 +139,7 @@ struct Inner {
     req_id: Arc<AtomicU32>,
     session: Arc<RwLock<Option<Session>>>,
     mac_address: MacAddress,
+    hub: ComelitHUB,
     user: String,
     password: String,
     last_action: Arc<DashMap<String, Arc<Mutex<Instant>>>>,
@@ -168,7 +174,7 @@ impl ComelitOptions {
                 .await
                 .map_err(|e| ComelitClientError::Scanner(e.to_string()))?;
             if devices.is_empty() {
-                Err(ComelitClientError::Scanner(
+                Err(ComelitClientError::Connection(
                     "No Comelit HUB found".to_string(),
                 ))
             } else {
@@ -279,6 +285,7 @@ impl ComelitClient {
                     req_id,
                     session,
                     mac_address: hub.mac_address().clone(),
+                    hub,
                     user: options.user.unwrap_or_default(),
                     password: options.password.unwrap_or_default(),
                     last_action: Arc::new(DashMap::new()),
@@ -287,7 +294,7 @@ impl ComelitClient {
                 }),
             })
         } else {
-            Err(ComelitClientError::Scanner(
+            Err(ComelitClientError::Connection(
                 "No Comelit HUB found".to_string(),
             ))
         }
@@ -297,6 +304,11 @@ impl ComelitClient {
         &self.inner.mac_address
     }
 
+    /// Information about the hub as returned by the network scan.
+    pub fn hub(&self) -> &ComelitHUB {
+        &self.inner.hub
+    }
+
     pub async fn disconnect(&self) -> Result<(), ComelitClientError> {
         self.inner.request_manager.stop();
         self.inner
@@ -315,6 +327,7 @@ impl ComelitClient {
         Ok(())
     }
 
+    #[instrument(skip_all)]
     pub async fn login(&self, state: State) -> Result<JoinHandle<()>, ComelitClientError> {
         let mut state = state.clone();
         loop {
@@ -324,8 +337,7 @@ impl ComelitClient {
                     info!("Announcing the to HUB");
                     let response = self
                         .send_request(make_announce_message(make_id(&self.inner.req_id).await, 0))
-                        .await
-                        .map_err(|e| ComelitClientError::Generic(e.to_string()))?;
+                        .await?;
                     if response.req_result.unwrap_or_default() != 0 {
                         break Err(ComelitClientError::Login(format!(
                             "Announce failed: {}",
@@ -347,8 +359,7 @@ impl ComelitClient {
                             self.inner.password.as_str(),
                             agent_id,
                         ))
-                        .await
-                        .map_err(|e| ComelitClientError::Generic(e.to_string()))?;
+                        .await?;
                     if response.req_result.unwrap_or_default() != 0 {
                         break Err(ComelitClientError::Login(format!(
                             "Login failed: {}",
@@ -394,8 +405,7 @@ impl ComelitClient {
                 device_id,
                 detail_level,
             ))
-            .await
-            .map_err(|e| ComelitClientError::Generic(e.to_string()))?;
+            .await?;
         Ok(resp
             .out_data
             .iter()
@@ -415,8 +425,7 @@ impl ComelitClient {
                 session.1.as_str(),
                 device_id,
             ))
-            .await
-            .map_err(|e| ComelitClientError::Generic(e.to_string()))?;
+            .await?;
         Ok(())
     }
 
@@ -433,8 +442,7 @@ impl ComelitClient {
                 ROOT_ID,
                 level,
             ))
-            .await
-            .map_err(|e| ComelitClientError::Generic(e.to_string()))?;
+            .await?;
         let index = DashMap::new();
         for v in resp.out_data.iter() {
             debug!(
@@ -449,6 +457,28 @@ impl ComelitClient {
         Ok(index)
     }
 
+    /// Fetch the zone hierarchy and map each device id to the room it belongs to.
+    pub async fn fetch_rooms(
+        &self,
+        level: u8,
+    ) -> Result<HashMap<String, String>, ComelitClientError> {
+        let session = self.get_session().await?;
+        let resp = self
+            .send_request(make_status_message(
+                make_id(&self.inner.req_id).await,
+                session.0,
+                session.1.as_str(),
+                ROOT_ID,
+                level,
+            ))
+            .await?;
+        Ok(resp
+            .out_data
+            .iter()
+            .flat_map(|v| device_rooms(v, level))
+            .collect())
+    }
+
     pub async fn fetch_external_devices(
         &self,
     ) -> Result<DashMap<String, HomeDeviceData>, ComelitClientError> {
@@ -467,6 +497,7 @@ impl ComelitClient {
             .collect())
     }
 
+    #[instrument(skip(self))]
     pub async fn send_action(
         &self,
         device_id: &str,
@@ -517,23 +548,23 @@ impl ComelitClient {
                 ))
                 .await
                 .map(|_| ())
-                .map_err(|e| ComelitClientError::Generic(e.to_string()))
             }
-            Err(e) => Err(ComelitClientError::Generic(e.to_string())),
+            Err(e) => Err(e),
         }
     }
 
+    #[instrument(skip_all)]
     async fn re_login(&self, old_token: Option<&str>) -> Result<(), ComelitClientError> {
         let _guard = self.inner.relogin_lock.lock().await;
 
         // Skip if a concurrent call already refreshed the token
         {
             let session = self.inner.session.read().await;
-            if let Some(ref s) = *session {
-                if Some(s.session_token.as_str()) != old_token {
-                    info!("Session already refreshed by concurrent re-login, skipping");
-                    return Ok(());
-                }
+            if let Some(ref s) = *session
+                && Some(s.session_token.as_str()) != old_token
+            {
+                info!("Session already refreshed by concurrent re-login, skipping");
+                return Ok(());
             }
         }
 
@@ -541,8 +572,7 @@ impl ComelitClient {
 
         let announce_resp = self
             .send_request(make_announce_message(make_id(&self.inner.req_id).await, 0))
-            .await
-            .map_err(|e| ComelitClientError::Generic(e.to_string()))?;
+            .await?;
 
         let agent_data = serde_json::from_value::<AgentDeviceData>(
             announce_resp
@@ -561,8 +591,7 @@ impl ComelitClient {
                 self.inner.password.as_str(),
                 agent_id,
             ))
-            .await
-            .map_err(|e| ComelitClientError::Generic(e.to_string()))?;
+            .await?;
 
         let new_token = login_resp
             .session_token
@@ -586,6 +615,7 @@ impl ComelitClient {
             .await
     }
 
+    /// The hub only moves a blind all the way: any position above 0 opens it
     pub async fn toggle_blind_position(
         &self,
         id: &str,
@@ -833,6 +863,7 @@ impl ComelitClient {
     /// Send a request and wait for the response
     /// In case of invalid token, it will try to reconnect and send the request again
     /// If the reconnection fails, it will return an error
+    #[instrument(skip_all, fields(seq_id = payload.seq_id))]
     async fn send_request(
         &self,
         payload: MqttMessage,
@@ -859,7 +890,7 @@ impl ComelitClient {
         tokio::select! {
             _ = sleep(Duration::from_secs(5)) => {
                 error!("Request timed out");
-                Err(ComelitClientError::ReadError("Request timed out".to_string()))
+                Err(ComelitClientError::Timeout)
             }
             res = response_receiver => {
                 match res {
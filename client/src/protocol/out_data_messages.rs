@@ -1,9 +1,13 @@
+use std::collections::HashMap;
 use std::fmt::Display;
+use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use tracing::debug;
 
+use crate::protocol::client::ROOT_ID;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(into = "i32", from = "i32")]
 pub enum ObjectType {
@@ -582,10 +586,22 @@ pub struct DeviceData {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OtherDeviceData {
     #[serde(flatten)]
-    data: DeviceData,
+    pub data: DeviceData,
+    #[serde(default)]
     tempo_uscita: String,
 }
 
+impl OtherDeviceData {
+    /// Activation time of temporized outputs (`OtherTmp`), if configured on the hub.
+    pub fn output_time(&self) -> Option<Duration> {
+        self.tempo_uscita
+            .parse::<u64>()
+            .ok()
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LightDeviceData {
     pub id: String,
@@ -623,15 +639,29 @@ pub struct WindowCoveringDeviceData {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OutletDeviceData {
     #[serde(flatten)]
-    data: DeviceData,
+    pub data: DeviceData,
+    #[serde(default)]
     instant_power: String,
+    #[serde(default)]
     out_power: u16,
 }
 
+impl OutletDeviceData {
+    /// Power drawn by the load on the outlet, in watts.
+    pub fn instant_power(&self) -> Option<f64> {
+        self.instant_power.parse::<f64>().ok()
+    }
+
+    /// Power limit of the outlet, in watts, if configured on the hub.
+    pub fn out_power(&self) -> Option<u16> {
+        Some(self.out_power).filter(|power| *power > 0)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IrrigationDeviceData {
     #[serde(flatten)]
-    data: DeviceData,
+    pub data: DeviceData,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -824,6 +854,49 @@ pub fn device_data_to_home_device(value: Value, level: u8) -> Vec<HomeDeviceData
     }
 }
 
+/// Map every device nested in a zone to the description of its innermost zone (its room).
+///
+/// The root zone is not considered a room, so devices placed directly under it are skipped.
+pub fn device_rooms(value: &Value, level: u8) -> HashMap<String, String> {
+    let mut rooms = HashMap::new();
+    collect_device_rooms(value, level, None, &mut rooms);
+    rooms
+}
+
+fn collect_device_rooms(
+    value: &Value,
+    level: u8,
+    room: Option<&str>,
+    rooms: &mut HashMap<String, String>,
+) {
+    let Ok(data) = serde_json::from_value::<DeviceData>(value.clone()) else {
+        return;
+    };
+    match data.r#type {
+        ObjectType::Zone => {
+            let room = if data.id == ROOT_ID {
+                room
+            } else {
+                data.description.as_deref().or(room)
+            };
+            for element in data.elements.iter() {
+                if level == 1 {
+                    if let Ok(inner) = serde_json::from_value::<InnerDeviceData>(element.clone()) {
+                        collect_device_rooms(&inner.data, level, room, rooms);
+                    }
+                } else {
+                    collect_device_rooms(element, level, room, rooms);
+                }
+            }
+        }
+        _ => {
+            if let Some(room) = room {
+                rooms.insert(data.id, room.to_string());
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -866,4 +939,85 @@ mod tests {
             assert_eq!(device_data.id, "GEN#17#13#1");
         })
     }
+
+    #[test]
+    fn parse_other_output_time() {
+        let json = serde_json::json!({
+            "id": "DOM#OT#5.1",
+            "type": 1,
+            "sub_type": 6,
+            "status": "0",
+            "descrizione": "Cancelletto",
+            "tempo_uscita": "5"
+        });
+        let devices = device_data_to_home_device(json, 1);
+        match devices.first() {
+            Some(HomeDeviceData::Other(other)) => {
+                assert_eq!(other.data.sub_type, ObjectSubtype::OtherTmp);
+                assert_eq!(other.output_time(), Some(Duration::from_secs(5)));
+            }
+            _ => panic!("Expected an Other device"),
+        }
+    }
+
+    #[test]
+    fn parse_outlet_power() {
+        let json = serde_json::json!({
+            "id": "DOM#LC#3.1",
+            "type": 10,
+            "sub_type": 5,
+            "status": "1",
+            "descrizione": "Lavatrice",
+            "instant_power": "1250.5",
+            "out_power": 0
+        });
+        let devices = device_data_to_home_device(json, 1);
+        match devices.first() {
+            Some(HomeDeviceData::Outlet(outlet)) => {
+                assert_eq!(outlet.data.status, Some(DeviceStatus::On));
+                assert_eq!(outlet.instant_power(), Some(1250.5));
+                assert_eq!(outlet.out_power(), None);
+            }
+            _ => panic!("Expected an Outlet device"),
+        }
+    }
+
+    #[test]
+    fn parse_device_rooms() {
+        let json = serde_json::json!({
+            "id": "GEN#17#13#1",
+            "type": 1001,
+            "sub_type": 13,
+            "descrizione": "root",
+            "elements": [{
+                "id": "GEN#17#13#2",
+                "data": {
+                    "id": "GEN#17#13#2",
+                    "type": 1001,
+                    "sub_type": 13,
+                    "descrizione": "Cucina",
+                    "elements": [{
+                        "id": "DOM#LT#1.1",
+                        "data": {
+                            "id": "DOM#LT#1.1",
+                            "type": 3,
+                            "sub_type": 16,
+                            "descrizione": "Luce soffitto"
+                        }
+                    }]
+                }
+            }, {
+                "id": "DOM#LT#1.2",
+                "data": {
+                    "id": "DOM#LT#1.2",
+                    "type": 3,
+                    "sub_type": 16,
+                    "descrizione": "Luce esterna"
+                }
+            }]
+        });
+        let rooms = device_rooms(&json, 1);
+        assert_eq!(rooms.len(), 1);
+        assert_eq!(rooms.get("DOM#LT#1.1").map(String::as_str), Some("Cucina"));
+    }
 }
@@ -1,4 +1,5 @@
-use std::net::UdpSocket;
+use serde::{Serialize, Serializer};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
 use std::time::Duration;
 use std::{fmt::Display, io};
 use tracing::{debug, error, info};
@@ -24,6 +25,12 @@ impl MacAddress {
     }
 }
 
+impl Serialize for MacAddress {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
 impl Display for MacAddress {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -34,7 +41,7 @@ impl Display for MacAddress {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 #[allow(dead_code)]
 pub struct ComelitHUB {
     mac_address: MacAddress,
@@ -122,7 +129,15 @@ pub struct Scanner;
 
 impl Scanner {
     pub async fn scan(timeout: Option<Duration>) -> Result<Vec<ComelitHUB>, std::io::Error> {
-        let socket = UdpSocket::bind("0.0.0.0:34254")?;
+        Self::scan_interface(IpAddr::V4(Ipv4Addr::UNSPECIFIED), timeout).await
+    }
+
+    /// Broadcast the scan from the network interface with this local address
+    pub async fn scan_interface(
+        local: IpAddr,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<ComelitHUB>, std::io::Error> {
+        let socket = UdpSocket::bind(SocketAddr::new(local, 34254))?;
 
         // Set the read timeout to 1 second
         socket.set_read_timeout(timeout)?;
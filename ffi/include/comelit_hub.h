@@ -0,0 +1,61 @@
+/* C API of the Comelit HUB client (comelit-hub-ffi). */
+#ifndef COMELIT_HUB_H
+#define COMELIT_HUB_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* The call succeeded. */
+#define COMELIT_OK 0
+/* A pointer argument was NULL or a string was not valid UTF-8. */
+#define COMELIT_ERR_INVALID_ARGUMENT -1
+/* The hub rejected the request or could not be reached. */
+#define COMELIT_ERR_CLIENT -2
+/* The state of the handle was left inconsistent by a panic in a previous call. */
+#define COMELIT_ERR_INTERNAL -3
+
+/* A connected client. */
+typedef struct ComelitHandle ComelitHandle;
+
+/* Receives the JSON of a device each time the hub reports an update. The string
+ * is only valid during the call, which runs on a thread of the client. */
+typedef void (*comelit_update_callback)(const char *device_json, void *user_data);
+
+/* Message of the last error on the calling thread, or NULL. Owned by the library,
+ * valid until the next call on this thread. */
+const char *comelit_last_error(void);
+
+/* Connect to a hub, NULL on failure. A NULL host discovers the hub on the local
+ * network, a port of 0 uses the default MQTT port. */
+ComelitHandle *comelit_connect(const char *host, uint16_t port, const char *user,
+                               const char *password);
+
+/* Log in with the credentials given to comelit_connect. */
+int32_t comelit_login(const ComelitHandle *handle);
+
+/* Devices of the hub as a JSON array, NULL on failure. Free with comelit_string_free. */
+char *comelit_list_devices(const ComelitHandle *handle);
+
+/* Turn a light or an outlet on or off. */
+int32_t comelit_toggle(const ComelitHandle *handle, const char *id, bool on);
+
+/* Subscribe to the updates of all the devices. Replaces the previous callback,
+ * a NULL callback stops the delivery. On failure no callback is registered. */
+int32_t comelit_subscribe(const ComelitHandle *handle, comelit_update_callback callback,
+                          void *user_data);
+
+/* Disconnect from the hub and free the handle. */
+void comelit_disconnect(ComelitHandle *handle);
+
+/* Free a string returned by the library. */
+void comelit_string_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* COMELIT_HUB_H */
@@ -0,0 +1,437 @@
+//! C ABI over the Comelit HUB client, to reuse the protocol implementation from
+//! C, C++ or Swift applications.
+//!
+//! A `ComelitHandle` owns a client and the runtime it runs on: every function
+//! blocks until the request completes. Devices are exchanged as JSON, in the same
+//! format as the serialized `HomeDeviceData` (e.g. `{"Light": {"id": ...}}`).
+//! Functions returning an `int32_t` return `COMELIT_OK` on success; on failure
+//! `comelit_last_error` describes what went wrong. The declarations are in
+//! `include/comelit_hub.h`.
+
+use async_trait::async_trait;
+use comelit_client_rs::{
+    ComelitClient, ComelitClientError, ComelitOptions, HomeDeviceData, ROOT_ID, State,
+    StatusUpdate, get_secrets,
+};
+use std::cell::RefCell;
+use std::ffi::{CStr, CString, c_char, c_void};
+use std::ptr;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use tokio::runtime::Runtime;
+use tokio::task::JoinHandle;
+
+/// The call succeeded.
+pub const COMELIT_OK: i32 = 0;
+/// A pointer argument was NULL or a string was not valid UTF-8.
+pub const COMELIT_ERR_INVALID_ARGUMENT: i32 = -1;
+/// The hub rejected the request or could not be reached.
+pub const COMELIT_ERR_CLIENT: i32 = -2;
+/// The state of the handle was left inconsistent by a panic in a previous call.
+pub const COMELIT_ERR_INTERNAL: i32 = -3;
+
+/// Callback receiving the JSON of a device each time the hub reports an update.
+///
+/// The string is only valid for the duration of the call. The callback runs on a
+/// thread of the client runtime, not on the thread that subscribed.
+pub type ComelitUpdateCallback =
+    Option<unsafe extern "C" fn(device_json: *const c_char, user_data: *mut c_void)>;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Record the error of the last failed call on this thread.
+fn set_last_error(message: impl ToString) {
+    let message = CString::new(message.to_string().replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+}
+
+/// Lock a mutex of the handle, failing instead of panicking when it is poisoned.
+fn lock<T>(mutex: &Mutex<T>) -> Result<MutexGuard<'_, T>, i32> {
+    mutex.lock().map_err(|_| {
+        set_last_error("Handle state poisoned by a previous panic");
+        COMELIT_ERR_INTERNAL
+    })
+}
+
+/// Registered update callback, with the opaque pointer passed back to it.
+#[derive(Clone, Copy)]
+struct Callback {
+    func: unsafe extern "C" fn(*const c_char, *mut c_void),
+    user_data: *mut c_void,
+}
+
+// The caller guarantees that `user_data` can be used from the client threads
+unsafe impl Send for Callback {}
+
+/// Forwards the status updates of the client to the registered callback.
+#[derive(Default)]
+struct CallbackObserver {
+    callback: Mutex<Option<Callback>>,
+}
+
+#[async_trait]
+impl StatusUpdate for CallbackObserver {
+    async fn status_update(&self, device: &HomeDeviceData) {
+        // Copied out of the lock, so the callback can subscribe again
+        let Ok(Some(callback)) = self.callback.lock().as_deref().copied() else {
+            return;
+        };
+        let Ok(json) = serde_json::to_string(device) else {
+            return;
+        };
+        if let Ok(json) = CString::new(json) {
+            unsafe { (callback.func)(json.as_ptr(), callback.user_data) };
+        }
+    }
+}
+
+/// A connected client, created by `comelit_connect`.
+pub struct ComelitHandle {
+    runtime: Runtime,
+    client: ComelitClient,
+    observer: Arc<CallbackObserver>,
+    ping_task: Mutex<Option<JoinHandle<()>>>,
+}
+
+/// Read an optional string argument, `Ok(None)` when the pointer is NULL.
+unsafe fn optional_str(s: *const c_char) -> Result<Option<String>, i32> {
+    if s.is_null() {
+        return Ok(None);
+    }
+    match unsafe { CStr::from_ptr(s) }.to_str() {
+        Ok(s) => Ok(Some(s.to_string())),
+        Err(e) => {
+            set_last_error(format!("Invalid string argument: {e}"));
+            Err(COMELIT_ERR_INVALID_ARGUMENT)
+        }
+    }
+}
+
+/// Read a required string argument.
+unsafe fn required_str(s: *const c_char, name: &str) -> Result<String, i32> {
+    match unsafe { optional_str(s) }? {
+        Some(s) => Ok(s),
+        None => {
+            set_last_error(format!("Missing argument: {name}"));
+            Err(COMELIT_ERR_INVALID_ARGUMENT)
+        }
+    }
+}
+
+/// Borrow the handle behind a pointer.
+unsafe fn handle<'a>(handle: *const ComelitHandle) -> Result<&'a ComelitHandle, i32> {
+    match unsafe { handle.as_ref() } {
+        Some(handle) => Ok(handle),
+        None => {
+            set_last_error("Missing argument: handle");
+            Err(COMELIT_ERR_INVALID_ARGUMENT)
+        }
+    }
+}
+
+/// Map the result of a client call to a return code.
+fn code(result: Result<(), ComelitClientError>) -> i32 {
+    match result {
+        Ok(()) => COMELIT_OK,
+        Err(e) => {
+            set_last_error(e);
+            COMELIT_ERR_CLIENT
+        }
+    }
+}
+
+/// Message of the last error that occurred on the calling thread, or NULL.
+///
+/// The string is owned by the library and valid until the next call on this thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn comelit_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+/// Connect to a hub, returning NULL on failure.
+///
+/// When `host` is NULL the hub is discovered on the local network; a `port` of 0
+/// uses the default MQTT port. Call `comelit_login` before any request.
+///
+/// # Safety
+///
+/// The string arguments must be NULL or valid NUL-terminated strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn comelit_connect(
+    host: *const c_char,
+    port: u16,
+    user: *const c_char,
+    password: *const c_char,
+) -> *mut ComelitHandle {
+    let args = unsafe {
+        (
+            optional_str(host),
+            optional_str(user),
+            optional_str(password),
+        )
+    };
+    let (Ok(host), Ok(user), Ok(password)) = args else {
+        return ptr::null_mut();
+    };
+    let runtime = match Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            set_last_error(format!("Failed to start the runtime: {e}"));
+            return ptr::null_mut();
+        }
+    };
+
+    let (mqtt_user, mqtt_password) = get_secrets();
+    let options = match ComelitOptions::builder()
+        .user(user)
+        .password(password)
+        .mqtt_user(mqtt_user)
+        .mqtt_password(mqtt_password)
+        .port((port != 0).then_some(port))
+        .host(host)
+        .build()
+    {
+        Ok(options) => options,
+        Err(e) => {
+            set_last_error(e);
+            return ptr::null_mut();
+        }
+    };
+    let observer = Arc::new(CallbackObserver::default());
+    let client = match runtime.block_on(ComelitClient::new(options, Some(observer.clone()))) {
+        Ok(client) => client,
+        Err(e) => {
+            set_last_error(e);
+            return ptr::null_mut();
+        }
+    };
+    Box::into_raw(Box::new(ComelitHandle {
+        runtime,
+        client,
+        observer,
+        ping_task: Mutex::new(None),
+    }))
+}
+
+/// Log in to the hub with the credentials given to `comelit_connect`.
+///
+/// # Safety
+///
+/// `handle` must be NULL or a handle returned by `comelit_connect`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn comelit_login(handle: *const ComelitHandle) -> i32 {
+    let handle = match unsafe { self::handle(handle) } {
+        Ok(handle) => handle,
+        Err(code) => return code,
+    };
+    let result = handle
+        .runtime
+        .block_on(handle.client.login(State::Disconnected));
+    let ping_task = match result {
+        Ok(ping_task) => ping_task,
+        Err(e) => return code(Err(e)),
+    };
+    match lock(&handle.ping_task) {
+        Ok(mut guard) => {
+            if let Some(previous) = guard.replace(ping_task) {
+                previous.abort();
+            }
+            COMELIT_OK
+        }
+        Err(code) => {
+            ping_task.abort();
+            code
+        }
+    }
+}
+
+/// List the devices of the hub as a JSON array, or NULL on failure.
+///
+/// Free the returned string with `comelit_string_free`.
+///
+/// # Safety
+///
+/// `handle` must be NULL or a handle returned by `comelit_connect`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn comelit_list_devices(handle: *const ComelitHandle) -> *mut c_char {
+    let Ok(handle) = (unsafe { self::handle(handle) }) else {
+        return ptr::null_mut();
+    };
+    let index = match handle.runtime.block_on(handle.client.fetch_index(1)) {
+        Ok(index) => index,
+        Err(e) => {
+            set_last_error(e);
+            return ptr::null_mut();
+        }
+    };
+    let mut devices: Vec<HomeDeviceData> = index.into_iter().map(|(_, d)| d).collect();
+    devices.sort_by_key(|d| d.id());
+    match serde_json::to_string(&devices).map(CString::new) {
+        Ok(Ok(json)) => json.into_raw(),
+        Ok(Err(e)) => {
+            set_last_error(e);
+            ptr::null_mut()
+        }
+        Err(e) => {
+            set_last_error(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Turn a light or an outlet on or off.
+///
+/// # Safety
+///
+/// `handle` must be NULL or a handle returned by `comelit_connect`, and `id` NULL
+/// or a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn comelit_toggle(
+    handle: *const ComelitHandle,
+    id: *const c_char,
+    on: bool,
+) -> i32 {
+    let (handle, id) = match unsafe { (self::handle(handle), required_str(id, "id")) } {
+        (Ok(handle), Ok(id)) => (handle, id),
+        (Err(code), _) | (_, Err(code)) => return code,
+    };
+    code(
+        handle
+            .runtime
+            .block_on(handle.client.toggle_device_status(&id, on)),
+    )
+}
+
+/// Subscribe to the updates of all the devices, delivered to `callback`.
+///
+/// Replaces the callback of a previous subscription; a NULL callback stops the
+/// delivery of the updates. When the subscription fails no callback is left
+/// registered.
+///
+/// # Safety
+///
+/// `handle` must be NULL or a handle returned by `comelit_connect`. `user_data` is
+/// passed back to the callback from other threads and must stay valid until the
+/// callback is replaced or the handle is freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn comelit_subscribe(
+    handle: *const ComelitHandle,
+    callback: ComelitUpdateCallback,
+    user_data: *mut c_void,
+) -> i32 {
+    let handle = match unsafe { self::handle(handle) } {
+        Ok(handle) => handle,
+        Err(code) => return code,
+    };
+    match lock(&handle.observer.callback) {
+        Ok(mut guard) => *guard = callback.map(|func| Callback { func, user_data }),
+        Err(code) => return code,
+    }
+    let result = handle.runtime.block_on(handle.client.subscribe(ROOT_ID));
+    if result.is_err() {
+        // The caller may free `user_data` as soon as the call fails
+        if let Ok(mut guard) = lock(&handle.observer.callback) {
+            guard.take();
+        }
+    }
+    code(result)
+}
+
+/// Disconnect from the hub and free the handle. NULL is ignored.
+///
+/// # Safety
+///
+/// `handle` must be NULL or a handle returned by `comelit_connect`, and must not
+/// be used after this call.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn comelit_disconnect(handle: *mut ComelitHandle) {
+    if handle.is_null() {
+        return;
+    }
+    let handle = unsafe { Box::from_raw(handle) };
+    // The handle is freed anyway, a poisoned lock still holds valid data
+    handle
+        .observer
+        .callback
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take();
+    if let Some(ping_task) = handle
+        .ping_task
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take()
+    {
+        ping_task.abort();
+    }
+    if let Err(e) = handle.runtime.block_on(handle.client.disconnect()) {
+        set_last_error(e);
+    }
+}
+
+/// Free a string returned by the library. NULL is ignored.
+///
+/// # Safety
+///
+/// `s` must be NULL or a string returned by the library, and not freed before.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn comelit_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use comelit_client_rs::{LightDeviceData, ObjectSubtype, ObjectType};
+
+    #[test]
+    fn test_invalid_arguments() {
+        assert_eq!(
+            unsafe { comelit_toggle(ptr::null(), c"DOM#LT#1.1".as_ptr(), true) },
+            COMELIT_ERR_INVALID_ARGUMENT
+        );
+        let error = unsafe { CStr::from_ptr(comelit_last_error()) };
+        assert_eq!(error.to_str().unwrap(), "Missing argument: handle");
+        assert!(unsafe { comelit_list_devices(ptr::null()) }.is_null());
+        // NULL is ignored
+        unsafe {
+            comelit_disconnect(ptr::null_mut());
+            comelit_string_free(ptr::null_mut());
+        }
+    }
+
+    unsafe extern "C" fn collect(device_json: *const c_char, user_data: *mut c_void) {
+        let updates = unsafe { &mut *(user_data as *mut Vec<String>) };
+        let json = unsafe { CStr::from_ptr(device_json) };
+        updates.push(json.to_str().unwrap().to_string());
+    }
+
+    #[tokio::test]
+    async fn test_callback() {
+        let observer = CallbackObserver::default();
+        let device = HomeDeviceData::Light(LightDeviceData {
+            id: "DOM#LT#1.1".to_string(),
+            r#type: ObjectType::Light,
+            sub_type: ObjectSubtype::DigitalLight,
+            status: None,
+            description: Some("Kitchen".to_string()),
+            power_status: None,
+        });
+        // Without a callback the updates are dropped
+        observer.status_update(&device).await;
+
+        let mut updates: Vec<String> = Vec::new();
+        *observer.callback.lock().unwrap() = Some(Callback {
+            func: collect,
+            user_data: &mut updates as *mut Vec<String> as *mut c_void,
+        });
+        observer.status_update(&device).await;
+        assert_eq!(updates.len(), 1);
+        let json: serde_json::Value = serde_json::from_str(&updates[0]).unwrap();
+        assert_eq!(json["Light"]["id"], "DOM#LT#1.1");
+        assert_eq!(json["Light"]["descrizione"], "Kitchen");
+    }
+}
@@ -0,0 +1,38 @@
+//! Embeds the git commit and the compiler version in the binary, for the
+//! `comelit_bridge_build_info` metric and the `/api/version` endpoint, and
+//! generates the gRPC service with the `grpc` feature.
+
+use std::path::Path;
+use std::process::Command;
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn main() {
+    // COMELIT_GIT_SHA can be set when building outside of a git checkout (e.g. in Docker)
+    let git_sha = std::env::var("COMELIT_GIT_SHA")
+        .ok()
+        .or_else(|| command_output("git", &["rev-parse", "--short", "HEAD"]))
+        .unwrap_or_else(|| "unknown".to_string());
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version =
+        command_output(&rustc, &["--version"]).unwrap_or_else(|| "unknown".to_string());
+
+    println!("cargo:rustc-env=COMELIT_GIT_SHA={git_sha}");
+    println!("cargo:rustc-env=COMELIT_RUSTC_VERSION={rustc_version}");
+    println!("cargo:rerun-if-env-changed=COMELIT_GIT_SHA");
+    for git_file in ["../.git/HEAD", "../.git/index"] {
+        if Path::new(git_file).exists() {
+            println!("cargo:rerun-if-changed={git_file}");
+        }
+    }
+
+    #[cfg(feature = "grpc")]
+    tonic_prost_build::compile_protos("proto/comelit.proto")
+        .expect("Failed to compile proto/comelit.proto (is protoc installed?)");
+}
@@ -23,8 +23,6 @@ echo $$ > "${PID_DIR}/comelit-hub-hap.pid"
 
 exec /usr/local/bin/comelit-hub-hap \
     --settings "$COMELIT_CONFIG" \
-    --user "$COMELIT_USER" \
-    --password "$COMELIT_PASSWORD" \
     --log-dir "$COMELIT_LOG_DIR" \
     --log-prefix "$COMELIT_LOG_PREFIX" \
     --log-rotation "$COMELIT_LOG_ROTATION" \
@@ -15,9 +15,10 @@ use serde_json::Value;
 use tracing::{info, warn};
 
 use crate::accessories::{
-    ComelitAccessory,
+    ComelitAccessory, Reachability,
     state::door::{DoorPositionState, DoorState, FULLY_CLOSED, FULLY_OPENED},
 };
+use crate::web::metrics::Metrics;
 
 #[allow(dead_code)]
 pub enum DoorType {
@@ -35,6 +36,7 @@ pub struct DoorConfig {
 pub(crate) struct ComelitDoorAccessory {
     id: String,
     state: Arc<Mutex<DoorState>>,
+    reachability: Reachability,
 }
 
 impl ComelitDoorAccessory {
@@ -94,7 +96,13 @@ impl ComelitDoorAccessory {
 
         let state = Arc::new(Mutex::new(state));
 
-        Self::setup_read_characteristics(&device_id, &mut door_accessory, state.clone());
+        let reachability = Reachability::default();
+        Self::setup_read_characteristics(
+            &device_id,
+            &mut door_accessory,
+            state.clone(),
+            reachability.clone(),
+        );
         Self::setup_update_target_position(
             &device_id,
             client.clone(),
@@ -108,18 +116,25 @@ impl ComelitDoorAccessory {
         Ok(Self {
             id: device_id,
             state,
+            reachability,
         })
     }
 
+    pub(crate) fn reachability(&self) -> &Reachability {
+        &self.reachability
+    }
+
     fn setup_read_characteristics(
         id: &str,
         accessory: &mut DoorAccessory,
         state: Arc<Mutex<DoorState>>,
+        reachability: Reachability,
     ) {
         let id_ = id.to_string();
         let state_ = state.clone();
         accessory.door.position_state.on_read(Some(move || {
             info!("Door POSITION STATE read {}", id_);
+            Metrics::inc_hap_read("door", &id_, "position_state");
             let state = state_.lock().unwrap();
             Ok(Some(state.position_state))
         }));
@@ -128,14 +143,18 @@ impl ComelitDoorAccessory {
         let state_ = state.clone();
         accessory.door.current_position.on_read(Some(move || {
             info!("Door CURRENT POSITION read {}", id_);
+            Metrics::inc_hap_read("door", &id_, "current_position");
             let state = state_.lock().unwrap();
-            Ok(Some(state.current_position))
+            reachability
+                .check(&id_)
+                .map(|()| Some(state.current_position))
         }));
 
         let id_ = id.to_string();
         let state_ = state.clone();
         accessory.door.target_position.on_read(Some(move || {
             info!("Door TARGET POSITION read {}", id_);
+            Metrics::inc_hap_read("door", &id_, "target_position");
             let state = state_.lock().unwrap();
             Ok(Some(state.target_position))
         }));
@@ -156,6 +175,7 @@ impl ComelitDoorAccessory {
             .door
             .target_position
             .on_update_async(Some(move |_, new_pos| {
+                Metrics::inc_hap_write("door", &id, "target_position");
                 // For blinds/shades/awnings, a value of 0 indicates a position that permits the least light and a value
                 // of 100 indicates a position that allows most light.
                 // This means:
@@ -89,6 +89,7 @@ struct State {
 
 pub(crate) struct ComelitDoorbellAccessory {
     pub(crate) id: String,
+    pub(crate) name: String,
     #[allow(dead_code)]
     pub(crate) accessory: Accessory,
     state: Arc<Mutex<State>>,
@@ -137,10 +138,32 @@ impl ComelitDoorbellAccessory {
 
         Ok(Self {
             id: device_id,
+            name,
             accessory,
             state,
         })
     }
+
+    /// Ring the doorbell, returning false when it already rang in the last 2 seconds.
+    /// The same call can be reported twice by the hub, or by both the hub and the intercom.
+    pub(crate) async fn trigger(&self) -> Result<bool> {
+        let mut state = self.state.lock().await;
+        let now = Instant::now();
+        if state.last_ring.map(|t| t.elapsed() < Duration::from_secs(2)).unwrap_or(false) {
+            return Ok(false);
+        }
+        state.last_ring = Some(now);
+        if let Some(accessory) = state.accessory.clone() {
+            drop(state);
+            ring(&self.id, accessory).await?;
+        }
+        Ok(true)
+    }
+
+    /// Time of the last ring forwarded to HomeKit (duplicates excluded).
+    pub(crate) async fn last_ring(&self) -> Option<Instant> {
+        self.state.lock().await.last_ring
+    }
 }
 
 /// Send a Single Press event on ProgrammableSwitchEvent.
@@ -165,17 +188,7 @@ impl ComelitAccessory<DoorbellDeviceData> for ComelitDoorbellAccessory {
     async fn update(&mut self, _data: &DoorbellDeviceData) -> Result<()> {
         // The Comelit hub pushes VIP events with status=Off on every ring — the status
         // value is not meaningful here. Treat any incoming update as a ring event.
-        // Deduplicate within 2 seconds to handle the hub sending the same event twice.
-        let mut state = self.state.lock().await;
-        let now = Instant::now();
-        if state.last_ring.map(|t| t.elapsed() < Duration::from_secs(2)).unwrap_or(false) {
-            return Ok(());
-        }
-        state.last_ring = Some(now);
-        if let Some(accessory) = state.accessory.clone() {
-            drop(state);
-            ring(&self.id, accessory).await?;
-        }
+        self.trigger().await?;
         Ok(())
     }
 }
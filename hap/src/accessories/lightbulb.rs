@@ -15,7 +15,9 @@ use tokio::sync::mpsc::{self, Sender};
 use tracing::{debug, info, warn};
 
 use crate::accessories::comelit_accessory::ComelitAccessory;
+use crate::accessories::reachability::Reachability;
 use crate::accessories::state::light::LightState;
+use crate::web::metrics::Metrics;
 use comelit_client_rs::{ComelitClient, DeviceStatus, LightDeviceData};
 
 #[derive(Debug)]
@@ -97,6 +99,7 @@ pub(crate) struct ComelitLightbulbAccessory {
     pub name: String,
     state: Arc<LightState>,
     command_sender: Sender<LightbulbCommand>,
+    reachability: Reachability,
     #[allow(dead_code)]
     accessory: Accessory,
 }
@@ -144,25 +147,30 @@ impl ComelitLightbulbAccessory {
             .await?;
 
         let (command_sender, command_receiver) = mpsc::channel::<LightbulbCommand>(16);
+        let reachability = Reachability::default();
 
         // Read callback: reads from atomic state — no lock required
         {
             let id_ = device_id.clone();
             let state_ = state.clone();
+            let reachability_ = reachability.clone();
             lightbulb_accessory.lightbulb.power_state.on_read(Some(move || {
                 let value = state_.on.load(Ordering::Acquire);
                 debug!("Lightbulb {} read: {}", id_, value);
-                Ok(Some(value))
+                Metrics::inc_hap_read("light", &id_, "power_state");
+                reachability_.check(&id_).map(|()| Some(value))
             }));
         }
 
         // Write callback: only sends to worker channel; returns immediately
         {
             let tx = command_sender.clone();
+            let id_ = device_id.clone();
             lightbulb_accessory
                 .lightbulb
                 .power_state
                 .on_update_async(Some(move |_current_val: bool, new_val: bool| {
+                    Metrics::inc_hap_write("light", &id_, "power_state");
                     let tx = tx.clone();
                     async move {
                         if let Err(e) = tx.send(LightbulbCommand::HapWrite(new_val)).await {
@@ -189,9 +197,14 @@ impl ComelitLightbulbAccessory {
             name,
             state,
             command_sender,
+            reachability,
             accessory,
         })
     }
+
+    pub(crate) fn reachability(&self) -> &Reachability {
+        &self.reachability
+    }
 }
 
 impl ComelitAccessory<LightDeviceData> for ComelitLightbulbAccessory {
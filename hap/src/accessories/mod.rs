@@ -3,7 +3,9 @@ mod comelit_accessory;
 mod door;
 mod doorbell;
 mod lightbulb;
+mod reachability;
 mod state;
+mod switch;
 mod thermostat;
 mod window_covering;
 
@@ -11,6 +13,8 @@ pub(crate) use comelit_accessory::ComelitAccessory;
 pub(crate) use door::*;
 pub(crate) use doorbell::ComelitDoorbellAccessory;
 pub(crate) use lightbulb::ComelitLightbulbAccessory;
+pub(crate) use reachability::Reachability;
+pub(crate) use switch::ComelitSwitchAccessory;
 pub(crate) use thermostat::ComelitThermostatAccessory;
 pub(crate) use window_covering::ComelitWindowCoveringAccessory;
 pub(crate) use window_covering::WindowCoveringConfig;
@@ -0,0 +1,33 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Error returned by HAP read callbacks while a device is in fault.
+pub(crate) type ReadError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Shared fault flag of a Comelit device.
+///
+/// While the flag is set, the read callbacks of the accessory fail, so the Home app
+/// shows the accessory as "Not Responding" instead of frozen values.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Reachability {
+    fault: Arc<AtomicBool>,
+}
+
+impl Reachability {
+    pub(crate) fn set_fault(&self, fault: bool) {
+        self.fault.store(fault, Ordering::Release);
+    }
+
+    pub(crate) fn is_fault(&self) -> bool {
+        self.fault.load(Ordering::Acquire)
+    }
+
+    /// Fails with a read error when the device is in fault.
+    pub(crate) fn check(&self, id: &str) -> Result<(), ReadError> {
+        if self.is_fault() {
+            Err(format!("Device {id} is not responding").into())
+        } else {
+            Ok(())
+        }
+    }
+}
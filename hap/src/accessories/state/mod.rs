@@ -1,4 +1,5 @@
 pub(crate) mod door;
 pub(crate) mod light;
+pub(crate) mod switch;
 pub(crate) mod thermostat;
 pub(crate) mod window_covering;
@@ -0,0 +1,22 @@
+use std::sync::atomic::AtomicBool;
+
+use comelit_client_rs::{DeviceStatus, OtherDeviceData};
+
+#[derive(Debug)]
+pub(crate) struct SwitchState {
+    pub(crate) on: AtomicBool,
+}
+
+impl From<&OtherDeviceData> for SwitchState {
+    fn from(data: &OtherDeviceData) -> Self {
+        // Outputs like pumps report Running while active
+        let on = matches!(
+            data.data.status.clone().unwrap_or_default(),
+            DeviceStatus::On | DeviceStatus::Running
+        );
+
+        Self {
+            on: AtomicBool::new(on),
+        }
+    }
+}
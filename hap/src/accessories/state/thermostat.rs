@@ -46,21 +46,16 @@ impl From<&ThermostatDeviceData> for ThermostatState {
             .unwrap_or_default();
 
         let auto_man = data.auto_man.clone().unwrap_or_default();
-        let is_off = auto_man == ClimaMode::OffAuto || auto_man == ClimaMode::OffManual;
-        let is_auto = auto_man == ClimaMode::Auto;
-        let is_winter = data.season.clone().unwrap_or_default() == ThermoSeason::Winter;
+        let season = data.season.clone().unwrap_or_default();
 
-        let heating_cooling_state = if is_off {
-            TargetHeatingCoolingState::Off
-        } else if is_winter {
-            TargetHeatingCoolingState::Heat
-        } else if is_auto {
-            TargetHeatingCoolingState::Auto
-        } else {
-            TargetHeatingCoolingState::Cool
-        };
-
-        let target_heating_cooling_state = heating_cooling_state;
+        let target_heating_cooling_state = target_heating_cooling_state(&auto_man, &season);
+        let heating_cooling_state = current_heating_cooling_state(
+            target_heating_cooling_state,
+            &season,
+            data.status.as_ref(),
+            temperature,
+            target_temperature,
+        );
 
         // Dehumidifier: active when auto_man_umi is not None/OffAuto/OffManual
         let auto_man_umi = data.auto_man_umi.clone().unwrap_or_default();
@@ -89,6 +84,54 @@ impl From<&ThermostatDeviceData> for ThermostatState {
     }
 }
 
+/// Maps the hub operating mode (`auto_man`) and season (`est_inv`) to the HomeKit target state.
+///
+/// Off modes map to Off, (semi-)automatic modes follow the hub schedule and map to Auto,
+/// manual modes map to Heat in winter and Cool in summer.
+fn target_heating_cooling_state(
+    auto_man: &ClimaMode,
+    season: &ThermoSeason,
+) -> TargetHeatingCoolingState {
+    match auto_man {
+        ClimaMode::None | ClimaMode::OffAuto | ClimaMode::OffManual => {
+            TargetHeatingCoolingState::Off
+        }
+        ClimaMode::Auto | ClimaMode::SemiAuto => TargetHeatingCoolingState::Auto,
+        ClimaMode::Manual | ClimaMode::SemiMan => match season {
+            ThermoSeason::Winter => TargetHeatingCoolingState::Heat,
+            ThermoSeason::Summer => TargetHeatingCoolingState::Cool,
+        },
+    }
+}
+
+/// Derives what the thermostat is currently doing (HomeKit only accepts Off, Heat or Cool).
+///
+/// The output status reported by the hub is used when present, otherwise the current
+/// temperature is compared with the active threshold (`soglia_attiva`).
+fn current_heating_cooling_state(
+    target: TargetHeatingCoolingState,
+    season: &ThermoSeason,
+    status: Option<&DeviceStatus>,
+    temperature: f32,
+    threshold: f32,
+) -> TargetHeatingCoolingState {
+    if target == TargetHeatingCoolingState::Off {
+        return TargetHeatingCoolingState::Off;
+    }
+    let active = match status {
+        Some(status) => matches!(status, DeviceStatus::On | DeviceStatus::Running),
+        None => match season {
+            ThermoSeason::Winter => temperature < threshold,
+            ThermoSeason::Summer => temperature > threshold,
+        },
+    };
+    match (active, season) {
+        (false, _) => TargetHeatingCoolingState::Off,
+        (true, ThermoSeason::Winter) => TargetHeatingCoolingState::Heat,
+        (true, ThermoSeason::Summer) => TargetHeatingCoolingState::Cool,
+    }
+}
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
 #[repr(u8)]
 pub enum TargetHeatingCoolingState {
@@ -116,3 +159,96 @@ impl From<TargetHeatingCoolingState> for u8 {
         value as u8
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::accessories::state::thermostat::{TargetHeatingCoolingState, ThermostatState};
+    use comelit_client_rs::ThermostatDeviceData;
+
+    fn thermostat(auto_man: &str, est_inv: &str, status: &str) -> ThermostatDeviceData {
+        serde_json::from_value(serde_json::json!({
+            "id": "DOM#CZ#1",
+            "type": 9,
+            "sub_type": 12,
+            "status": status,
+            "descrizione": "Soggiorno",
+            "temperatura": "205",
+            "auto_man": auto_man,
+            "est_inv": est_inv,
+            "soglia_attiva": "210",
+        }))
+        .unwrap()
+    }
+
+    fn states(auto_man: &str, est_inv: &str, status: &str) -> (u8, u8) {
+        let state = ThermostatState::from(&thermostat(auto_man, est_inv, status));
+        (
+            state.target_heating_cooling_state as u8,
+            state.heating_cooling_state as u8,
+        )
+    }
+
+    const OFF: u8 = TargetHeatingCoolingState::Off as u8;
+    const HEAT: u8 = TargetHeatingCoolingState::Heat as u8;
+    const COOL: u8 = TargetHeatingCoolingState::Cool as u8;
+    const AUTO: u8 = TargetHeatingCoolingState::Auto as u8;
+
+    #[test]
+    fn test_none_mode_is_off() {
+        assert_eq!(states("0", "1", "1"), (OFF, OFF));
+        assert_eq!(states("0", "0", "1"), (OFF, OFF));
+    }
+
+    #[test]
+    fn test_auto_mode() {
+        assert_eq!(states("1", "1", "1"), (AUTO, HEAT));
+        assert_eq!(states("1", "0", "1"), (AUTO, COOL));
+        assert_eq!(states("1", "1", "0"), (AUTO, OFF));
+    }
+
+    #[test]
+    fn test_manual_mode() {
+        assert_eq!(states("2", "1", "1"), (HEAT, HEAT));
+        assert_eq!(states("2", "0", "1"), (COOL, COOL));
+        assert_eq!(states("2", "1", "0"), (HEAT, OFF));
+    }
+
+    #[test]
+    fn test_semi_auto_mode() {
+        assert_eq!(states("3", "1", "1"), (AUTO, HEAT));
+        assert_eq!(states("3", "0", "0"), (AUTO, OFF));
+    }
+
+    #[test]
+    fn test_semi_manual_mode() {
+        assert_eq!(states("4", "1", "1"), (HEAT, HEAT));
+        assert_eq!(states("4", "0", "1"), (COOL, COOL));
+    }
+
+    #[test]
+    fn test_off_auto_mode() {
+        assert_eq!(states("5", "1", "1"), (OFF, OFF));
+        assert_eq!(states("5", "0", "1"), (OFF, OFF));
+    }
+
+    #[test]
+    fn test_off_manual_mode() {
+        assert_eq!(states("6", "1", "1"), (OFF, OFF));
+        assert_eq!(states("6", "0", "0"), (OFF, OFF));
+    }
+
+    #[test]
+    fn test_current_state_from_threshold_without_status() {
+        let mut data = thermostat("2", "1", "0");
+        data.status = None;
+        // 20.5°C with a 21.0°C threshold in winter: heating
+        let state = ThermostatState::from(&data);
+        assert_eq!(state.heating_cooling_state, TargetHeatingCoolingState::Heat);
+
+        let mut data = thermostat("2", "0", "0");
+        data.status = None;
+        // 20.5°C with a 21.0°C threshold in summer: idle
+        let state = ThermostatState::from(&data);
+        assert_eq!(state.heating_cooling_state, TargetHeatingCoolingState::Off);
+    }
+}
@@ -0,0 +1,261 @@
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use anyhow::Result;
+use futures::FutureExt;
+use hap::HapType;
+use hap::characteristic::{
+    AsyncCharacteristicCallbacks, CharacteristicCallbacks, HapCharacteristic,
+};
+use hap::{
+    accessory::{AccessoryInformation, switch::SwitchAccessory},
+    pointer::Accessory,
+    server::{IpServer, Server},
+};
+use serde_json::Value;
+use tokio::sync::mpsc::{self, Sender, WeakSender};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::accessories::comelit_accessory::ComelitAccessory;
+use crate::accessories::reachability::Reachability;
+use crate::accessories::state::switch::SwitchState;
+use crate::web::metrics::Metrics;
+use comelit_client_rs::{ComelitClient, DeviceStatus, ObjectSubtype, OtherDeviceData};
+
+#[derive(Debug)]
+enum SwitchCommand {
+    /// HomeKit wrote a new power state → forward to MQTT
+    HapWrite(bool),
+    /// Hub pushed a status update → update HAP characteristic
+    MqttPush(bool),
+    /// Initialise the accessory pointer inside the worker
+    SetAccessory(Accessory),
+}
+
+struct SwitchWorker {
+    id: String,
+    state: Arc<SwitchState>,
+    client: ComelitClient,
+    accessory: Option<Accessory>,
+    /// Activation time of temporized outputs, after which the hub turns them off
+    auto_off: Option<Duration>,
+    sender: WeakSender<SwitchCommand>,
+    auto_off_task: Option<JoinHandle<()>>,
+}
+
+impl SwitchWorker {
+    fn new(
+        id: String,
+        state: Arc<SwitchState>,
+        client: ComelitClient,
+        auto_off: Option<Duration>,
+        sender: WeakSender<SwitchCommand>,
+    ) -> Self {
+        Self {
+            id,
+            state,
+            client,
+            accessory: None,
+            auto_off,
+            sender,
+            auto_off_task: None,
+        }
+    }
+
+    /// Temporized outputs turn off by themselves on the hub, which does not always
+    /// push the new status: schedule the off state locally once the output time elapses.
+    fn schedule_auto_off(&mut self) {
+        if let Some(task) = self.auto_off_task.take() {
+            task.abort();
+        }
+        if let Some(duration) = self.auto_off {
+            let sender = self.sender.clone();
+            let id = self.id.clone();
+            self.auto_off_task = Some(tokio::spawn(async move {
+                tokio::time::sleep(duration).await;
+                if let Some(sender) = sender.upgrade() {
+                    debug!("Switch {id}: output time elapsed, turning off");
+                    sender.send(SwitchCommand::MqttPush(false)).await.ok();
+                }
+            }));
+        }
+    }
+
+    async fn run(mut self, mut rx: mpsc::Receiver<SwitchCommand>) {
+        while let Some(cmd) = rx.recv().await {
+            match cmd {
+                SwitchCommand::SetAccessory(acc) => {
+                    self.accessory = Some(acc);
+                }
+                SwitchCommand::HapWrite(new_val) => {
+                    let current = self.state.on.load(Ordering::Acquire);
+                    if new_val != current {
+                        if let Err(e) = self.client.toggle_device_status(&self.id, new_val).await {
+                            warn!("toggle_device_status for switch {} failed: {e}", self.id);
+                        } else {
+                            info!("Switch {}: power state set to {}", self.id, new_val);
+                            self.state.on.store(new_val, Ordering::Release);
+                            if new_val {
+                                self.schedule_auto_off();
+                            }
+                        }
+                    }
+                }
+                SwitchCommand::MqttPush(is_on) => {
+                    self.state.on.store(is_on, Ordering::Release);
+                    if is_on {
+                        self.schedule_auto_off();
+                    }
+                    if let Some(ref accessory) = self.accessory {
+                        let mut acc = accessory.lock().await;
+                        let service = acc.get_mut_service(HapType::Switch).unwrap();
+                        if let Some(ch) = service.get_mut_characteristic(HapType::PowerState)
+                            && let Err(e) = ch.update_value(Value::from(is_on)).await
+                        {
+                            warn!("update_value for switch {} failed: {e}", self.id);
+                        }
+                    }
+                    info!(
+                        "Updated power state for device {}: {}",
+                        self.id,
+                        if is_on { "On" } else { "Off" }
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Generic Comelit output ("Other" objects), exposed to HomeKit as a switch.
+pub(crate) struct ComelitSwitchAccessory {
+    id: String,
+    pub name: String,
+    state: Arc<SwitchState>,
+    command_sender: Sender<SwitchCommand>,
+    reachability: Reachability,
+    #[allow(dead_code)]
+    accessory: Accessory,
+}
+
+impl ComelitSwitchAccessory {
+    pub(crate) async fn new(
+        id: u64,
+        other_data: &OtherDeviceData,
+        client: ComelitClient,
+        server: &IpServer,
+    ) -> Result<Self> {
+        let device_id = other_data.data.id.clone();
+        let name = other_data
+            .data
+            .description
+            .clone()
+            .unwrap_or(device_id.clone());
+
+        let mut switch_accessory = SwitchAccessory::new(
+            id,
+            AccessoryInformation {
+                name: name.clone(),
+                manufacturer: "Comelit".to_string(),
+                serial_number: device_id.clone(),
+                ..Default::default()
+            },
+        )?;
+
+        let state = Arc::new(SwitchState::from(other_data));
+        debug!(?state, "Created Switch state: {other_data:#?}");
+        switch_accessory
+            .switch
+            .power_state
+            .set_value(Value::Bool(state.on.load(Ordering::Acquire)))
+            .await?;
+
+        let (command_sender, command_receiver) = mpsc::channel::<SwitchCommand>(16);
+        let reachability = Reachability::default();
+
+        // Read callback: reads from atomic state — no lock required
+        {
+            let id_ = device_id.clone();
+            let state_ = state.clone();
+            let reachability_ = reachability.clone();
+            switch_accessory.switch.power_state.on_read(Some(move || {
+                let value = state_.on.load(Ordering::Acquire);
+                debug!("Switch {} read: {}", id_, value);
+                Metrics::inc_hap_read("switch", &id_, "power_state");
+                reachability_.check(&id_).map(|()| Some(value))
+            }));
+        }
+
+        // Write callback: only sends to worker channel; returns immediately
+        {
+            let tx = command_sender.clone();
+            let id_ = device_id.clone();
+            switch_accessory.switch.power_state.on_update_async(Some(
+                move |_current_val: bool, new_val: bool| {
+                    Metrics::inc_hap_write("switch", &id_, "power_state");
+                    let tx = tx.clone();
+                    async move {
+                        if let Err(e) = tx.send(SwitchCommand::HapWrite(new_val)).await {
+                            warn!("Failed to send switch HapWrite command: {e}");
+                        }
+                        Ok(())
+                    }
+                    .boxed()
+                },
+            ));
+        }
+
+        let auto_off = if other_data.data.sub_type == ObjectSubtype::OtherTmp {
+            other_data.output_time()
+        } else {
+            None
+        };
+        let worker = SwitchWorker::new(
+            device_id.clone(),
+            state.clone(),
+            client,
+            auto_off,
+            command_sender.downgrade(),
+        );
+        tokio::spawn(worker.run(command_receiver));
+
+        let accessory = server.add_accessory(switch_accessory).await?;
+        command_sender
+            .send(SwitchCommand::SetAccessory(accessory.clone()))
+            .await
+            .ok();
+
+        Ok(Self {
+            id: device_id,
+            name,
+            state,
+            command_sender,
+            reachability,
+            accessory,
+        })
+    }
+
+    pub(crate) fn reachability(&self) -> &Reachability {
+        &self.reachability
+    }
+}
+
+impl ComelitAccessory<OtherDeviceData> for ComelitSwitchAccessory {
+    fn get_comelit_id(&self) -> &str {
+        self.id.as_str()
+    }
+
+    async fn update(&mut self, other_data: &OtherDeviceData) -> Result<()> {
+        let is_on = matches!(
+            other_data.data.status.clone().unwrap_or_default(),
+            DeviceStatus::On | DeviceStatus::Running
+        );
+        self.state.on.store(is_on, Ordering::Release);
+        self.command_sender
+            .send(SwitchCommand::MqttPush(is_on))
+            .await
+            .ok();
+        Ok(())
+    }
+}
@@ -26,9 +26,10 @@ use tokio::sync::mpsc::{self, Sender};
 use tracing::{debug, info, warn};
 
 use crate::accessories::{
-    ComelitAccessory,
+    ComelitAccessory, Reachability,
     state::thermostat::{TargetHeatingCoolingState, ThermostatState},
 };
+use crate::web::metrics::Metrics;
 use comelit_client_rs::{
     ClimaMode, ClimaOnOff, ComelitClient, ObjectSubtype, ThermoSeason, ThermostatDeviceData,
 };
@@ -216,9 +217,13 @@ impl ThermostatWorker {
                     warn!("toggle_thermostat_status failed: {e}");
                 }
 
-                if prev == TargetHeatingCoolingState::Auto as u8
-                    && new != TargetHeatingCoolingState::Off as u8
-                {
+                // Heat and Cool are manual modes: leaving Auto (or turning on a thermostat that
+                // was switched off in automatic mode) must also switch the hub to manual
+                let is_manual = |mode: u8| {
+                    mode == TargetHeatingCoolingState::Heat as u8
+                        || mode == TargetHeatingCoolingState::Cool as u8
+                };
+                if is_manual(new) && !is_manual(prev) {
                     if let Err(e) = self
                         .client
                         .set_thermostat_mode(&self.id, ClimaMode::Manual)
@@ -366,6 +371,7 @@ pub(crate) struct ComelitThermostatAccessory {
     id: String,
     pub name: String,
     command_sender: Sender<ThermostatCommand>,
+    reachability: Reachability,
     #[allow(dead_code)]
     accessory: Accessory,
 }
@@ -441,42 +447,57 @@ impl ComelitThermostatAccessory {
 
         // ── Read callbacks (read from shared state — no accessory lock needed) ──
 
+        let reachability = Reachability::default();
         {
             let s = Arc::clone(&arc_state);
+            let id_ = comelit_id.clone();
+            let reachability_ = reachability.clone();
             accessory
                 .thermostat
                 .current_temperature
                 .on_read_async(Some(move || {
+                    Metrics::inc_hap_read("thermostat", &id_, "current_temperature");
                     let s = s.clone();
-                    async move { Ok(Some(s.lock().await.temperature)) }.boxed()
+                    let check = reachability_.check(&id_);
+                    async move {
+                        let temperature = s.lock().await.temperature;
+                        check.map(|()| Some(temperature))
+                    }
+                    .boxed()
                 }));
         }
         {
             let s = Arc::clone(&arc_state);
+            let id_ = comelit_id.clone();
             accessory
                 .thermostat
                 .target_temperature
                 .on_read_async(Some(move || {
+                    Metrics::inc_hap_read("thermostat", &id_, "target_temperature");
                     let s = s.clone();
                     async move { Ok(Some(s.lock().await.target_temperature)) }.boxed()
                 }));
         }
         {
             let s = Arc::clone(&arc_state);
+            let id_ = comelit_id.clone();
             accessory
                 .thermostat
                 .current_heating_cooling_state
                 .on_read_async(Some(move || {
+                    Metrics::inc_hap_read("thermostat", &id_, "current_heating_cooling_state");
                     let s = s.clone();
                     async move { Ok(Some(s.lock().await.heating_cooling_state as u8)) }.boxed()
                 }));
         }
         {
             let s = Arc::clone(&arc_state);
+            let id_ = comelit_id.clone();
             accessory
                 .thermostat
                 .target_heating_cooling_state
                 .on_read_async(Some(move || {
+                    Metrics::inc_hap_read("thermostat", &id_, "target_heating_cooling_state");
                     let s = s.clone();
                     async move { Ok(Some(s.lock().await.target_heating_cooling_state as u8)) }
                         .boxed()
@@ -484,14 +505,18 @@ impl ComelitThermostatAccessory {
         }
         if let Some(ref mut char) = accessory.thermostat.current_relative_humidity {
             let s = Arc::clone(&arc_state);
+            let id_ = comelit_id.clone();
             char.on_read_async(Some(move || {
+                Metrics::inc_hap_read("thermostat", &id_, "current_relative_humidity");
                 let s = s.clone();
                 async move { Ok(Some(s.lock().await.humidity)) }.boxed()
             }));
         }
         if let Some(ref mut char) = accessory.thermostat.target_relative_humidity {
             let s = Arc::clone(&arc_state);
+            let id_ = comelit_id.clone();
             char.on_read_async(Some(move || {
+                Metrics::inc_hap_read("thermostat", &id_, "target_relative_humidity");
                 let s = s.clone();
                 async move { Ok(Some(s.lock().await.target_humidity)) }.boxed()
             }));
@@ -503,10 +528,12 @@ impl ComelitThermostatAccessory {
 
         {
             let tx = command_sender.clone();
+            let id_ = comelit_id.clone();
             accessory
                 .thermostat
                 .target_temperature
                 .on_update_async(Some(move |_, new: f32| {
+                    Metrics::inc_hap_write("thermostat", &id_, "target_temperature");
                     let tx = tx.clone();
                     async move {
                         tx.send(ThermostatCommand::SetTargetTemperature(new))
@@ -520,7 +547,9 @@ impl ComelitThermostatAccessory {
 
         if let Some(ref mut char) = accessory.thermostat.target_relative_humidity {
             let tx = command_sender.clone();
+            let id_ = comelit_id.clone();
             char.on_update_async(Some(move |_prev, new: f32| {
+                Metrics::inc_hap_write("thermostat", &id_, "target_relative_humidity");
                 let tx = tx.clone();
                 async move {
                     tx.send(ThermostatCommand::SetTargetHumidity(new))
@@ -534,10 +563,12 @@ impl ComelitThermostatAccessory {
 
         {
             let tx = command_sender.clone();
+            let id_ = comelit_id.clone();
             accessory
                 .thermostat
                 .target_heating_cooling_state
                 .on_update_async(Some(move |_prev: u8, new: u8| {
+                    Metrics::inc_hap_write("thermostat", &id_, "target_heating_cooling_state");
                     let tx = tx.clone();
                     async move {
                         tx.send(ThermostatCommand::SetHvacMode(new)).await.ok();
@@ -560,7 +591,9 @@ impl ComelitThermostatAccessory {
 
             {
                 let s = Arc::clone(&arc_state);
+                let id_ = comelit_id.clone();
                 hd.active.on_read_async(Some(move || {
+                    Metrics::inc_hap_read("dehumidifier", &id_, "active");
                     let s = s.clone();
                     async move { Ok(Some(s.lock().await.dehumidifier_active as u8)) }.boxed()
                 }));
@@ -572,8 +605,14 @@ impl ComelitThermostatAccessory {
 
             {
                 let s = Arc::clone(&arc_state);
+                let id_ = comelit_id.clone();
                 hd.current_humidifier_dehumidifier_state
                     .on_read_async(Some(move || {
+                        Metrics::inc_hap_read(
+                            "dehumidifier",
+                            &id_,
+                            "current_humidifier_dehumidifier_state",
+                        );
                         let s = s.clone();
                         async move { Ok(Some(s.lock().await.dehumidifier_current_state)) }.boxed()
                     }));
@@ -585,7 +624,9 @@ impl ComelitThermostatAccessory {
 
             {
                 let s = Arc::clone(&arc_state);
+                let id_ = comelit_id.clone();
                 hd.current_relative_humidity.on_read_async(Some(move || {
+                    Metrics::inc_hap_read("dehumidifier", &id_, "current_relative_humidity");
                     let s = s.clone();
                     async move { Ok(Some(s.lock().await.humidity)) }.boxed()
                 }));
@@ -598,14 +639,18 @@ impl ComelitThermostatAccessory {
 
                 {
                     let s = Arc::clone(&arc_state);
+                    let id_ = comelit_id.clone();
                     threshold.on_read_async(Some(move || {
+                        Metrics::inc_hap_read("dehumidifier", &id_, "humidity_threshold");
                         let s = s.clone();
                         async move { Ok(Some(s.lock().await.target_humidity)) }.boxed()
                     }));
                 }
 
                 let tx = command_sender.clone();
+                let id_ = comelit_id.clone();
                 threshold.on_update_async(Some(move |_prev, new: f32| {
+                    Metrics::inc_hap_write("dehumidifier", &id_, "humidity_threshold");
                     let tx = tx.clone();
                     async move {
                         tx.send(ThermostatCommand::SetDehumidifierThreshold(new))
@@ -619,7 +664,9 @@ impl ComelitThermostatAccessory {
 
             {
                 let tx = command_sender.clone();
+                let id_ = comelit_id.clone();
                 hd.active.on_update_async(Some(move |_prev: u8, new: u8| {
+                    Metrics::inc_hap_write("dehumidifier", &id_, "active");
                     let tx = tx.clone();
                     async move {
                         tx.send(ThermostatCommand::SetDehumidifierActive(new))
@@ -647,7 +694,12 @@ impl ComelitThermostatAccessory {
             id: data.id.clone(),
             name,
             command_sender,
+            reachability,
             accessory,
         })
     }
+
+    pub(crate) fn reachability(&self) -> &Reachability {
+        &self.reachability
+    }
 }
@@ -17,21 +17,25 @@ use tokio::sync::mpsc::{self, Sender};
 use tokio::time::Instant;
 use tracing::{debug, info, warn};
 
-use crate::accessories::ComelitAccessory;
 use crate::accessories::state::window_covering::{
     FULLY_CLOSED, FULLY_OPENED, PositionState, WindowCoveringState,
 };
+use crate::accessories::{ComelitAccessory, Reachability};
+use crate::web::metrics::Metrics;
 use comelit_client_rs::{ComelitClient, ComelitClientTrait, WindowCoveringDeviceData};
 
 #[derive(Clone, Copy)]
 pub struct WindowCoveringConfig {
     pub closing_time: Duration,
     pub opening_time: Duration,
+    /// Follow the movements started outside HomeKit (e.g. from a wall switch)
+    pub live_updates: bool,
 }
 
 pub(crate) struct ComelitWindowCoveringAccessory {
     id: String,
     command_sender: Sender<WorkerCommand>,
+    reachability: Reachability,
     #[allow(dead_code)]
     accessory: Accessory,
 }
@@ -42,6 +46,9 @@ enum WorkerCommand {
     /// Initiate movement from HomeKit (target position changed)
     MoveTo { old_pos: u8, new_pos: u8 },
 
+    /// HomeKit requested to stop the blind where it is (HoldPosition)
+    Hold,
+
     /// Comelit update received (status changed from external source or confirmation)
     StatusUpdate { new_state: WindowCoveringState },
 
@@ -128,6 +135,11 @@ impl<C: ComelitClientTrait + 'static> WindowCoveringWorker<C> {
                                 warn!("Error handling move_to: {}", e);
                             }
                         }
+                        Some(WorkerCommand::Hold) => {
+                            if let Err(e) = self.handle_hold().await {
+                                warn!("Error handling hold: {}", e);
+                            }
+                        }
                         Some(WorkerCommand::StatusUpdate { new_state }) => {
                             if let Err(e) = self.handle_status_update(new_state).await {
                                 warn!("Error handling status update: {}", e);
@@ -242,6 +254,37 @@ impl<C: ComelitClientTrait + 'static> WindowCoveringWorker<C> {
         Ok(())
     }
 
+    /// Handle HoldPosition from HomeKit: stop the blind at the estimated position
+    async fn handle_hold(&mut self) -> Result<()> {
+        // Bring the estimate up to date first; this may already stop the blind
+        // if the target was reached in the meantime.
+        self.update_position().await?;
+
+        let direction = match &self.worker_state {
+            WorkerState::WaitingForMoveConfirmation { direction, .. }
+            | WorkerState::MovingInternal { direction, .. }
+            | WorkerState::MovingExternal { direction, .. } => *direction,
+            WorkerState::Idle | WorkerState::WaitingForStopConfirmation { .. } => {
+                debug!("Hold requested for {} while not moving, no action", self.id);
+                return Ok(());
+            }
+        };
+
+        info!("Holding position for {}", self.id);
+        // Send stop command
+        let on = direction == PositionState::MovingDown;
+        self.client.toggle_device_status(&self.id, on).await?;
+
+        let current_pos = {
+            let mut state = self.state.lock().await;
+            state.target_position = state.current_position;
+            state.current_position
+        };
+        self.worker_state = WorkerState::WaitingForStopConfirmation { current_pos };
+
+        self.update_accessory().await
+    }
+
     /// Handle status update from Comelit
     async fn handle_status_update(&mut self, new_state: WindowCoveringState) -> Result<()> {
         let new_position_state = new_state.position_state;
@@ -249,7 +292,12 @@ impl<C: ComelitClientTrait + 'static> WindowCoveringWorker<C> {
         match &self.worker_state {
             WorkerState::Idle => {
                 // We weren't expecting any movement
-                if new_position_state != PositionState::Stopped {
+                if new_position_state != PositionState::Stopped && !self.config.live_updates {
+                    debug!(
+                        "Ignoring external movement for {} (live updates disabled)",
+                        self.id
+                    );
+                } else if new_position_state != PositionState::Stopped {
                     // External movement started (physical button)
                     let current_pos = {
                         let state = self.state.lock().await;
@@ -560,7 +608,6 @@ impl ComelitWindowCoveringAccessory {
         wc_accessory.window_covering.current_horizontal_tilt_angle = None;
         wc_accessory.window_covering.target_horizontal_tilt_angle = None;
         wc_accessory.window_covering.obstruction_detected = None;
-        wc_accessory.window_covering.hold_position = None;
         wc_accessory.window_covering.current_vertical_tilt_angle = None;
         wc_accessory.window_covering.target_vertical_tilt_angle = None;
 
@@ -602,11 +649,27 @@ impl ComelitWindowCoveringAccessory {
         let (command_sender, command_receiver) = mpsc::channel::<WorkerCommand>(32);
 
         // Set up read callbacks
-        Self::setup_read_characteristics(device_id.as_str(), &mut wc_accessory, state.clone())
-            .await;
+        let reachability = Reachability::default();
+        Self::setup_read_characteristics(
+            device_id.as_str(),
+            &mut wc_accessory,
+            state.clone(),
+            reachability.clone(),
+        )
+        .await;
 
-        // Set up update callback
-        Self::setup_update_target_position(&mut wc_accessory, command_sender.clone()).await;
+        // Set up update callbacks
+        Self::setup_update_target_position(
+            device_id.as_str(),
+            &mut wc_accessory,
+            command_sender.clone(),
+        )
+        .await;
+        Self::setup_update_hold_position(
+            device_id.as_str(),
+            &mut wc_accessory,
+            command_sender.clone(),
+        );
 
         // Spawn the worker thread
         let worker = WindowCoveringWorker::new(device_id.clone(), state.clone(), client, config);
@@ -627,14 +690,20 @@ impl ComelitWindowCoveringAccessory {
         Ok(Self {
             id: device_id.to_string(),
             command_sender,
+            reachability,
             accessory,
         })
     }
 
+    pub(crate) fn reachability(&self) -> &Reachability {
+        &self.reachability
+    }
+
     async fn setup_read_characteristics(
         id: &str,
         accessory: &mut WindowCoveringAccessory,
         state: Arc<TokioMutex<WindowCoveringState>>,
+        reachability: Reachability,
     ) {
         let id_ = id.to_string();
         let state_ = state.clone();
@@ -646,6 +715,7 @@ impl ComelitWindowCoveringAccessory {
                 let state_ = state_.clone();
                 async move {
                     debug!("Window covering POSITION STATE read {}", id_);
+                    Metrics::inc_hap_read("window_covering", &id_, "position_state");
                     let state = state_.lock().await;
                     Ok(Some(state.position_state as u8))
                 }
@@ -660,10 +730,12 @@ impl ComelitWindowCoveringAccessory {
             .on_read_async(Some(move || {
                 let id_ = id_.to_string();
                 let state_ = state_.clone();
+                let check = reachability.check(&id_);
                 async move {
                     debug!("Window covering POSITION read {}", id_);
+                    Metrics::inc_hap_read("window_covering", &id_, "current_position");
                     let state = state_.lock().await;
-                    Ok(Some(state.current_position))
+                    check.map(|()| Some(state.current_position))
                 }
                 .boxed()
             }));
@@ -678,6 +750,7 @@ impl ComelitWindowCoveringAccessory {
                 let state_ = state_.clone();
                 async move {
                     debug!("Window covering TARGET POSITION read {}", id_);
+                    Metrics::inc_hap_read("window_covering", &id_, "target_position");
                     let state = state_.lock().await;
                     Ok(Some(state.target_position))
                 }
@@ -686,13 +759,16 @@ impl ComelitWindowCoveringAccessory {
     }
 
     async fn setup_update_target_position(
+        id: &str,
         accessory: &mut WindowCoveringAccessory,
         command_sender: Sender<WorkerCommand>,
     ) {
+        let id = id.to_string();
         accessory
             .window_covering
             .target_position
             .on_update_async(Some(move |old_pos, new_pos| {
+                Metrics::inc_hap_write("window_covering", &id, "target_position");
                 let command_sender = command_sender.clone();
                 async move {
                     info!(
@@ -712,6 +788,31 @@ impl ComelitWindowCoveringAccessory {
                 .boxed()
             }));
     }
+
+    fn setup_update_hold_position(
+        id: &str,
+        accessory: &mut WindowCoveringAccessory,
+        command_sender: Sender<WorkerCommand>,
+    ) {
+        let Some(hold_position) = accessory.window_covering.hold_position.as_mut() else {
+            return;
+        };
+        let id = id.to_string();
+        hold_position.on_update_async(Some(move |_old: bool, hold: bool| {
+            Metrics::inc_hap_write("window_covering", &id, "hold_position");
+            let command_sender = command_sender.clone();
+            async move {
+                if hold {
+                    info!("Window covering hold position requested");
+                    if let Err(e) = command_sender.send(WorkerCommand::Hold).await {
+                        warn!("Failed to send hold command: {}", e);
+                    }
+                }
+                Ok(())
+            }
+            .boxed()
+        }));
+    }
 }
 
 impl ComelitAccessory<WindowCoveringDeviceData> for ComelitWindowCoveringAccessory {
@@ -900,10 +1001,22 @@ pub mod testing {
         Sender<WorkerCommand>,
         Arc<TokioMutex<WindowCoveringState>>,
         FakeComelitClient,
+    ) {
+        create_test_worker_with_live_updates(state, true).await
+    }
+
+    async fn create_test_worker_with_live_updates(
+        state: WindowCoveringState,
+        live_updates: bool,
+    ) -> (
+        Sender<WorkerCommand>,
+        Arc<TokioMutex<WindowCoveringState>>,
+        FakeComelitClient,
     ) {
         let config = WindowCoveringConfig {
             opening_time: Duration::from_secs(5),
             closing_time: Duration::from_secs(5),
+            live_updates,
         };
         let client = FakeComelitClient::new();
         let state = Arc::new(TokioMutex::new(state));
@@ -1024,6 +1137,66 @@ pub mod testing {
         );
     }
 
+    #[tokio::test]
+    async fn test_external_movement_without_live_updates() {
+        let initial_state = WindowCoveringState {
+            current_position: 50,
+            target_position: 50,
+            position_state: PositionState::Stopped,
+        };
+
+        let (sender, state, client) =
+            create_test_worker_with_live_updates(initial_state, false).await;
+
+        // External movement is ignored
+        sender
+            .send(WorkerCommand::StatusUpdate {
+                new_state: WindowCoveringState {
+                    current_position: 50,
+                    target_position: FULLY_OPENED,
+                    position_state: PositionState::MovingUp,
+                },
+            })
+            .await
+            .unwrap();
+
+        sleep(Duration::from_millis(1500)).await;
+
+        {
+            let current_state = state.lock().await;
+            assert_eq!(current_state.current_position, 50);
+            assert_eq!(current_state.position_state, PositionState::Stopped);
+        }
+
+        // Movements started from HomeKit are still confirmed by the status updates
+        sender
+            .send(WorkerCommand::MoveTo {
+                old_pos: 50,
+                new_pos: FULLY_CLOSED,
+            })
+            .await
+            .unwrap();
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(client.toggle_calls.read().await.len(), 1);
+
+        sender
+            .send(WorkerCommand::StatusUpdate {
+                new_state: WindowCoveringState {
+                    current_position: 50,
+                    target_position: FULLY_CLOSED,
+                    position_state: PositionState::MovingDown,
+                },
+            })
+            .await
+            .unwrap();
+
+        sleep(Duration::from_millis(1500)).await;
+
+        let current_state = state.lock().await;
+        assert!(current_state.current_position < 50);
+        assert_eq!(current_state.position_state, PositionState::MovingDown);
+    }
+
     #[tokio::test]
     async fn test_move_to_close() {
         let initial_state = WindowCoveringState {
@@ -1148,6 +1321,7 @@ pub mod testing {
         let config = WindowCoveringConfig {
             opening_time: Duration::from_secs(5),
             closing_time: Duration::from_secs(5),
+            live_updates: true,
         };
         let client = FakeComelitClient::new();
         let state = Arc::new(TokioMutex::new(initial_state));
@@ -1192,4 +1366,89 @@ pub mod testing {
         let calls = client.toggle_calls.read().await;
         assert!(calls.len() >= 2); // Start + stop
     }
+
+    #[tokio::test]
+    async fn test_hold_position_stops_movement() {
+        let initial_state = WindowCoveringState {
+            current_position: FULLY_CLOSED,
+            target_position: FULLY_CLOSED,
+            position_state: PositionState::Stopped,
+        };
+
+        let (sender, state, client) = create_test_worker(initial_state).await;
+
+        sender
+            .send(WorkerCommand::MoveTo {
+                old_pos: FULLY_CLOSED,
+                new_pos: FULLY_OPENED,
+            })
+            .await
+            .unwrap();
+        sender
+            .send(WorkerCommand::StatusUpdate {
+                new_state: WindowCoveringState {
+                    current_position: FULLY_CLOSED,
+                    target_position: FULLY_OPENED,
+                    position_state: PositionState::MovingUp,
+                },
+            })
+            .await
+            .unwrap();
+
+        sleep(Duration::from_secs(2)).await;
+
+        sender.send(WorkerCommand::Hold).await.unwrap();
+        sleep(Duration::from_millis(100)).await;
+
+        // Start + stop (stop while opening = toggle off)
+        {
+            let calls = client.toggle_calls.read().await;
+            assert_eq!(calls.len(), 2);
+            assert_eq!(calls[1], ("test-123".to_string(), false));
+        }
+        let held_position = {
+            let current_state = state.lock().await;
+            assert!(current_state.current_position > FULLY_CLOSED);
+            assert!(current_state.current_position < FULLY_OPENED);
+            assert_eq!(
+                current_state.target_position,
+                current_state.current_position
+            );
+            current_state.current_position
+        };
+
+        // Comelit confirms the stop: the estimated position is kept
+        sender
+            .send(WorkerCommand::StatusUpdate {
+                new_state: WindowCoveringState {
+                    current_position: FULLY_CLOSED,
+                    target_position: FULLY_CLOSED,
+                    position_state: PositionState::Stopped,
+                },
+            })
+            .await
+            .unwrap();
+        sleep(Duration::from_millis(100)).await;
+
+        let current_state = state.lock().await;
+        assert_eq!(current_state.position_state, PositionState::Stopped);
+        assert_eq!(current_state.current_position, held_position);
+    }
+
+    #[tokio::test]
+    async fn test_hold_position_when_idle_does_nothing() {
+        let initial_state = WindowCoveringState {
+            current_position: 50,
+            target_position: 50,
+            position_state: PositionState::Stopped,
+        };
+
+        let (sender, state, client) = create_test_worker(initial_state).await;
+
+        sender.send(WorkerCommand::Hold).await.unwrap();
+        sleep(Duration::from_millis(100)).await;
+
+        assert!(client.toggle_calls.read().await.is_empty());
+        assert_eq!(state.lock().await.current_position, 50);
+    }
 }
@@ -1,17 +1,21 @@
 use crate::accessories::{
     ComelitAccessory, ComelitDoorAccessory, ComelitDoorbellAccessory, ComelitLightbulbAccessory,
-    ComelitThermostatAccessory, ComelitWindowCoveringAccessory, DoorConfig, WindowCoveringConfig,
+    ComelitSwitchAccessory, ComelitThermostatAccessory, ComelitWindowCoveringAccessory, DoorConfig,
+    Reachability, WindowCoveringConfig,
 };
-use crate::settings::Settings;
+use crate::settings::{BridgeDeviceKind, BridgeSettings, LiveUpdateSettings, RoomNaming, Settings};
+use crate::viper::Intercom;
 use crate::web::metrics::Metrics;
-use crate::web::state::{BridgeState, ConnectionStatus, DeviceInfo, DeviceType};
+use crate::web::state::{
+    BridgeState, ConnectionStatus, DeviceInfo, DeviceType, IndexEntry, RingEvent,
+};
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use comelit_client_rs::{
-    ComelitClient, ComelitClientError, ComelitOptions, DoorbellDeviceData, HomeDeviceData, State,
-    StatusUpdate, get_secrets,
+    ComelitClient, ComelitClientError, ComelitHUB, ComelitOptions, DoorbellDeviceData,
+    HomeDeviceData, State, StatusUpdate, get_secrets,
 };
-use comelit_client_rs::{DeviceStatus, ObjectSubtype};
+use comelit_client_rs::{DeviceStatus, ObjectSubtype, ObjectType};
 use comelit_client_rs::{DoorDeviceData, ROOT_ID};
 use dashmap::DashMap;
 use hap::BonjourStatusFlag;
@@ -23,12 +27,17 @@ use hap::{
 };
 use qrcode::QrCode;
 use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 use tokio::signal;
+use tokio::task::JoinHandle;
 use tracing::{error, info, warn};
 
+/// Delay between the attempts to log in again after the connection to the hub was lost.
+const RECONNECT_DELAY: Duration = Duration::from_secs(10);
+
 /// Updater that handles status updates from the Comelit client.
 /// Also updates the shared bridge state for the web UI.
 struct Updater {
@@ -37,18 +46,173 @@ struct Updater {
     thermostats: DashMap<String, ComelitThermostatAccessory>,
     doors: DashMap<String, ComelitDoorAccessory>,
     doorbells: DashMap<String, ComelitDoorbellAccessory>,
+    switches: DashMap<String, ComelitSwitchAccessory>,
     bridge_state: BridgeState,
+    /// Device types whose hub updates are forwarded to HomeKit
+    live_updates: LiveUpdateSettings,
 }
 
 impl Updater {
-    fn new(bridge_state: BridgeState) -> Self {
+    fn new(bridge_state: BridgeState, live_updates: LiveUpdateSettings) -> Self {
         Self {
             lights: DashMap::new(),
             window_coverings: DashMap::new(),
             thermostats: DashMap::new(),
             doors: DashMap::new(),
             doorbells: DashMap::new(),
+            switches: DashMap::new(),
             bridge_state,
+            live_updates,
+        }
+    }
+
+    /// Ring the doorbells for a call received from the intercom, only `device` when given,
+    /// and record the ring in the event log.
+    async fn ring_doorbells(&self, device: Option<&str>) {
+        let ids: Vec<String> = self
+            .doorbells
+            .iter()
+            .map(|e| e.key().clone())
+            .filter(|id| device.is_none_or(|d| d == id))
+            .collect();
+        if ids.is_empty() {
+            warn!("Intercom call received but no doorbell is mounted");
+        }
+        for id in ids {
+            let Some(accessory) = self.doorbells.get(&id) else {
+                continue;
+            };
+            match accessory.trigger().await {
+                Ok(true) => self.bridge_state.record_ring(RingEvent {
+                    device_id: id.clone(),
+                    name: accessory.name.clone(),
+                    time: SystemTime::now(),
+                }),
+                Ok(false) => {}
+                Err(e) => error!("Failed to ring doorbell {}: {}", id, e),
+            }
+        }
+    }
+
+    /// Returns the fault flags of all the mounted devices that can report a fault.
+    fn reachabilities(&self) -> Vec<(String, Reachability)> {
+        let lights = self
+            .lights
+            .iter()
+            .map(|e| (e.key().clone(), e.reachability().clone()));
+        let window_coverings = self
+            .window_coverings
+            .iter()
+            .map(|e| (e.key().clone(), e.reachability().clone()));
+        let thermostats = self
+            .thermostats
+            .iter()
+            .map(|e| (e.key().clone(), e.reachability().clone()));
+        let doors = self
+            .doors
+            .iter()
+            .map(|e| (e.key().clone(), e.reachability().clone()));
+        let switches = self
+            .switches
+            .iter()
+            .map(|e| (e.key().clone(), e.reachability().clone()));
+        lights
+            .chain(window_coverings)
+            .chain(thermostats)
+            .chain(doors)
+            .chain(switches)
+            .collect()
+    }
+
+    /// Flags all devices as faulty, e.g. when the connection to the hub is lost.
+    ///
+    /// The hub only pushes state changes, so a device that sent nothing for a while is
+    /// not faulty: the fault follows the connection, and is cleared by the next update.
+    fn set_all_faults(&self) {
+        for (_, reachability) in self.reachabilities() {
+            reachability.set_fault(true);
+        }
+    }
+
+    /// Log in to the hub again and subscribe to the updates, then refresh the mounted
+    /// devices from the index to clear their faults: the hub would only send the ones
+    /// that change.
+    async fn login_again(
+        &self,
+        client: &ComelitClient,
+    ) -> Result<JoinHandle<()>, ComelitClientError> {
+        let ping_task = client.login(State::Disconnected).await?;
+        let refresh = async {
+            client.subscribe(ROOT_ID).await?;
+            let doorbells: Vec<String> = self.doorbells.iter().map(|e| e.key().clone()).collect();
+            for id in doorbells {
+                client.subscribe(&id).await?;
+            }
+            let mounted: HashSet<String> = self
+                .reachabilities()
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect();
+            for (_, device) in client.fetch_index(1).await? {
+                if mounted.contains(&device.id()) {
+                    self.status_update(&device).await;
+                }
+            }
+            Ok::<(), ComelitClientError>(())
+        };
+        match refresh.await {
+            Ok(()) => Ok(ping_task),
+            Err(e) => {
+                ping_task.abort();
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Wait for the ping task to end, i.e. for the connection to the hub to be lost, then
+/// call `lost` and `reconnect` to get the ping task of the new connection, forever.
+///
+/// The HAP servers keep running meanwhile, so HomeKit shows the devices as not
+/// responding while the hub is down instead of losing the bridge.
+async fn keep_connected<L, R, F>(mut ping_task: JoinHandle<()>, lost: L, mut reconnect: R)
+where
+    L: Fn(),
+    R: FnMut() -> F,
+    F: Future<Output = JoinHandle<()>>,
+{
+    loop {
+        let _ = ping_task.await;
+        lost();
+        ping_task = reconnect().await;
+    }
+}
+
+/// Log in again until the hub answers, returning the new ping task.
+async fn reconnect(
+    client: ComelitClient,
+    updater: Arc<Updater>,
+    bridge_state: BridgeState,
+) -> JoinHandle<()> {
+    loop {
+        tokio::time::sleep(RECONNECT_DELAY).await;
+        bridge_state.set_connection_status(ConnectionStatus::Connecting);
+        match updater.login_again(&client).await {
+            Ok(ping_task) => {
+                info!("Reconnected to the Comelit hub");
+                bridge_state.set_connection_status(ConnectionStatus::Connected);
+                bridge_state.set_error(None);
+                bridge_state.set_client(Some(client));
+                Metrics::set_connected(true);
+                return ping_task;
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to reconnect to the Comelit hub: {e}, retrying in {RECONNECT_DELAY:?}"
+                );
+                bridge_state.set_connection_status(ConnectionStatus::Disconnected);
+                bridge_state.set_error(Some(e.to_string()));
+            }
         }
     }
 }
@@ -56,13 +220,44 @@ impl Updater {
 #[async_trait]
 impl StatusUpdate for Updater {
     async fn status_update(&self, device: &HomeDeviceData) {
+        // Keep the raw fields for the device detail page, without the enum variant wrapper
+        if let Ok(serde_json::Value::Object(variant)) = serde_json::to_value(device)
+            && let Some(fields) = variant.values().next()
+        {
+            self.bridge_state.set_device_fields(&device.id(), fields);
+        }
         match device {
             HomeDeviceData::Agent(_) => {}
             HomeDeviceData::Data(_) => {}
-            HomeDeviceData::Other(_) => {}
+            HomeDeviceData::Other(data) => {
+                Metrics::inc_device_updates("switch");
+                if let Some(mut accessory) = self.switches.get_mut(&device.id()) {
+                    accessory.reachability().set_fault(false);
+                    let is_on = matches!(
+                        data.data.status,
+                        Some(DeviceStatus::On) | Some(DeviceStatus::Running)
+                    );
+                    let status = if is_on { "on" } else { "off" };
+                    self.bridge_state
+                        .update_device_status(&device.id(), status.to_string());
+                    if self.live_updates.switches {
+                        accessory.update(data).await.unwrap_or_else(|e| {
+                            Metrics::inc_device_update_errors("switch");
+                            error!(
+                                "Failed to update switch accessory {}: {}",
+                                accessory.get_comelit_id(),
+                                e
+                            );
+                        });
+                    }
+                } else {
+                    warn!("Received update for unknown switch device: {}", device.id());
+                }
+            }
             HomeDeviceData::Light(data) => {
                 Metrics::inc_device_updates("light");
                 if let Some(mut accessory) = self.lights.get_mut(&device.id()) {
+                    accessory.reachability().set_fault(false);
                     let is_on = matches!(
                         data.status,
                         Some(DeviceStatus::On) | Some(DeviceStatus::Running)
@@ -72,14 +267,21 @@ impl StatusUpdate for Updater {
                         .update_device_status(&device.id(), status.to_string());
                     let name = accessory.name.as_str();
                     Metrics::set_light_status(name, is_on);
-                    accessory.update(data).await.unwrap_or_else(|e| {
-                        Metrics::inc_device_update_errors("light");
-                        error!(
-                            "Failed to update light accessory {}: {}",
-                            accessory.get_comelit_id(),
-                            e
-                        );
-                    });
+                    self.bridge_state.persist_sample(
+                        "comelit_light_status",
+                        name,
+                        if is_on { 1.0 } else { 0.0 },
+                    );
+                    if self.live_updates.lights {
+                        accessory.update(data).await.unwrap_or_else(|e| {
+                            Metrics::inc_device_update_errors("light");
+                            error!(
+                                "Failed to update light accessory {}: {}",
+                                accessory.get_comelit_id(),
+                                e
+                            );
+                        });
+                    }
                 } else {
                     warn!("Received update for unknown light device: {}", device.id());
                 }
@@ -87,11 +289,14 @@ impl StatusUpdate for Updater {
             HomeDeviceData::WindowCovering(data) => {
                 Metrics::inc_device_updates("window_covering");
                 if let Some(mut accessory) = self.window_coverings.get_mut(&device.id()) {
+                    accessory.reachability().set_fault(false);
                     let status = match &data.status {
                         Some(s) => format!("{:?}", s),
                         None => "unknown".to_string(),
                     };
                     self.bridge_state.update_device_status(&device.id(), status);
+                    // The worker needs the updates to confirm the movements started from
+                    // HomeKit, it ignores the others when live updates are disabled
                     accessory.update(data).await.unwrap_or_else(|e| {
                         Metrics::inc_device_update_errors("window_covering");
                         error!(
@@ -99,7 +304,7 @@ impl StatusUpdate for Updater {
                             accessory.get_comelit_id(),
                             e
                         );
-                    })
+                    });
                 } else {
                     warn!(
                         "Received update for unknown window covering device: {}",
@@ -112,6 +317,7 @@ impl StatusUpdate for Updater {
             HomeDeviceData::Thermostat(data) => {
                 Metrics::inc_device_updates("thermostat");
                 if let Some(mut accessory) = self.thermostats.get_mut(&device.id()) {
+                    accessory.reachability().set_fault(false);
                     let status = format!("{}°C", data.temperature.as_deref().unwrap_or("--"));
                     self.bridge_state.update_device_status(&device.id(), status);
                     let name = accessory.name.as_str();
@@ -120,29 +326,49 @@ impl StatusUpdate for Updater {
                         Some(DeviceStatus::On) | Some(DeviceStatus::Running)
                     );
                     let is_dehumidifier = data.sub_type == ObjectSubtype::ClimaDehumidifier;
-                    if is_dehumidifier {
+                    let status_metric = if is_dehumidifier {
                         Metrics::set_dehumidifier_status(name, is_on);
+                        "comelit_dehumidifier_status"
                     } else {
                         Metrics::set_thermostat_status(name, is_on);
-                    }
+                        "comelit_thermostat_status"
+                    };
+                    self.bridge_state.persist_sample(
+                        status_metric,
+                        name,
+                        if is_on { 1.0 } else { 0.0 },
+                    );
                     if let Some(temp_str) = &data.temperature
                         && let Ok(raw) = temp_str.parse::<f64>()
                     {
                         Metrics::set_thermostat_temperature(name, raw / 10.0);
+                        self.bridge_state.record_sample(&device.id(), raw / 10.0);
+                        self.bridge_state.persist_sample(
+                            "comelit_thermostat_temperature",
+                            name,
+                            raw / 10.0,
+                        );
                     }
                     if let Some(humi_str) = &data.humidity
                         && let Ok(raw) = humi_str.parse::<f64>()
                     {
                         Metrics::set_dehumidifier_humidity(name, raw);
-                    }
-                    accessory.update(data).await.unwrap_or_else(|e| {
-                        Metrics::inc_device_update_errors("thermostat");
-                        error!(
-                            "Failed to update thermostat accessory {}: {}",
-                            device.id(),
-                            e
+                        self.bridge_state.persist_sample(
+                            "comelit_dehumidifier_humidity",
+                            name,
+                            raw,
                         );
-                    });
+                    }
+                    if self.live_updates.thermostats {
+                        accessory.update(data).await.unwrap_or_else(|e| {
+                            Metrics::inc_device_update_errors("thermostat");
+                            error!(
+                                "Failed to update thermostat accessory {}: {}",
+                                device.id(),
+                                e
+                            );
+                        });
+                    }
                 } else {
                     warn!(
                         "Received update for unknown thermostat/dehumidifier device: {}",
@@ -157,10 +383,18 @@ impl StatusUpdate for Updater {
                     .parse::<f64>()
                     .unwrap_or(0.0);
                 Metrics::set_total_consumption(total_consumption);
+                self.bridge_state
+                    .record_sample(&device.id(), total_consumption);
+                self.bridge_state.persist_sample(
+                    "comelit_total_consumption",
+                    "total",
+                    total_consumption,
+                );
             }
             HomeDeviceData::Doorbell(bell_device_data) => {
                 Metrics::inc_device_updates("doorbell");
                 if let Some(mut accessory) = self.doorbells.get_mut(&device.id()) {
+                    let previous_ring = accessory.last_ring().await;
                     accessory
                         .update(bell_device_data)
                         .await
@@ -168,24 +402,34 @@ impl StatusUpdate for Updater {
                             Metrics::inc_device_update_errors("doorbell");
                             error!("Failed to update doorbell {}: {}", device.id(), e);
                         });
+                    if accessory.last_ring().await != previous_ring {
+                        self.bridge_state.record_ring(RingEvent {
+                            device_id: device.id(),
+                            name: accessory.name.clone(),
+                            time: SystemTime::now(),
+                        });
+                    }
                 }
             }
             HomeDeviceData::Door(door_device_data) => {
                 Metrics::inc_device_updates("door");
                 if let Some(mut accessory) = self.doors.get_mut(&device.id()) {
+                    accessory.reachability().set_fault(false);
                     let status = match door_device_data.status {
                         Some(DeviceStatus::On) | Some(DeviceStatus::Running) => "open",
                         _ => "closed",
                     };
                     self.bridge_state
                         .update_device_status(&device.id(), status.to_string());
-                    accessory
-                        .update(door_device_data)
-                        .await
-                        .unwrap_or_else(|e| {
-                            Metrics::inc_device_update_errors("door");
-                            error!("Failed to update door accessory {}: {}", device.id(), e);
-                        });
+                    if self.live_updates.doors {
+                        accessory
+                            .update(door_device_data)
+                            .await
+                            .unwrap_or_else(|e| {
+                                Metrics::inc_device_update_errors("door");
+                                error!("Failed to update door accessory {}: {}", device.id(), e);
+                            });
+                    }
                 } else {
                     warn!("Received update for unknown door device: {}", device.id());
                 }
@@ -194,8 +438,49 @@ impl StatusUpdate for Updater {
     }
 }
 
+/// Add the Comelit room to a device name, unless the name already mentions it.
+///
+/// A plain hyphen is used as separator since HomeKit rejects most punctuation in names.
+fn name_with_room(naming: RoomNaming, room: &str, name: &str) -> String {
+    if name.to_lowercase().contains(&room.to_lowercase()) {
+        return name.to_string();
+    }
+    match naming {
+        RoomNaming::Prefix => format!("{room} - {name}"),
+        RoomNaming::Suffix => format!("{name} - {room}"),
+    }
+}
+
+/// Describe a device of the hub index for the devices export.
+fn index_entry(device: &HomeDeviceData, rooms: &HashMap<String, String>) -> IndexEntry {
+    let fields = match serde_json::to_value(device) {
+        Ok(serde_json::Value::Object(variant)) => variant.into_values().next(),
+        _ => None,
+    }
+    .unwrap_or_default();
+    let code = |key: &str| fields[key].as_i64().map(|c| c as i32);
+    let id = device.id();
+    IndexEntry {
+        object_type: code("type")
+            .map(|t| format!("{:?}", ObjectType::from(t)))
+            .unwrap_or_default(),
+        subtype: code("sub_type")
+            .map(|t| format!("{:?}", ObjectSubtype::from(t)))
+            .unwrap_or_default(),
+        room: rooms.get(&id).cloned(),
+        name: device.name(),
+        status: match &fields["status"] {
+            serde_json::Value::Null => String::new(),
+            serde_json::Value::String(s) => s.clone(),
+            other => other.to_string(),
+        },
+        id,
+    }
+}
+
 /// Derives a stable, locally-administered MAC address from a device ID string.
-/// Used to give each doorbell's standalone HAP server a persistent identity.
+/// Used to give each doorbell's standalone HAP server (and each additional bridge)
+/// a persistent identity.
 fn doorbell_mac(device_id: &str) -> [u8; 6] {
     let mut hasher = DefaultHasher::new();
     device_id.hash(&mut hasher);
@@ -204,6 +489,124 @@ fn doorbell_mac(device_id: &str) -> [u8; 6] {
     [(h[0] | 0x02) & 0xFE, h[1], h[2], h[3], h[4], h[5]]
 }
 
+/// Builds the bridge accessory information from the hub scan record, so the Home app
+/// shows the real hardware model, serial number and firmware version.
+fn bridge_information(name: &str, hub: &ComelitHUB) -> AccessoryInformation {
+    let firmware = hub.app_version().trim();
+    AccessoryInformation {
+        name: name.into(),
+        manufacturer: "Comelit".into(),
+        model: format!("{} ({})", hub.model(), hub.model_id()),
+        serial_number: hub.mac_address().to_string().replace(':', ""),
+        firmware_revision: (!firmware.is_empty()).then(|| firmware.to_string()),
+        hardware_revision: (!hub.hw_id().is_empty()).then(|| hub.hw_id().to_string()),
+        ..Default::default()
+    }
+}
+
+/// A running HAP bridge server and the accessory ids allocated on it.
+struct HapBridge {
+    /// Settings of an additional bridge, `None` for the main bridge.
+    settings: Option<BridgeSettings>,
+    server: IpServer,
+    last_id: u64,
+}
+
+impl HapBridge {
+    fn new(settings: Option<BridgeSettings>, server: IpServer) -> Self {
+        // Accessory id 1 is reserved for the bridge accessory itself
+        Self {
+            settings,
+            server,
+            last_id: 1,
+        }
+    }
+
+    fn name(&self) -> &str {
+        self.settings
+            .as_ref()
+            .map(|s| s.name.as_str())
+            .unwrap_or("main")
+    }
+
+    fn next_id(&mut self) -> u64 {
+        self.last_id += 1;
+        self.last_id
+    }
+}
+
+/// Picks the first additional bridge accepting the device, falling back to the main bridge.
+fn select_bridge<'a>(
+    bridges: &'a mut [HapBridge],
+    kind: BridgeDeviceKind,
+    device_id: &str,
+    room: Option<&str>,
+) -> &'a mut HapBridge {
+    let index = bridges
+        .iter()
+        .position(|b| {
+            b.settings
+                .as_ref()
+                .is_some_and(|s| s.matches(kind, device_id, room))
+        })
+        .unwrap_or(0);
+    &mut bridges[index]
+}
+
+/// Creates the HAP server of an additional bridge, storing its pairing data in a dedicated
+/// directory so each bridge keeps its own identity across restarts.
+async fn create_additional_bridge(
+    settings: &BridgeSettings,
+    hub: &ComelitHUB,
+) -> Result<HapBridge> {
+    let name_sanitized: String = settings
+        .name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    let mut storage = FileStorage::new(&format!("bridge_{}", name_sanitized)).await?;
+    let config = match storage.load_config().await {
+        Ok(mut c) => {
+            c.redetermine_local_ip();
+            storage.save_config(&c).await?;
+            c
+        }
+        Err(_) => {
+            let pin = Pin::new(settings.pairing_code)
+                .map_err(|e| anyhow::anyhow!("Invalid pairing code for {}: {e}", settings.name))?;
+            let c = Config {
+                pin,
+                name: settings.name.clone(),
+                device_id: MacAddress::from(doorbell_mac(&format!("bridge_{}", name_sanitized))),
+                category: AccessoryCategory::Bridge,
+                port: settings.port,
+                ..Default::default()
+            };
+            storage.save_config(&c).await?;
+            c
+        }
+    };
+
+    info!(
+        "Pair bridge {} using pin code {}",
+        settings.name,
+        config.pin.to_string()
+    );
+    let bridge = BridgeAccessory::new(1, bridge_information(&settings.name, hub))?;
+    let server = IpServer::new(config, storage).await?;
+    server.add_accessory(bridge).await?;
+    Ok(HapBridge::new(Some(settings.clone()), server))
+}
+
+/// Reason why the bridge stopped without errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BridgeExit {
+    /// The process received a termination signal.
+    Shutdown,
+    /// The settings were changed and the bridge must be restarted to apply them.
+    Reload,
+}
+
 pub async fn start_bridge(
     user: &str,
     password: &str,
@@ -211,9 +614,10 @@ pub async fn start_bridge(
     port: Option<u16>,
     settings: Settings,
     bridge_state: BridgeState,
-) -> Result<()> {
+) -> Result<BridgeExit> {
     // Set bridge info metric
     Metrics::set_bridge_info(env!("CARGO_PKG_VERSION"));
+    Metrics::set_build_info();
 
     // Update connection status
     bridge_state.set_connection_status(ConnectionStatus::Connecting);
@@ -229,7 +633,10 @@ pub async fn start_bridge(
         .build()
         .map_err(|e| ComelitClientError::Generic(e.to_string()))?;
 
-    let updater = Arc::new(Updater::new(bridge_state.clone()));
+    let updater = Arc::new(Updater::new(
+        bridge_state.clone(),
+        settings.live_updates.clone(),
+    ));
     let client = ComelitClient::new(options, Some(updater.clone())).await?;
 
     // Set the hub host in state
@@ -240,19 +647,12 @@ pub async fn start_bridge(
     if let Ok(ping_task) = client.login(State::Disconnected).await {
         info!("Login successful");
         bridge_state.set_connection_status(ConnectionStatus::Connected);
+        bridge_state.set_error(None);
+        bridge_state.set_client(Some(client.clone()));
         Metrics::set_connected(true);
 
         let bridge_name = "ComelitHUB-HK";
-        let bridge = BridgeAccessory::new(
-            1,
-            AccessoryInformation {
-                name: bridge_name.into(),
-                serial_number: "20003150".into(),
-                manufacturer: "Comelit".into(),
-                model: "20003150".into(),
-                ..Default::default()
-            },
-        )?;
+        let bridge = BridgeAccessory::new(1, bridge_information(bridge_name, client.hub()))?;
 
         let mut storage = FileStorage::current_dir().await?;
 
@@ -306,6 +706,12 @@ pub async fn start_bridge(
         info!("IP server created, adding bridge accessory...");
         server.add_accessory(bridge).await?;
 
+        let mut bridges = vec![HapBridge::new(None, server)];
+        for bridge_settings in &settings.bridges {
+            info!("Creating additional bridge {}...", bridge_settings.name);
+            bridges.push(create_additional_bridge(bridge_settings, client.hub()).await?);
+        }
+
         info!("Fetching device index...");
         let index = client
             .fetch_index(1)
@@ -323,6 +729,8 @@ pub async fn start_bridge(
         let mut window_coverings = vec![];
         let mut doors = vec![];
         let mut bells = vec![];
+        let mut others = vec![];
+        let mut suppliers = vec![];
         for (_, v) in index.clone().into_iter() {
             match v {
                 HomeDeviceData::Light(light) => {
@@ -334,6 +742,12 @@ pub async fn start_bridge(
                 HomeDeviceData::Thermostat(thermo) => {
                     thermostats.push(thermo.clone());
                 }
+                HomeDeviceData::Other(other) => {
+                    others.push(other.clone());
+                }
+                HomeDeviceData::Supplier(supplier) => {
+                    suppliers.push(supplier.clone());
+                }
                 _ => {}
             }
         }
@@ -353,13 +767,88 @@ pub async fn start_bridge(
         window_coverings.sort_by_key(|wc| wc.id.clone());
         thermostats.sort_by_key(|t| t.id.clone());
         doors.sort_by_key(|t| t.id.clone());
+        bells.sort_by_key(|b| b.id.clone());
+        others.sort_by_key(|o| o.data.id.clone());
+
+        // Apply the names configured in the settings, taking precedence over room naming
+        let rename = |id: &str, description: &mut Option<String>| {
+            if let Some(name) = settings.names.get(id) {
+                *description = Some(name.clone());
+            }
+        };
+
+        info!("Fetching rooms...");
+        let rooms = client.fetch_rooms(1).await.unwrap_or_else(|e| {
+            warn!("Failed to fetch rooms, device rooms will be unknown: {}", e);
+            Default::default()
+        });
+        bridge_state.set_index(
+            index
+                .iter()
+                .chain(external_index.iter())
+                .map(|device| index_entry(device.value(), &rooms))
+                .collect(),
+        );
+
+        if let Some(naming) = settings.name_with_room {
+            let add_room = |id: &str, description: &mut Option<String>| {
+                if let Some(room) = rooms.get(id) {
+                    let name = description.clone().unwrap_or_else(|| id.to_string());
+                    *description = Some(name_with_room(naming, room, &name));
+                }
+            };
+            lights
+                .iter_mut()
+                .for_each(|l| add_room(&l.id, &mut l.description));
+            window_coverings
+                .iter_mut()
+                .for_each(|wc| add_room(&wc.id, &mut wc.description));
+            thermostats
+                .iter_mut()
+                .for_each(|t| add_room(&t.id, &mut t.description));
+            others
+                .iter_mut()
+                .for_each(|o| add_room(&o.data.id, &mut o.data.description));
+        }
+
+        lights
+            .iter_mut()
+            .for_each(|l| rename(&l.id, &mut l.description));
+        window_coverings
+            .iter_mut()
+            .for_each(|wc| rename(&wc.id, &mut wc.description));
+        thermostats
+            .iter_mut()
+            .for_each(|t| rename(&t.id, &mut t.description));
+        doors
+            .iter_mut()
+            .for_each(|d| rename(&d.id, &mut d.description));
+        bells
+            .iter_mut()
+            .for_each(|b| rename(&b.id, &mut b.description));
+        others
+            .iter_mut()
+            .for_each(|o| rename(&o.data.id, &mut o.data.description));
+
+        let room = |id: &str| rooms.get(id).map(String::as_str);
 
-        let mut i: u64 = 1;
         for light in lights {
             if settings.mount_lights.unwrap_or_default() {
-                i += 1;
-                info!("Adding light device: {} with id {i}", light.id);
-                match ComelitLightbulbAccessory::new(i, &light, client.clone(), &server).await {
+                let bridge = select_bridge(
+                    &mut bridges,
+                    BridgeDeviceKind::Light,
+                    &light.id,
+                    room(&light.id),
+                );
+                let i = bridge.next_id();
+                info!(
+                    "Adding light device: {} with id {i} to bridge {}",
+                    light.id,
+                    bridge.name()
+                );
+                match ComelitLightbulbAccessory::new(i, &light, client.clone(), &bridge.server)
+                    .await
+                {
                     Ok(accessory) => {
                         info!("Light {} added to the hub", accessory.get_comelit_id());
 
@@ -391,19 +880,27 @@ pub async fn start_bridge(
 
         for window_covering in window_coverings {
             if settings.mount_window_covering.unwrap_or_default() {
-                i += 1;
+                let bridge = select_bridge(
+                    &mut bridges,
+                    BridgeDeviceKind::WindowCovering,
+                    &window_covering.id,
+                    room(&window_covering.id),
+                );
+                let i = bridge.next_id();
                 info!(
-                    "Adding window covering device: {} with id {i}",
-                    window_covering.id
+                    "Adding window covering device: {} with id {i} to bridge {}",
+                    window_covering.id,
+                    bridge.name()
                 );
                 match ComelitWindowCoveringAccessory::new(
                     i,
                     &window_covering,
                     client.clone(),
-                    &server,
+                    &bridge.server,
                     WindowCoveringConfig {
                         closing_time: Duration::from_secs(settings.window_covering.closing_time),
                         opening_time: Duration::from_secs(settings.window_covering.opening_time),
+                        live_updates: settings.live_updates.window_coverings,
                     },
                 )
                 .await
@@ -440,9 +937,25 @@ pub async fn start_bridge(
 
         for thermostat in thermostats {
             if settings.mount_thermo.unwrap_or_default() {
-                i += 1;
-                info!("Adding thermostat device: {} with id {i}", thermostat.id);
-                match ComelitThermostatAccessory::new(i, &thermostat, client.clone(), &server).await
+                let bridge = select_bridge(
+                    &mut bridges,
+                    BridgeDeviceKind::Thermostat,
+                    &thermostat.id,
+                    room(&thermostat.id),
+                );
+                let i = bridge.next_id();
+                info!(
+                    "Adding thermostat device: {} with id {i} to bridge {}",
+                    thermostat.id,
+                    bridge.name()
+                );
+                match ComelitThermostatAccessory::new(
+                    i,
+                    &thermostat,
+                    client.clone(),
+                    &bridge.server,
+                )
+                .await
                 {
                     Ok(accessory) => {
                         info!("Thermostat {} added to the hub", accessory.get_comelit_id());
@@ -473,14 +986,26 @@ pub async fn start_bridge(
 
         for door in doors {
             if settings.mount_doors.unwrap_or_default() {
-                i += 1;
-                info!("Adding door device: {} with id {i}", door.id);
-                let data = client.info::<DoorDeviceData>(&door.id, 1).await?;
+                let bridge = select_bridge(
+                    &mut bridges,
+                    BridgeDeviceKind::Door,
+                    &door.id,
+                    room(&door.id),
+                );
+                let i = bridge.next_id();
+                info!(
+                    "Adding door device: {} with id {i} to bridge {}",
+                    door.id,
+                    bridge.name()
+                );
+                let mut data = client.info::<DoorDeviceData>(&door.id, 1).await?;
+                let door_data = data.first_mut().unwrap();
+                rename(&door_data.id, &mut door_data.description);
                 match ComelitDoorAccessory::new(
                     i,
-                    data.first().unwrap(),
+                    door_data,
                     client.clone(),
-                    &server,
+                    &bridge.server,
                     DoorConfig {
                         opening_closing_time: Duration::from_secs(
                             settings.door.opening_closing_time,
@@ -513,12 +1038,68 @@ pub async fn start_bridge(
             }
         }
 
+        for other in others {
+            if settings.mount_others.unwrap_or_default() {
+                let bridge = select_bridge(
+                    &mut bridges,
+                    BridgeDeviceKind::Switch,
+                    &other.data.id,
+                    room(&other.data.id),
+                );
+                let i = bridge.next_id();
+                info!(
+                    "Adding switch device: {} with id {i} to bridge {}",
+                    other.data.id,
+                    bridge.name()
+                );
+                match ComelitSwitchAccessory::new(i, &other, client.clone(), &bridge.server).await {
+                    Ok(accessory) => {
+                        info!("Switch {} added to the hub", accessory.get_comelit_id());
+
+                        // Register device in bridge state
+                        bridge_state.register_device(DeviceInfo {
+                            id: accessory.get_comelit_id().to_string(),
+                            name: accessory.name.clone(),
+                            device_type: DeviceType::Switch,
+                            status: match other.data.status {
+                                Some(DeviceStatus::On) | Some(DeviceStatus::Running) => {
+                                    "on".to_string()
+                                }
+                                _ => "off".to_string(),
+                            },
+                            last_update: None,
+                        });
+
+                        updater
+                            .switches
+                            .insert(accessory.get_comelit_id().to_string(), accessory);
+                    }
+                    Err(err) => error!("Failed to add switch device: {}", err),
+                }
+            }
+        }
+
+        // Energy meters are not exposed to HomeKit, only to the web UI
+        for supplier in suppliers {
+            bridge_state.register_device(DeviceInfo {
+                name: supplier
+                    .description
+                    .clone()
+                    .unwrap_or_else(|| supplier.id.clone()),
+                id: supplier.id,
+                device_type: DeviceType::Meter,
+                status: String::new(),
+                last_update: None,
+            });
+        }
+
         for (bell_index, bell) in bells.iter().enumerate() {
             if settings.mount_doorbells.unwrap_or_default() {
-                i += 1;
+                let i = bridges[0].next_id();
                 info!("Adding doorbell device: {} with id {i}", bell.id);
-                let data = client.info::<DoorbellDeviceData>(&bell.id, 1).await?;
-                let bell_data = data.first().unwrap();
+                let mut data = client.info::<DoorbellDeviceData>(&bell.id, 1).await?;
+                let bell_data = data.first_mut().unwrap();
+                rename(&bell_data.id, &mut bell_data.description);
 
                 // Each doorbell needs its own standalone HAP server with VideoDoorbell category.
                 // iOS does not support bridged VIDEO_DOORBELL accessories — this mirrors
@@ -601,9 +1182,10 @@ pub async fn start_bridge(
         Metrics::set_device_count("window_covering", updater.window_coverings.len());
         Metrics::set_device_count("door", updater.doors.len());
         Metrics::set_device_count("doorbell", updater.doorbells.len());
+        Metrics::set_device_count("switch", updater.switches.len());
 
-        info!("Starting HAP bridge server...");
-        let handle = server.run_handle();
+        info!("Starting {} HAP bridge server(s)...", bridges.len());
+        let handle = futures::future::select_all(bridges.iter().map(|b| b.server.run_handle()));
 
         // Generate and display QR code
         let code = QrCode::new(url.as_bytes())?;
@@ -618,16 +1200,29 @@ pub async fn start_bridge(
         info!("Subscribing to root device updates...");
         client.subscribe(ROOT_ID).await?;
 
-        // Clone bridge_state for the ping monitoring task
-        let ping_state = bridge_state.clone();
-
-        // Wrap ping_task to record metrics
-        let monitored_ping_task = async move {
-            // The ping_task from the client
-            let _ = ping_task.await;
-            // When it completes, record failure
-            ping_state.record_ping(false);
-            Metrics::record_ping(false);
+        // Spawn a task logging in again when the connection to the hub is lost, while
+        // the HAP servers keep reporting the devices as not responding
+        let connection = {
+            let lost = {
+                let updater = updater.clone();
+                let bridge_state = bridge_state.clone();
+                move || {
+                    warn!("Lost connection to Comelit hub (ping failure), reconnecting...");
+                    updater.set_all_faults();
+                    bridge_state.record_ping(false);
+                    Metrics::record_ping(false);
+                    bridge_state.set_client(None);
+                    bridge_state.set_connection_status(ConnectionStatus::Disconnected);
+                    bridge_state.set_error(Some("Lost connection to Comelit hub".to_string()));
+                    Metrics::set_connected(false);
+                }
+            };
+            let client = client.clone();
+            let updater = updater.clone();
+            let bridge_state = bridge_state.clone();
+            tokio::spawn(keep_connected(ping_task, lost, move || {
+                reconnect(client.clone(), updater.clone(), bridge_state.clone())
+            }))
         };
 
         // Spawn a task to periodically record successful pings while connected
@@ -643,6 +1238,20 @@ pub async fn start_bridge(
             }
         });
 
+        // Spawn a task ringing the doorbells on the calls received by the intercom
+        let intercom = settings.viper.as_ref().map(|viper| {
+            let updater = updater.clone();
+            let doorbell = viper.doorbell.clone();
+            let (listener, mut calls) = Intercom::spawn(viper);
+            let task = tokio::spawn(async move {
+                while let Some(from) = calls.recv().await {
+                    info!("Intercom call from {}", from);
+                    updater.ring_doorbells(doorbell.as_deref()).await;
+                }
+            });
+            (listener, task)
+        });
+
         let ctrl_c = async {
             signal::ctrl_c()
                 .await
@@ -660,40 +1269,124 @@ pub async fn start_bridge(
         #[cfg(not(unix))]
         let terminate = std::future::pending::<()>();
 
-        tokio::select! {
-            _ = monitored_ping_task => {
-                warn!("Ping task exited: lost connection to Comelit hub");
+        let result = tokio::select! {
+            _ = handle => {
+                warn!("HAP server exited unexpectedly");
                 bridge_state.set_connection_status(ConnectionStatus::Disconnected);
                 Metrics::set_connected(false);
                 let _ = client.disconnect().await;
-                Err(anyhow::anyhow!("Lost connection to Comelit hub (ping failure)"))
+                Err(anyhow::anyhow!("HAP server exited unexpectedly"))
             }
-            _ = handle => {
-                warn!("HAP server exited unexpectedly");
+            _ = bridge_state.reload_requested() => {
+                info!("Settings changed, stopping bridge to apply them");
                 bridge_state.set_connection_status(ConnectionStatus::Disconnected);
                 Metrics::set_connected(false);
                 let _ = client.disconnect().await;
-                Err(anyhow::anyhow!("HAP server exited unexpectedly"))
+                Ok(BridgeExit::Reload)
             }
             _ = ctrl_c => {
                 info!("signal received, starting graceful shutdown");
                 bridge_state.set_connection_status(ConnectionStatus::Disconnected);
                 Metrics::set_connected(false);
                 let _ = client.disconnect().await;
-                Ok(())
+                Ok(BridgeExit::Shutdown)
             },
             _ = terminate => {
                 info!("signal received, starting graceful shutdown");
                 bridge_state.set_connection_status(ConnectionStatus::Disconnected);
                 Metrics::set_connected(false);
                 let _ = client.disconnect().await;
-                Ok(())
+                Ok(BridgeExit::Shutdown)
             },
+        };
+
+        connection.abort();
+        if let Some((listener, task)) = intercom {
+            task.abort();
+            // Close the connection to the intercom before connecting again on reload
+            let _ = tokio::task::spawn_blocking(move || listener.stop()).await;
         }
+        bridge_state.set_client(None);
+        result
     } else {
         bridge_state.set_connection_status(ConnectionStatus::Error);
-        bridge_state.set_error(Some("Login failed".to_string()));
         Metrics::set_connected(false);
         Err(ComelitClientError::Login("Login failed".to_string()).into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Wait until `condition` holds, giving the spawned tasks a chance to run.
+    async fn wait_until(condition: impl Fn() -> bool) {
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while !condition() {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("condition not met");
+    }
+
+    /// Reconnection of the test: waits for the hub to be back, then refreshes the device.
+    async fn hub_back(
+        hub_up: Arc<tokio::sync::Notify>,
+        reachability: Reachability,
+    ) -> JoinHandle<()> {
+        hub_up.notified().await;
+        reachability.set_fault(false);
+        tokio::spawn(std::future::pending())
+    }
+
+    #[tokio::test]
+    async fn test_fault_while_hub_down() {
+        let reachability = Reachability::default();
+        let (hub_down, ping_failure) = tokio::sync::oneshot::channel::<()>();
+        let hub_up = Arc::new(tokio::sync::Notify::new());
+
+        // The ping task ends when the hub stops answering
+        let ping_task = tokio::spawn(async move {
+            let _ = ping_failure.await;
+        });
+        let lost = {
+            let reachability = reachability.clone();
+            move || reachability.set_fault(true)
+        };
+        let reconnect = {
+            let reachability = reachability.clone();
+            let hub_up = hub_up.clone();
+            move || hub_back(hub_up.clone(), reachability.clone())
+        };
+        let connection = tokio::spawn(keep_connected(ping_task, lost, reconnect));
+        assert!(reachability.check("DOM#LT#1.1").is_ok());
+
+        // HomeKit reads fail while the hub is down, and the bridge keeps running
+        hub_down.send(()).unwrap();
+        wait_until(|| reachability.is_fault()).await;
+        assert!(reachability.check("DOM#LT#1.1").is_err());
+        assert!(!connection.is_finished());
+
+        hub_up.notify_one();
+        wait_until(|| !reachability.is_fault()).await;
+        assert!(!connection.is_finished());
+        connection.abort();
+    }
+
+    #[test]
+    fn test_name_with_room() {
+        assert_eq!(
+            name_with_room(RoomNaming::Prefix, "Cucina", "Luce soffitto"),
+            "Cucina - Luce soffitto"
+        );
+        assert_eq!(
+            name_with_room(RoomNaming::Suffix, "Cucina", "Luce soffitto"),
+            "Luce soffitto - Cucina"
+        );
+        assert_eq!(
+            name_with_room(RoomNaming::Prefix, "Cucina", "Luce cucina"),
+            "Luce cucina"
+        );
+    }
+}
@@ -0,0 +1,216 @@
+//! gRPC control service, mirroring the REST API of the web server.
+//!
+//! The service is defined in `proto/comelit.proto`: integrators can list and get
+//! devices, send commands and stream the event log instead of polling over HTTP.
+
+use crate::web::control::{self, ControlError, DeviceCommand};
+use crate::web::state::{BridgeState, DeviceInfo, LogEvent};
+use futures::Stream;
+use proto::comelit_bridge_server::{ComelitBridge, ComelitBridgeServer};
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::time::SystemTime;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+use tonic::{Request, Response, Status};
+use tracing::{error, info, warn};
+
+pub mod proto {
+    tonic::include_proto!("comelit.v1");
+}
+
+/// Start serving the gRPC service on the given address.
+pub fn spawn(addr: SocketAddr, state: BridgeState) -> JoinHandle<()> {
+    info!("Starting gRPC server on {}", addr);
+    tokio::spawn(async move {
+        let service = ComelitBridgeServer::new(BridgeService { state });
+        if let Err(e) = tonic::transport::Server::builder()
+            .add_service(service)
+            .serve(addr)
+            .await
+        {
+            error!("gRPC server error: {}", e);
+        }
+    })
+}
+
+/// Implementation of the `ComelitBridge` service over the bridge state.
+struct BridgeService {
+    state: BridgeState,
+}
+
+#[tonic::async_trait]
+impl ComelitBridge for BridgeService {
+    async fn list_devices(
+        &self,
+        request: Request<proto::ListDevicesRequest>,
+    ) -> Result<Response<proto::ListDevicesResponse>, Status> {
+        let device_type = request.into_inner().r#type;
+        let mut devices: Vec<proto::Device> = self
+            .state
+            .devices()
+            .iter()
+            .filter(|d| device_type.is_empty() || d.device_type.as_str() == device_type)
+            .map(device)
+            .collect();
+        devices.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(Response::new(proto::ListDevicesResponse { devices }))
+    }
+
+    async fn get_device(
+        &self,
+        request: Request<proto::GetDeviceRequest>,
+    ) -> Result<Response<proto::Device>, Status> {
+        let id = request.into_inner().id;
+        match self.state.device(&id) {
+            Some(info) => Ok(Response::new(device(&info))),
+            None => Err(status(ControlError::NotFound(id))),
+        }
+    }
+
+    async fn send_action(
+        &self,
+        request: Request<proto::SendActionRequest>,
+    ) -> Result<Response<proto::SendActionResponse>, Status> {
+        let request = request.into_inner();
+        let command = command(request.action(), request.value)
+            .ok_or_else(|| Status::invalid_argument("Missing action"))?;
+        match control::send_command(&self.state, &request.id, command).await {
+            Ok(()) => Ok(Response::new(proto::SendActionResponse {})),
+            Err(e) => {
+                warn!(
+                    "Failed to run {:?} on device {} from gRPC: {}",
+                    command, request.id, e
+                );
+                Err(status(e))
+            }
+        }
+    }
+
+    type StreamUpdatesStream =
+        Pin<Box<dyn Stream<Item = Result<proto::DeviceUpdate, Status>> + Send>>;
+
+    async fn stream_updates(
+        &self,
+        request: Request<proto::StreamUpdatesRequest>,
+    ) -> Result<Response<Self::StreamUpdatesStream>, Status> {
+        let device_ids = request.into_inner().device_ids;
+        let events = self.state.subscribe_events();
+        let state = self.state.clone();
+        let stream = futures::stream::unfold(events, move |mut events| {
+            let state = state.clone();
+            let device_ids = device_ids.clone();
+            async move {
+                loop {
+                    let event = match events.recv().await {
+                        Ok(event) => event,
+                        Err(RecvError::Lagged(missed)) => {
+                            warn!("gRPC client too slow, skipped {missed} events");
+                            continue;
+                        }
+                        Err(RecvError::Closed) => return None,
+                    };
+                    let wanted = device_ids.is_empty()
+                        || event
+                            .device_id
+                            .as_ref()
+                            .is_some_and(|id| device_ids.contains(id));
+                    if wanted {
+                        return Some((Ok(update(&state, &event)), events));
+                    }
+                }
+            }
+        });
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
+
+/// Message of a device.
+fn device(device: &DeviceInfo) -> proto::Device {
+    proto::Device {
+        id: device.id.clone(),
+        name: device.name.clone(),
+        r#type: device.device_type.as_str().to_string(),
+        status: device.status.clone(),
+        last_update_seconds_ago: device.last_update.map(|t| t.elapsed().as_secs()),
+    }
+}
+
+/// Message of an event of the event log.
+fn update(state: &BridgeState, event: &LogEvent) -> proto::DeviceUpdate {
+    proto::DeviceUpdate {
+        event: event.kind.as_str().to_string(),
+        message: event.message.clone(),
+        timestamp: event
+            .time
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0),
+        device: event
+            .device_id
+            .as_deref()
+            .and_then(|id| state.device(id))
+            .map(|d| device(&d)),
+    }
+}
+
+/// Command of an action, `None` when the action is not set.
+fn command(action: proto::Action, value: f64) -> Option<DeviceCommand> {
+    match action {
+        proto::Action::Unspecified => None,
+        proto::Action::Toggle => Some(DeviceCommand::Toggle),
+        proto::Action::On => Some(DeviceCommand::On),
+        proto::Action::Off => Some(DeviceCommand::Off),
+        proto::Action::Open => Some(DeviceCommand::Open),
+        proto::Action::Close => Some(DeviceCommand::Close),
+        proto::Action::SetTarget => Some(DeviceCommand::SetTarget { value }),
+    }
+}
+
+/// Map a control error to a gRPC status, like the REST API maps it to an HTTP status.
+fn status(error: ControlError) -> Status {
+    let message = error.to_string();
+    match error {
+        ControlError::NotFound(_) => Status::not_found(message),
+        ControlError::NotConnected => Status::unavailable(message),
+        ControlError::Unsupported(_) => Status::invalid_argument(message),
+        ControlError::Client(_) => Status::internal(message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::state::DeviceType;
+
+    #[test]
+    fn test_command() {
+        assert_eq!(command(proto::Action::Unspecified, 0.0), None);
+        assert_eq!(command(proto::Action::On, 0.0), Some(DeviceCommand::On));
+        assert_eq!(
+            command(proto::Action::SetTarget, 21.5),
+            Some(DeviceCommand::SetTarget { value: 21.5 })
+        );
+    }
+
+    #[test]
+    fn test_update() {
+        let state = BridgeState::new();
+        state.register_device(DeviceInfo {
+            id: "light1".to_string(),
+            name: "Kitchen".to_string(),
+            device_type: DeviceType::Light,
+            status: "off".to_string(),
+            last_update: None,
+        });
+        let mut events = state.subscribe_events();
+        state.update_device_status("light1", "on".to_string());
+
+        let update = update(&state, &events.try_recv().unwrap());
+        assert_eq!(update.event, "update");
+        let device = update.device.unwrap();
+        assert_eq!(device.r#type, "light");
+        assert_eq!(device.status, "on");
+        assert!(device.last_update_seconds_ago.is_some());
+    }
+}
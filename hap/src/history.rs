@@ -0,0 +1,169 @@
+//! Persistent history of temperature, humidity, power and on/off samples.
+//!
+//! Samples are stored in an embedded SQLite database so that the charts of the
+//! web UI survive restarts even without a Prometheus server. Samples older than
+//! the configured retention are pruned periodically.
+
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use rusqlite::{Connection, params};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// How often old samples are pruned from the database.
+pub const PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Samples of a metric for one device, as `(unix timestamp, value)` pairs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistorySeries {
+    /// Name of the device (or `total` for bridge-wide metrics).
+    pub name: String,
+    /// Samples, oldest first.
+    pub values: Vec<(i64, f64)>,
+}
+
+/// SQLite database of metric samples.
+#[derive(Debug)]
+pub struct HistoryStore {
+    conn: Mutex<Connection>,
+    retention: Duration,
+}
+
+impl HistoryStore {
+    /// Open (or create) the history database at the given path.
+    pub fn open(path: &Path, retention_days: u32) -> Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open history database {}", path.display()))?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS samples (
+                metric TEXT NOT NULL,
+                name TEXT NOT NULL,
+                timestamp INTEGER NOT NULL,
+                value REAL NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS samples_metric_timestamp ON samples (metric, timestamp);",
+        )?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+            retention: Duration::from_secs(u64::from(retention_days) * 86400),
+        })
+    }
+
+    /// Record a sample of a metric taken now.
+    pub fn record(&self, metric: &str, name: &str, value: f64) -> Result<()> {
+        self.record_at(metric, name, unix_now(), value)
+    }
+
+    fn record_at(&self, metric: &str, name: &str, timestamp: i64, value: f64) -> Result<()> {
+        self.conn.lock().execute(
+            "INSERT INTO samples (metric, name, timestamp, value) VALUES (?1, ?2, ?3, ?4)",
+            params![metric, name, timestamp, value],
+        )?;
+        Ok(())
+    }
+
+    /// Get the samples of a metric between `start` and `end` (unix timestamps),
+    /// averaged over buckets of `step` seconds, one series per device.
+    pub fn query(
+        &self,
+        metric: &str,
+        start: i64,
+        end: i64,
+        step: i64,
+    ) -> Result<Vec<HistorySeries>> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(
+            "SELECT name, (timestamp / ?4) * ?4 AS bucket, AVG(value) FROM samples
+             WHERE metric = ?1 AND timestamp BETWEEN ?2 AND ?3
+             GROUP BY name, bucket ORDER BY name, bucket",
+        )?;
+        let rows = stmt.query_map(params![metric, start, end, step.max(1)], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, f64>(2)?,
+            ))
+        })?;
+
+        let mut series: Vec<HistorySeries> = vec![];
+        for row in rows {
+            let (name, timestamp, value) = row?;
+            match series.last_mut() {
+                Some(last) if last.name == name => last.values.push((timestamp, value)),
+                _ => series.push(HistorySeries {
+                    name,
+                    values: vec![(timestamp, value)],
+                }),
+            }
+        }
+        Ok(series)
+    }
+
+    /// Delete the samples older than the retention, returning how many were removed.
+    pub fn prune(&self) -> Result<usize> {
+        let cutoff = unix_now() - self.retention.as_secs() as i64;
+        let removed = self
+            .conn
+            .lock()
+            .execute("DELETE FROM samples WHERE timestamp < ?1", params![cutoff])?;
+        Ok(removed)
+    }
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_query() {
+        let store = HistoryStore::open(Path::new(":memory:"), 30).unwrap();
+        store
+            .record_at("temperature", "Kitchen", 1000, 20.0)
+            .unwrap();
+        store
+            .record_at("temperature", "Kitchen", 1030, 22.0)
+            .unwrap();
+        store
+            .record_at("temperature", "Kitchen", 1060, 23.0)
+            .unwrap();
+        store
+            .record_at("temperature", "Bedroom", 1000, 18.0)
+            .unwrap();
+        store.record_at("humidity", "Kitchen", 1000, 55.0).unwrap();
+
+        let series = store.query("temperature", 1000, 1100, 60).unwrap();
+        assert_eq!(
+            series,
+            vec![
+                HistorySeries {
+                    name: "Bedroom".to_string(),
+                    values: vec![(960, 18.0)],
+                },
+                HistorySeries {
+                    name: "Kitchen".to_string(),
+                    values: vec![(960, 20.0), (1020, 22.5)],
+                },
+            ]
+        );
+        assert!(store.query("power", 0, i64::MAX, 60).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_prune() {
+        let store = HistoryStore::open(Path::new(":memory:"), 1).unwrap();
+        store.record_at("temperature", "Kitchen", 0, 20.0).unwrap();
+        store.record("temperature", "Kitchen", 21.0).unwrap();
+        assert_eq!(store.prune().unwrap(), 1);
+        assert_eq!(
+            store.query("temperature", 0, i64::MAX, 60).unwrap().len(),
+            1
+        );
+    }
+}
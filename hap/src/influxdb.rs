@@ -0,0 +1,199 @@
+//! Export of temperature, humidity, power and on/off samples to InfluxDB.
+//!
+//! Samples are formatted in the line protocol and written in batches through the
+//! InfluxDB v2 HTTP API, for long-term energy and climate dashboards. Points that
+//! cannot be written because the server is unreachable, overloaded or failing are
+//! kept and retried with the next batch, up to a limit; points the server rejects
+//! are dropped.
+
+use crate::settings::InfluxDbSettings;
+use reqwest::StatusCode;
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc;
+use tracing::{debug, error, info, warn};
+
+/// How often the pending points are written.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Number of pending points that triggers a write before the flush interval.
+const MAX_BATCH_POINTS: usize = 500;
+
+/// Number of points kept while the server is unreachable, the oldest are dropped first.
+const MAX_PENDING_POINTS: usize = 10_000;
+
+/// Capacity of the channel between the bridge and the writer task.
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// Timeout of each write request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Writes points to an InfluxDB v2 bucket from a background task.
+#[derive(Debug)]
+pub struct InfluxExporter {
+    tx: mpsc::Sender<String>,
+}
+
+impl InfluxExporter {
+    /// Start the writer task for the configured server.
+    pub fn spawn(settings: &InfluxDbSettings) -> Self {
+        let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+        info!(
+            "Exporting samples to InfluxDB bucket {} at {}",
+            settings.bucket, settings.url
+        );
+        tokio::spawn(writer(settings.clone(), rx));
+        Self { tx }
+    }
+
+    /// Write a point of `measurement` with the given tags and a `value` field.
+    pub fn write(&self, measurement: &str, tags: &[(&str, &str)], value: f64) {
+        let timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let line = line(measurement, tags, value, timestamp);
+        if self.tx.try_send(line).is_err() {
+            debug!("InfluxDB exporter queue full, dropping {measurement} point");
+        }
+    }
+}
+
+/// Format a point in the line protocol.
+fn line(measurement: &str, tags: &[(&str, &str)], value: f64, timestamp: u64) -> String {
+    let mut line = escape(measurement, &[',', ' ']);
+    for (key, tag) in tags {
+        line.push(',');
+        line.push_str(&escape(key, &[',', '=', ' ']));
+        line.push('=');
+        line.push_str(&escape(tag, &[',', '=', ' ']));
+    }
+    format!("{line} value={value} {timestamp}")
+}
+
+/// Escape the characters with a special meaning in the line protocol.
+fn escape(text: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c == '\\' || special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Collect the points and write them in batches.
+async fn writer(settings: InfluxDbSettings, mut rx: mpsc::Receiver<String>) {
+    let url = format!("{}/api/v2/write", settings.url.trim_end_matches('/'));
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .unwrap_or_default();
+    let mut pending: Vec<String> = Vec::new();
+    let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+    // While the server is failing, only retry on the flush interval
+    let mut failing = false;
+
+    loop {
+        tokio::select! {
+            point = rx.recv() => match point {
+                Some(point) => {
+                    pending.push(point);
+                    if failing || pending.len() < MAX_BATCH_POINTS {
+                        continue;
+                    }
+                }
+                None => break,
+            },
+            _ = interval.tick() => {}
+        }
+        if pending.is_empty() {
+            continue;
+        }
+
+        let result = client
+            .post(&url)
+            .query(&[
+                ("org", settings.org.as_str()),
+                ("bucket", settings.bucket.as_str()),
+                ("precision", "s"),
+            ])
+            .header("Authorization", format!("Token {}", settings.token))
+            .header("Content-Type", "text/plain; charset=utf-8")
+            .body(pending.join("\n"))
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status());
+        failing = result.is_err();
+        match result {
+            Ok(_) => {
+                debug!("Wrote {} points to InfluxDB", pending.len());
+                pending.clear();
+            }
+            Err(e) if retryable(e.status()) => {
+                warn!(
+                    "Failed to write {} points to InfluxDB: {}",
+                    pending.len(),
+                    e
+                );
+                if pending.len() > MAX_PENDING_POINTS {
+                    pending.drain(..pending.len() - MAX_PENDING_POINTS);
+                }
+            }
+            Err(e) => {
+                // Retrying a batch the server rejected would fail forever
+                error!(
+                    "InfluxDB rejected {} points, dropping them: {}",
+                    pending.len(),
+                    e
+                );
+                failing = false;
+                pending.clear();
+            }
+        }
+    }
+}
+
+/// Whether a write that failed with the given status (none for transport errors)
+/// may succeed later.
+fn retryable(status: Option<StatusCode>) -> bool {
+    status.is_none_or(|s| s.is_server_error() || s == StatusCode::TOO_MANY_REQUESTS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line() {
+        assert_eq!(
+            line(
+                "comelit_thermostat_temperature",
+                &[("device", "Living room")],
+                21.5,
+                1700000000
+            ),
+            r"comelit_thermostat_temperature,device=Living\ room value=21.5 1700000000"
+        );
+        assert_eq!(
+            line(
+                "comelit_device_state",
+                &[("device", "a=b,c"), ("type", "light")],
+                1.0,
+                1
+            ),
+            r"comelit_device_state,device=a\=b\,c,type=light value=1 1"
+        );
+    }
+
+    #[test]
+    fn test_retryable() {
+        assert!(retryable(None));
+        assert!(retryable(Some(StatusCode::INTERNAL_SERVER_ERROR)));
+        assert!(retryable(Some(StatusCode::SERVICE_UNAVAILABLE)));
+        assert!(retryable(Some(StatusCode::TOO_MANY_REQUESTS)));
+        assert!(!retryable(Some(StatusCode::BAD_REQUEST)));
+        assert!(!retryable(Some(StatusCode::UNAUTHORIZED)));
+        assert!(!retryable(Some(StatusCode::NOT_FOUND)));
+    }
+}
@@ -3,6 +3,9 @@
 //! This module provides a rolling file appender that handles log rotation
 //! internally, without requiring external tools like logrotate.
 //! This works natively on all platforms including macOS.
+//!
+//! With the `otel` feature, spans can also be exported to an OpenTelemetry
+//! collector (Jaeger, Tempo, ...) over OTLP/gRPC.
 
 use std::path::Path;
 use tracing_appender::non_blocking::WorkerGuard;
@@ -12,6 +15,14 @@ use tracing_subscriber::fmt::Layer;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 
+/// Service name reported with the exported spans.
+#[cfg(feature = "otel")]
+const OTEL_SERVICE_NAME: &str = "comelit-hub-hap";
+
+/// Layer exporting the spans to an OpenTelemetry collector.
+#[cfg(feature = "otel")]
+type OtelLayer<S> = tracing_opentelemetry::OpenTelemetryLayer<S, opentelemetry_sdk::trace::Tracer>;
+
 /// Rotation period for log files.
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum RotationPeriod {
@@ -84,17 +95,88 @@ impl Default for LogConfig {
 /// Keep this value alive for the duration of your program.
 pub struct LogGuard {
     _guards: Vec<WorkerGuard>,
+    _otel: Option<OtelGuard>,
+}
+
+/// Flushes the pending spans to the OpenTelemetry collector when dropped.
+#[cfg(feature = "otel")]
+struct OtelGuard(opentelemetry_sdk::trace::SdkTracerProvider);
+
+#[cfg(feature = "otel")]
+impl Drop for OtelGuard {
+    fn drop(&mut self) {
+        if let Err(e) = self.0.shutdown() {
+            eprintln!("Warning: failed to flush the OpenTelemetry spans: {e}");
+        }
+    }
+}
+
+#[cfg(not(feature = "otel"))]
+struct OtelGuard;
+
+/// Creates the layer exporting the spans to the OTLP collector at `endpoint`
+/// (e.g. `http://localhost:4317`), if set.
+#[cfg(feature = "otel")]
+fn otel_layer<S>(
+    endpoint: Option<&str>,
+) -> std::io::Result<(Option<OtelLayer<S>>, Option<OtelGuard>)>
+where
+    S: tracing::Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span>,
+{
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_otlp::WithExportConfig;
+
+    let Some(endpoint) = endpoint else {
+        return Ok((None, None));
+    };
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()
+        .map_err(std::io::Error::other)?;
+    let provider = opentelemetry_sdk::trace::SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            opentelemetry_sdk::Resource::builder()
+                .with_service_name(OTEL_SERVICE_NAME)
+                .build(),
+        )
+        .build();
+    let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer(OTEL_SERVICE_NAME));
+    Ok((Some(layer), Some(OtelGuard(provider))))
+}
+
+#[cfg(not(feature = "otel"))]
+fn otel_layer(
+    endpoint: Option<&str>,
+) -> std::io::Result<(
+    Option<tracing_subscriber::layer::Identity>,
+    Option<OtelGuard>,
+)> {
+    if endpoint.is_some() {
+        eprintln!(
+            "Warning: --otlp-endpoint is set but the bridge was built without the `otel` feature"
+        );
+    }
+    Ok((None, None))
 }
 
 /// Sets up console-only logging (stdout/stderr).
 ///
+/// Spans are also exported to the OTLP collector at `otlp_endpoint`, if set.
 /// Returns a guard that must be kept alive for the duration of the program.
-pub fn setup_console_logging() -> LogGuard {
-    tracing_subscriber::fmt()
-        .with_env_filter(EnvFilter::from_default_env())
+pub fn setup_console_logging(otlp_endpoint: Option<&str>) -> std::io::Result<LogGuard> {
+    let (otel, otel_guard) = otel_layer(otlp_endpoint)?;
+    tracing_subscriber::registry()
+        .with(EnvFilter::from_default_env())
+        .with(Layer::default())
+        .with(otel)
         .init();
 
-    LogGuard { _guards: vec![] }
+    Ok(LogGuard {
+        _guards: vec![],
+        _otel: otel_guard,
+    })
 }
 
 /// Sets up file-based logging with automatic rotation.
@@ -105,6 +187,7 @@ pub fn setup_console_logging() -> LogGuard {
 /// # Arguments
 ///
 /// * `config` - Configuration for the log files
+/// * `otlp_endpoint` - OTLP collector the spans are also exported to, if any
 ///
 /// # Returns
 ///
@@ -120,11 +203,14 @@ pub fn setup_console_logging() -> LogGuard {
 ///     rotation: RotationPeriod::Daily,
 ///     max_log_files: 7,
 /// };
-/// let _guard = setup_file_logging(config)?;
+/// let _guard = setup_file_logging(config, None)?;
 /// // ... application runs ...
 /// // guard is dropped here, flushing any remaining logs
 /// ```
-pub fn setup_file_logging(config: LogConfig) -> std::io::Result<LogGuard> {
+pub fn setup_file_logging(
+    config: LogConfig,
+    otlp_endpoint: Option<&str>,
+) -> std::io::Result<LogGuard> {
     let log_dir = Path::new(&config.log_dir);
 
     // Clean up old log files if max_log_files is set
@@ -153,13 +239,16 @@ pub fn setup_file_logging(config: LogConfig) -> std::io::Result<LogGuard> {
         .with_file(true)
         .with_line_number(true);
 
+    let (otel, otel_guard) = otel_layer(otlp_endpoint)?;
     tracing_subscriber::registry()
         .with(EnvFilter::from_default_env())
         .with(file_layer)
+        .with(otel)
         .init();
 
     Ok(LogGuard {
         _guards: vec![guard],
+        _otel: otel_guard,
     })
 }
 
@@ -171,11 +260,15 @@ pub fn setup_file_logging(config: LogConfig) -> std::io::Result<LogGuard> {
 /// # Arguments
 ///
 /// * `config` - Configuration for the log files
+/// * `otlp_endpoint` - OTLP collector the spans are also exported to, if any
 ///
 /// # Returns
 ///
 /// A guard that must be kept alive for the duration of the program.
-pub fn setup_dual_logging(config: LogConfig) -> std::io::Result<LogGuard> {
+pub fn setup_dual_logging(
+    config: LogConfig,
+    otlp_endpoint: Option<&str>,
+) -> std::io::Result<LogGuard> {
     let log_dir = Path::new(&config.log_dir);
 
     // Clean up old log files if max_log_files is set
@@ -210,14 +303,17 @@ pub fn setup_dual_logging(config: LogConfig) -> std::io::Result<LogGuard> {
         .with_target(true)
         .with_level(true);
 
+    let (otel, otel_guard) = otel_layer(otlp_endpoint)?;
     tracing_subscriber::registry()
         .with(EnvFilter::from_default_env())
         .with(file_layer)
         .with(console_layer)
+        .with(otel)
         .init();
 
     Ok(LogGuard {
         _guards: vec![file_guard],
+        _otel: otel_guard,
     })
 }
 
@@ -1,12 +1,26 @@
 mod accessories;
 mod bridge;
+#[cfg(feature = "grpc")]
+mod grpc;
+#[cfg(feature = "history")]
+mod history;
+#[cfg(feature = "influxdb")]
+mod influxdb;
 mod logging;
+mod model;
+mod mqtt;
+mod notifications;
+mod rules;
+mod secrets;
 mod settings;
+mod viper;
 mod web;
 
+use std::net::IpAddr;
+use std::path::PathBuf;
 use std::process::exit;
 
-pub use bridge::start_bridge;
+pub use bridge::{BridgeExit, start_bridge};
 
 use anyhow::Result;
 use clap::Parser;
@@ -16,15 +30,18 @@ use tracing::{info, warn};
 use web::WebConfig;
 use web::state::BridgeState;
 
+/// How long in-flight web requests are given to complete on shutdown.
+const WEB_SHUTDOWN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
 #[derive(Parser, Debug)]
 #[command(version)]
 pub struct Params {
-    /// User name for the Comelit Bridge (default: "admin")
-    #[clap(long, default_value = "admin")]
-    user: String,
-    /// Password for the Comelit Bridge (default: "admin")
-    #[clap(long, default_value = "admin")]
-    password: String,
+    /// User name for the Comelit Bridge (falls back to COMELIT_USER_FILE, COMELIT_USER, then "admin")
+    #[clap(long)]
+    user: Option<String>,
+    /// Password for the Comelit Bridge (falls back to COMELIT_PASSWORD_FILE, COMELIT_PASSWORD, then "admin")
+    #[clap(long)]
+    password: Option<String>,
     /// Hostname or IP address of the Comelit Bridge (if not set, it will scan the network to find it)
     #[clap(long)]
     host: Option<String>,
@@ -51,6 +68,9 @@ pub struct Params {
     /// Also output logs to console when file logging is enabled
     #[clap(long)]
     log_to_console: bool,
+    /// OTLP/gRPC endpoint the tracing spans are exported to, e.g. http://localhost:4317 (requires the `otel` feature)
+    #[clap(long)]
+    otlp_endpoint: Option<String>,
 
     // Web UI options
     /// Enable the web UI and metrics endpoint
@@ -59,6 +79,27 @@ pub struct Params {
     /// Port for the web UI and metrics endpoint (default: 8080)
     #[clap(long, default_value = "8080")]
     web_port: u16,
+    /// Address the web UI listens on, e.g. 127.0.0.1 for localhost only (default: 0.0.0.0)
+    #[clap(long, default_value = "0.0.0.0")]
+    web_bind_addr: IpAddr,
+    /// URL prefix of the web UI when served behind a reverse proxy, e.g. /comelit
+    #[clap(long, default_value = "")]
+    web_base_path: String,
+    /// Serve /metrics and /health on this separate port only (by default they are served with the UI)
+    #[clap(long)]
+    metrics_port: Option<u16>,
+    /// Requests per minute each client can send to the device control endpoints (0 = unlimited)
+    #[clap(long, default_value = "60")]
+    web_rate_limit: u32,
+    /// Log every web request (client, method, path, status and latency)
+    #[clap(long)]
+    web_access_log: bool,
+    /// Reverse proxies allowed to give the client address in X-Forwarded-For, comma separated
+    #[clap(long, value_delimiter = ',')]
+    web_trusted_proxies: Vec<IpAddr>,
+    /// Serve the gRPC control service on this port, on the web UI address (requires the `grpc` feature)
+    #[clap(long)]
+    grpc_port: Option<u16>,
 }
 
 #[tokio::main]
@@ -69,36 +110,48 @@ async fn main() -> Result<()> {
     let _log_guard = setup_logging(&params)?;
 
     // Load settings before starting the web server so prometheus_url is available
-    let settings = if let Some(path) = params.settings {
-        if let Ok(read_to_string) = std::fs::read_to_string(path) {
-            serde_json::from_str(&read_to_string)?
-        } else {
-            warn!("Failed to read settings file, using default settings");
-            Settings::default()
-        }
-    } else {
-        Settings::default()
-    };
+    let mut settings = load_settings(&params)?;
+
+    // Resolve secrets from the environment (or secret files) when not passed on the command line
+    let user = secrets::resolve(params.user.clone(), secrets::USER_VAR)?
+        .unwrap_or_else(|| "admin".to_string());
+    let password = secrets::resolve(params.password.clone(), secrets::PASSWORD_VAR)?
+        .unwrap_or_else(|| "admin".to_string());
 
     // Create shared bridge state
     let bridge_state = BridgeState::new();
+    setup_history(&settings, &bridge_state)?;
+    setup_influxdb(&settings, &bridge_state);
 
     // Start web server if enabled
     let web_config = WebConfig {
+        bind_addr: params.web_bind_addr,
         port: params.web_port,
+        base_path: params.web_base_path.clone(),
         enabled: params.web_enabled,
         prometheus_url: settings.prometheus_url.clone(),
         prometheus_token: settings.prometheus_token.clone(),
+        settings_path: params.settings.as_ref().map(PathBuf::from),
+        metrics_port: params.metrics_port,
+        rate_limit: Some(params.web_rate_limit).filter(|n| *n > 0),
+        access_log: params.web_access_log,
+        trusted_proxies: params.web_trusted_proxies.clone(),
+        language: settings.language,
     };
 
-    if web_config.enabled {
-        web::start_web_server(web_config, bridge_state.clone()).await?;
-    }
+    let web_server = web::start_web_server(web_config, bridge_state.clone()).await?;
+    setup_grpc(&params, &bridge_state);
 
     loop {
+        let notifier = notifications::spawn(&settings.webhooks, bridge_state.clone());
+        let rule_engine = rules::spawn(&settings.rules, bridge_state.clone());
+        let mqtt_mirror = settings
+            .mqtt
+            .as_ref()
+            .map(|mqtt| mqtt::spawn(mqtt, bridge_state.clone()));
         match start_bridge(
-            params.user.as_str(),
-            params.password.as_str(),
+            user.as_str(),
+            password.as_str(),
             params.host.clone(),
             params.port,
             settings.clone(),
@@ -106,19 +159,120 @@ async fn main() -> Result<()> {
         )
         .await
         {
-            Ok(_) => break,
+            Ok(BridgeExit::Shutdown) => break,
+            Ok(BridgeExit::Reload) => {
+                info!("Restarting bridge with the new settings");
+            }
             Err(e) => {
                 warn!("Bridge exited with error: {e:#}, reconnecting in 10s...");
+                bridge_state.set_error(Some(format!("{e:#}")));
                 tokio::time::sleep(std::time::Duration::from_secs(10)).await;
             }
         }
+        if let Some(notifier) = notifier {
+            notifier.abort();
+        }
+        if let Some(mqtt_mirror) = mqtt_mirror {
+            mqtt_mirror.abort();
+        }
+        if let Some(rule_engine) = rule_engine {
+            rule_engine.abort();
+        }
+        // Pick up changes made to the settings file while the bridge was running
+        match load_settings(&params) {
+            Ok(new_settings) => settings = new_settings,
+            Err(e) => warn!("Failed to reload settings, keeping the previous ones: {e:#}"),
+        }
     }
 
     info!("Bridge ended");
+    if let Some(web_server) = web_server {
+        web_server.shutdown(WEB_SHUTDOWN_TIMEOUT).await;
+    }
     drop(_log_guard);
     exit(0);
 }
 
+/// Load the settings file, applying the pairing PIN from the environment if set.
+fn load_settings(params: &Params) -> Result<Settings> {
+    let mut settings = Settings::load(params.settings.as_deref())?;
+    if let Some(pin) = secrets::resolve(None, secrets::PIN_VAR)? {
+        settings.pairing_code = secrets::parse_pin(&pin)?;
+    }
+    Ok(settings)
+}
+
+/// Open the history database configured in the settings and prune it periodically.
+#[cfg(feature = "history")]
+fn setup_history(settings: &Settings, bridge_state: &BridgeState) -> Result<()> {
+    let Some(history) = settings.history.as_ref() else {
+        return Ok(());
+    };
+    let store = std::sync::Arc::new(history::HistoryStore::open(
+        std::path::Path::new(&history.path),
+        history.retention_days,
+    )?);
+    info!(
+        "Recording history to {} ({} days retention)",
+        history.path, history.retention_days
+    );
+    bridge_state.set_history_store(Some(store.clone()));
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(history::PRUNE_INTERVAL);
+        loop {
+            interval.tick().await;
+            match store.prune() {
+                Ok(0) => {}
+                Ok(removed) => info!("Pruned {removed} old history samples"),
+                Err(e) => warn!("Failed to prune history: {e:#}"),
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(feature = "history"))]
+fn setup_history(settings: &Settings, _bridge_state: &BridgeState) -> Result<()> {
+    if settings.history.is_some() {
+        warn!("History is configured but the bridge was built without the `history` feature");
+    }
+    Ok(())
+}
+
+/// Start exporting the samples to the InfluxDB server configured in the settings.
+#[cfg(feature = "influxdb")]
+fn setup_influxdb(settings: &Settings, bridge_state: &BridgeState) {
+    if let Some(influxdb) = settings.influxdb.as_ref() {
+        let exporter = influxdb::InfluxExporter::spawn(influxdb);
+        bridge_state.set_influx_exporter(Some(std::sync::Arc::new(exporter)));
+    }
+}
+
+#[cfg(not(feature = "influxdb"))]
+fn setup_influxdb(settings: &Settings, _bridge_state: &BridgeState) {
+    if settings.influxdb.is_some() {
+        warn!("InfluxDB is configured but the bridge was built without the `influxdb` feature");
+    }
+}
+
+/// Start the gRPC control service, if a port is configured.
+#[cfg(feature = "grpc")]
+fn setup_grpc(params: &Params, bridge_state: &BridgeState) {
+    if let Some(port) = params.grpc_port {
+        grpc::spawn(
+            std::net::SocketAddr::new(params.web_bind_addr, port),
+            bridge_state.clone(),
+        );
+    }
+}
+
+#[cfg(not(feature = "grpc"))]
+fn setup_grpc(params: &Params, _bridge_state: &BridgeState) {
+    if params.grpc_port.is_some() {
+        warn!("--grpc-port is set but the bridge was built without the `grpc` feature");
+    }
+}
+
 fn setup_logging(params: &Params) -> Result<LogGuard> {
     match &params.log_dir {
         Some(log_dir) => {
@@ -139,14 +293,22 @@ fn setup_logging(params: &Params) -> Result<LogGuard> {
             std::fs::create_dir_all(log_dir)?;
 
             if params.log_to_console {
-                Ok(logging::setup_dual_logging(config)?)
+                Ok(logging::setup_dual_logging(
+                    config,
+                    params.otlp_endpoint.as_deref(),
+                )?)
             } else {
-                Ok(logging::setup_file_logging(config)?)
+                Ok(logging::setup_file_logging(
+                    config,
+                    params.otlp_endpoint.as_deref(),
+                )?)
             }
         }
         None => {
             // No log directory specified, log to console only
-            Ok(logging::setup_console_logging())
+            Ok(logging::setup_console_logging(
+                params.otlp_endpoint.as_deref(),
+            )?)
         }
     }
 }
@@ -0,0 +1,55 @@
+//! Types shared by the settings and the web server.
+//!
+//! The settings refer to these types (e.g. the event kinds a webhook filters
+//! on or the commands a rule sends), so they live here rather than in the web
+//! server.
+
+use serde::{Deserialize, Serialize};
+
+/// Command that can be sent to a device.
+///
+/// In the REST API commands are JSON objects tagged by `action`,
+/// e.g. `{"action": "on"}` or `{"action": "set_target", "value": 21.5}`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum DeviceCommand {
+    /// Switch a light or a switch on/off, or open a door.
+    Toggle,
+    /// Switch a light, a switch or a thermostat on.
+    On,
+    /// Switch a light, a switch or a thermostat off.
+    Off,
+    /// Open a window covering or a door.
+    Open,
+    /// Close a window covering.
+    Close,
+    /// Move a window covering to a position (0 closes, anything else opens)
+    /// or set the target temperature of a thermostat, in °C.
+    SetTarget { value: f64 },
+}
+
+/// Kind of entry in the event log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    /// A device changed status.
+    Update,
+    /// A door was opened.
+    DoorOpen,
+    /// A doorbell rang.
+    Ring,
+    /// The bridge reported an error.
+    Error,
+}
+
+impl EventKind {
+    /// Returns the event kind as a string for display.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::Update => "update",
+            EventKind::DoorOpen => "door_open",
+            EventKind::Ring => "ring",
+            EventKind::Error => "error",
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! Mirror of the device states on an external MQTT broker.
+//!
+//! The state of every registered device is published as JSON (retained) to
+//! `<prefix>/<id>/state`, and commands are accepted on `<prefix>/<id>/set`, so that
+//! Node-RED and other MQTT consumers can integrate without HomeKit. MQTT wildcards
+//! in the Comelit IDs are replaced by `_` in the topics (`DOM#LT#1.1` becomes
+//! `DOM_LT_1.1`).
+
+use crate::settings::MqttSettings;
+use crate::web::control::{self, DeviceCommand};
+use crate::web::state::{BridgeState, DeviceInfo};
+use rumqttc::{AsyncClient, Event, LastWill, MqttOptions, Packet, QoS};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// How often the states are checked for changes not announced in the event log
+/// (e.g. newly registered devices).
+const SWEEP_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Delay before reconnecting to the broker after an error.
+const RECONNECT_DELAY: Duration = Duration::from_secs(10);
+
+/// Start mirroring the device states to the configured broker.
+///
+/// Abort the returned task to stop the mirror, e.g. before applying new settings.
+pub fn spawn(settings: &MqttSettings, state: BridgeState) -> JoinHandle<()> {
+    let prefix = settings.topic_prefix.trim_end_matches('/').to_string();
+    let status_topic = format!("{prefix}/bridge/status");
+
+    let mut options = MqttOptions::new(&settings.client_id, &settings.host, settings.port);
+    options.set_keep_alive(Duration::from_secs(30));
+    options.set_last_will(LastWill::new(
+        &status_topic,
+        "offline",
+        QoS::AtLeastOnce,
+        true,
+    ));
+    if let Some(user) = &settings.user {
+        options.set_credentials(user, settings.password.clone().unwrap_or_default());
+    }
+    info!(
+        "Mirroring device states to mqtt://{}:{}/{}",
+        settings.host, settings.port, prefix
+    );
+
+    tokio::spawn(async move {
+        let (client, mut event_loop) = AsyncClient::new(options, 100);
+        let mut events = state.subscribe_events();
+        let mut sweep = tokio::time::interval(SWEEP_INTERVAL);
+        // Last payload published for each device, to only publish changes
+        let mut published: HashMap<String, String> = HashMap::new();
+
+        loop {
+            tokio::select! {
+                notification = event_loop.poll() => match notification {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        info!("Connected to the MQTT broker");
+                        let _ = client.try_subscribe(format!("{prefix}/+/set"), QoS::AtLeastOnce);
+                        let _ = client.try_publish(&status_topic, QoS::AtLeastOnce, true, "online");
+                        // Publish everything again, the broker may have lost the retained states
+                        published.clear();
+                        publish_changes(&client, &prefix, &state, state.devices(), &mut published);
+                    }
+                    Ok(Event::Incoming(Packet::Publish(publish))) => {
+                        handle_command(&state, &prefix, &publish.topic, &publish.payload);
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        warn!("MQTT mirror connection error: {}, retrying in {:?}", e, RECONNECT_DELAY);
+                        tokio::time::sleep(RECONNECT_DELAY).await;
+                    }
+                },
+                event = events.recv() => {
+                    // Missed events are caught up by the next sweep
+                    if let Ok(event) = event
+                        && let Some(device) = event.device_id.and_then(|id| state.device(&id))
+                    {
+                        publish_changes(&client, &prefix, &state, vec![device], &mut published);
+                    }
+                }
+                _ = sweep.tick() => {
+                    publish_changes(&client, &prefix, &state, state.devices(), &mut published);
+                }
+            }
+        }
+    })
+}
+
+/// Topic segment of a device: its ID with the MQTT wildcards and separators replaced.
+fn topic_id(id: &str) -> String {
+    id.replace(['#', '+', '/'], "_")
+}
+
+/// JSON state published for a device, with the raw fields of its last hub update.
+fn state_payload(state: &BridgeState, device: &DeviceInfo) -> String {
+    serde_json::json!({
+        "id": device.id,
+        "name": device.name,
+        "type": device.device_type.as_str(),
+        "status": device.status,
+        "fields": state.device_fields(&device.id),
+    })
+    .to_string()
+}
+
+/// Publish the state of the devices that changed since they were last published.
+fn publish_changes(
+    client: &AsyncClient,
+    prefix: &str,
+    state: &BridgeState,
+    devices: Vec<DeviceInfo>,
+    published: &mut HashMap<String, String>,
+) {
+    for device in devices {
+        let payload = state_payload(state, &device);
+        if published.get(&device.id) == Some(&payload) {
+            continue;
+        }
+        let topic = format!("{prefix}/{}/state", topic_id(&device.id));
+        match client.try_publish(&topic, QoS::AtLeastOnce, true, payload.clone()) {
+            Ok(()) => {
+                published.insert(device.id, payload);
+            }
+            // Not recorded as published, so it is retried by the next sweep
+            Err(e) => debug!("Failed to publish {}: {}", topic, e),
+        }
+    }
+}
+
+/// Parse a command received on a `set` topic.
+///
+/// Accepts the JSON commands of the REST API (`{"action": "on"}`), the plain
+/// actions `on`, `off`, `toggle`, `open` and `close`, or a number for `set_target`.
+fn parse_command(payload: &[u8]) -> Option<DeviceCommand> {
+    let text = std::str::from_utf8(payload).ok()?.trim();
+    if let Ok(command) = serde_json::from_str(text) {
+        return Some(command);
+    }
+    match text.to_ascii_lowercase().as_str() {
+        "toggle" => Some(DeviceCommand::Toggle),
+        "on" => Some(DeviceCommand::On),
+        "off" => Some(DeviceCommand::Off),
+        "open" => Some(DeviceCommand::Open),
+        "close" => Some(DeviceCommand::Close),
+        other => other
+            .parse()
+            .ok()
+            .map(|value| DeviceCommand::SetTarget { value }),
+    }
+}
+
+/// Send the command received on `<prefix>/<id>/set` to the device.
+fn handle_command(state: &BridgeState, prefix: &str, topic: &str, payload: &[u8]) {
+    let Some(id) = topic
+        .strip_prefix(prefix)
+        .and_then(|t| t.strip_prefix('/'))
+        .and_then(|t| t.strip_suffix("/set"))
+    else {
+        return;
+    };
+    let Some(device) = state.devices().into_iter().find(|d| topic_id(&d.id) == id) else {
+        warn!("MQTT command for unknown device {}", id);
+        return;
+    };
+    let Some(command) = parse_command(payload) else {
+        warn!(
+            "Invalid MQTT command for {}: {}",
+            device.id,
+            String::from_utf8_lossy(payload)
+        );
+        return;
+    };
+
+    let state = state.clone();
+    tokio::spawn(async move {
+        match control::send_command(&state, &device.id, command).await {
+            Ok(()) => info!("Sent {:?} to {} from MQTT", command, device.id),
+            Err(e) => warn!(
+                "Failed to run {:?} on {} from MQTT: {}",
+                command, device.id, e
+            ),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topic_id() {
+        assert_eq!(topic_id("DOM#LT#1.1"), "DOM_LT_1.1");
+        assert_eq!(topic_id("a+b/c"), "a_b_c");
+    }
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(parse_command(b"ON"), Some(DeviceCommand::On));
+        assert_eq!(parse_command(b" toggle\n"), Some(DeviceCommand::Toggle));
+        assert_eq!(
+            parse_command(br#"{"action": "set_target", "value": 21.5}"#),
+            Some(DeviceCommand::SetTarget { value: 21.5 })
+        );
+        assert_eq!(
+            parse_command(b"50"),
+            Some(DeviceCommand::SetTarget { value: 50.0 })
+        );
+        assert_eq!(parse_command(b"dance"), None);
+    }
+}
@@ -0,0 +1,150 @@
+//! Webhook notifications of bridge events.
+//!
+//! Every entry added to the event log (doorbell rings, door openings, device
+//! changes and errors) is posted as JSON to the webhooks whose filters match, so
+//! that services such as ntfy, Slack or Home Assistant can react to it.
+
+use crate::settings::WebhookSettings;
+use crate::web::state::{BridgeState, LogEvent};
+use std::time::{Duration, SystemTime};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// Attempts made to deliver a notification before giving up.
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Delay before the first retry, doubled after each failed attempt.
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// Timeout of each webhook request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Start posting the bridge events to the configured webhooks.
+///
+/// Returns `None` when no webhook is configured. Abort the returned task to stop
+/// the notifications, e.g. before applying new settings.
+pub fn spawn(webhooks: &[WebhookSettings], state: BridgeState) -> Option<JoinHandle<()>> {
+    if webhooks.is_empty() {
+        return None;
+    }
+    info!("Sending notifications to {} webhook(s)", webhooks.len());
+    let webhooks = webhooks.to_vec();
+    let mut events = state.subscribe_events();
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .unwrap_or_default();
+
+    Some(tokio::spawn(async move {
+        loop {
+            let event = match events.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(missed)) => {
+                    warn!("Notifications too slow, skipped {missed} events");
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+            let payload = payload(&state, &event);
+            for webhook in &webhooks {
+                if webhook.matches(event.kind, event.device_id.as_deref()) {
+                    tokio::spawn(deliver(
+                        client.clone(),
+                        webhook.url.clone(),
+                        payload.clone(),
+                    ));
+                }
+            }
+        }
+    }))
+}
+
+/// JSON body posted to the webhooks for an event.
+fn payload(state: &BridgeState, event: &LogEvent) -> serde_json::Value {
+    let device = event.device_id.as_deref().and_then(|id| state.device(id));
+    serde_json::json!({
+        "event": event.kind.as_str(),
+        "message": event.message,
+        "timestamp": event
+            .time
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        "device": device.map(|d| serde_json::json!({
+            "id": d.id,
+            "name": d.name,
+            "type": d.device_type.as_str(),
+            "status": d.status,
+        })),
+    })
+}
+
+/// Post the payload to a webhook, retrying with exponential backoff on failure.
+async fn deliver(client: reqwest::Client, url: String, payload: serde_json::Value) {
+    let mut delay = RETRY_DELAY;
+    for attempt in 1..=MAX_ATTEMPTS {
+        let result = client
+            .post(&url)
+            .json(&payload)
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status());
+        match result {
+            Ok(_) => {
+                debug!("Notified webhook {}", url);
+                return;
+            }
+            Err(e) if attempt < MAX_ATTEMPTS => {
+                debug!("Webhook {} failed (attempt {}): {}", url, attempt, e);
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+            Err(e) => warn!("Giving up notifying webhook {}: {}", url, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::state::{DeviceInfo, DeviceType, EventKind};
+
+    #[test]
+    fn test_webhook_filters() {
+        let webhook: WebhookSettings = serde_json::from_value(serde_json::json!({
+            "url": "https://ntfy.sh/comelit",
+            "events": ["ring", "door_open"],
+            "devices": ["door1"],
+        }))
+        .unwrap();
+        assert!(webhook.matches(EventKind::DoorOpen, Some("door1")));
+        assert!(!webhook.matches(EventKind::DoorOpen, Some("door2")));
+        assert!(!webhook.matches(EventKind::Update, Some("door1")));
+
+        let all: WebhookSettings =
+            serde_json::from_value(serde_json::json!({ "url": "http://localhost" })).unwrap();
+        assert!(all.matches(EventKind::Update, Some("light1")));
+        assert!(all.matches(EventKind::Error, None));
+    }
+
+    #[test]
+    fn test_payload() {
+        let state = BridgeState::new();
+        state.register_device(DeviceInfo {
+            id: "door1".to_string(),
+            name: "Gate".to_string(),
+            device_type: DeviceType::Door,
+            status: "closed".to_string(),
+            last_update: None,
+        });
+        let mut events = state.subscribe_events();
+        state.update_device_status("door1", "open".to_string());
+
+        let payload = payload(&state, &events.try_recv().unwrap());
+        assert_eq!(payload["event"], "door_open");
+        assert_eq!(payload["message"], "Gate opened");
+        assert_eq!(payload["device"]["name"], "Gate");
+        assert_eq!(payload["device"]["status"], "open");
+    }
+}
@@ -0,0 +1,163 @@
+//! Automation rules run by the bridge.
+//!
+//! Rules are declared in the settings file: a trigger (a device changing status,
+//! a doorbell ring or a time of day) and the commands sent to the devices when it
+//! fires, e.g. turning on the courtyard light when the gate opens.
+
+use crate::settings::{RuleSettings, RuleTrigger};
+use crate::web::control;
+use crate::web::state::BridgeState;
+use chrono::{Local, NaiveTime, Timelike};
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+/// How often the time triggers are checked.
+const CLOCK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Start running the rules.
+///
+/// Returns `None` when no rule is configured. Abort the returned task to stop the
+/// rules, e.g. before applying new settings (commands already scheduled with a
+/// delay are still sent).
+pub fn spawn(rules: &[RuleSettings], state: BridgeState) -> Option<JoinHandle<()>> {
+    if rules.is_empty() {
+        return None;
+    }
+    info!("Running {} automation rule(s)", rules.len());
+    let rules = rules.to_vec();
+    let times: Vec<Option<NaiveTime>> = rules
+        .iter()
+        .map(|rule| match &rule.trigger {
+            RuleTrigger::Time { at } => {
+                let time = parse_time(at);
+                if time.is_none() {
+                    warn!("Rule {}: invalid time {:?}, expected HH:MM", rule.name, at);
+                }
+                time
+            }
+            _ => None,
+        })
+        .collect();
+    let mut events = state.subscribe_events();
+
+    Some(tokio::spawn(async move {
+        let mut clock = tokio::time::interval(CLOCK_INTERVAL);
+        // Minute the time triggers were last checked, so that each fires once
+        let mut last_minute = current_minute();
+        loop {
+            tokio::select! {
+                event = events.recv() => {
+                    let event = match event {
+                        Ok(event) => event,
+                        Err(RecvError::Lagged(missed)) => {
+                            warn!("Rules too slow, skipped {missed} events");
+                            continue;
+                        }
+                        Err(RecvError::Closed) => break,
+                    };
+                    let device_id = event.device_id.as_deref();
+                    let status = device_id.and_then(|id| state.device(id)).map(|d| d.status);
+                    for rule in &rules {
+                        if rule.trigger.matches(event.kind, device_id, status.as_deref()) {
+                            run(rule, &state);
+                        }
+                    }
+                }
+                _ = clock.tick() => {
+                    let minute = current_minute();
+                    if minute == last_minute {
+                        continue;
+                    }
+                    last_minute = minute;
+                    for (rule, time) in rules.iter().zip(&times) {
+                        if *time == Some(minute) {
+                            run(rule, &state);
+                        }
+                    }
+                }
+            }
+        }
+    }))
+}
+
+/// Parse a time of day as `HH:MM`.
+fn parse_time(at: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(at.trim(), "%H:%M").ok()
+}
+
+/// The current local time, truncated to the minute.
+fn current_minute() -> NaiveTime {
+    let now = Local::now().time();
+    NaiveTime::from_hms_opt(now.hour(), now.minute(), 0).unwrap_or(now)
+}
+
+/// Send the commands of a rule in the background.
+fn run(rule: &RuleSettings, state: &BridgeState) {
+    info!("Running rule {}", rule.name);
+    let rule = rule.clone();
+    let state = state.clone();
+    tokio::spawn(async move {
+        for action in &rule.actions {
+            if action.delay > 0 {
+                tokio::time::sleep(Duration::from_secs(action.delay)).await;
+            }
+            if let Err(e) = control::send_command(&state, &action.device, action.command).await {
+                warn!(
+                    "Rule {}: failed to run {:?} on {}: {}",
+                    rule.name, action.command, action.device, e
+                );
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::control::DeviceCommand;
+    use crate::web::state::EventKind;
+
+    #[test]
+    fn test_parse_rule() {
+        let rule: RuleSettings = serde_json::from_value(serde_json::json!({
+            "name": "Courtyard light",
+            "trigger": { "type": "device", "device": "DOM#DO#1", "status": "open" },
+            "actions": [
+                { "device": "DOM#LT#5.1", "action": "on" },
+                { "device": "DOM#LT#5.1", "action": "off", "delay": 300 },
+            ],
+        }))
+        .unwrap();
+        assert_eq!(rule.actions[0].command, DeviceCommand::On);
+        assert_eq!(rule.actions[0].delay, 0);
+        assert_eq!(rule.actions[1].delay, 300);
+        // The flattened command survives a round trip through the settings file
+        let saved = serde_json::to_value(&rule).unwrap();
+        assert_eq!(saved["actions"][1]["action"], "off");
+        assert_eq!(serde_json::from_value::<RuleSettings>(saved).unwrap(), rule);
+    }
+
+    #[test]
+    fn test_trigger_matches() {
+        let gate = RuleTrigger::Device {
+            device: "gate".to_string(),
+            status: Some("open".to_string()),
+        };
+        assert!(gate.matches(EventKind::DoorOpen, Some("gate"), Some("open")));
+        assert!(!gate.matches(EventKind::Update, Some("gate"), Some("closed")));
+        assert!(!gate.matches(EventKind::DoorOpen, Some("door"), Some("open")));
+
+        let any_ring = RuleTrigger::Ring { device: None };
+        assert!(any_ring.matches(EventKind::Ring, Some("bell"), None));
+        assert!(!any_ring.matches(EventKind::Update, Some("bell"), None));
+
+        let time = RuleTrigger::Time {
+            at: "07:30".to_string(),
+        };
+        assert!(!time.matches(EventKind::Update, None, None));
+        assert_eq!(parse_time("07:30"), NaiveTime::from_hms_opt(7, 30, 0));
+        assert_eq!(parse_time("7.30"), None);
+    }
+}
@@ -0,0 +1,77 @@
+//! Resolution of credentials and pairing PIN from the environment.
+//!
+//! Every secret can be provided directly through an environment variable
+//! (e.g. `COMELIT_PASSWORD`) or indirectly through a file whose path is stored
+//! in the same variable with a `_FILE` suffix (e.g. `COMELIT_PASSWORD_FILE`),
+//! which is how Docker and systemd credentials expose secrets.
+
+use anyhow::{Context, Result, anyhow};
+use std::env;
+
+/// Environment variable holding the Comelit hub user.
+pub const USER_VAR: &str = "COMELIT_USER";
+/// Environment variable holding the Comelit hub password.
+pub const PASSWORD_VAR: &str = "COMELIT_PASSWORD";
+/// Environment variable holding the HomeKit pairing PIN.
+pub const PIN_VAR: &str = "HAP_PIN";
+
+/// Resolve a secret, in order of precedence, from the given explicit value,
+/// from the file pointed by `<var>_FILE` or from the `<var>` environment variable.
+pub fn resolve(explicit: Option<String>, var: &str) -> Result<Option<String>> {
+    if explicit.is_some() {
+        return Ok(explicit);
+    }
+    let file_var = format!("{var}_FILE");
+    if let Ok(path) = env::var(&file_var) {
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {file_var} from {path}"))?;
+        return Ok(Some(content.trim().to_string()));
+    }
+    Ok(env::var(var).ok())
+}
+
+/// Parse a HomeKit pairing PIN (e.g. "111-22-333" or "11122333") into its 8 digits.
+pub fn parse_pin(pin: &str) -> Result<[u8; 8]> {
+    let digits: Vec<u8> = pin
+        .chars()
+        .filter(|c| *c != '-' && !c.is_whitespace())
+        .map(|c| {
+            c.to_digit(10)
+                .map(|d| d as u8)
+                .ok_or_else(|| anyhow!("Invalid character '{c}' in pairing PIN"))
+        })
+        .collect::<Result<_>>()?;
+    digits
+        .try_into()
+        .map_err(|d: Vec<u8>| anyhow!("Pairing PIN must have exactly 8 digits, got {}", d.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_parse_pin() {
+        assert_eq!(parse_pin("111-22-333").unwrap(), [1, 1, 1, 2, 2, 3, 3, 3]);
+        assert_eq!(parse_pin("12345678\n").unwrap(), [1, 2, 3, 4, 5, 6, 7, 8]);
+        assert!(parse_pin("1234").is_err());
+        assert!(parse_pin("1234567a").is_err());
+    }
+
+    #[test]
+    fn test_resolve_prefers_explicit_value() {
+        let value = resolve(Some("explicit".into()), "COMELIT_TEST_UNSET_VAR").unwrap();
+        assert_eq!(value.as_deref(), Some("explicit"));
+    }
+
+    #[test]
+    fn test_resolve_from_file() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "s3cret").unwrap();
+        // SAFETY: the variable name is unique to this test
+        unsafe { env::set_var("COMELIT_TEST_SECRET_FILE", file.path()) };
+        let value = resolve(None, "COMELIT_TEST_SECRET").unwrap();
+        assert_eq!(value.as_deref(), Some("s3cret"));
+    }
+}
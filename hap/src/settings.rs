@@ -1,4 +1,9 @@
+use crate::model::{DeviceCommand, EventKind};
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use tracing::warn;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WindowCoveringSettings {
@@ -30,6 +35,270 @@ impl Default for DoorSettings {
     }
 }
 
+/// Whether status updates pushed by the hub are forwarded to HomeKit, per device type.
+///
+/// When disabled, the HomeKit state of those accessories only changes on commands sent
+/// from HomeKit (e.g. for blinds whose motor reports bouncing states).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LiveUpdateSettings {
+    pub lights: bool,
+    pub window_coverings: bool,
+    pub thermostats: bool,
+    pub doors: bool,
+    pub switches: bool,
+}
+
+impl Default for LiveUpdateSettings {
+    fn default() -> Self {
+        LiveUpdateSettings {
+            lights: true,
+            window_coverings: true,
+            thermostats: true,
+            doors: true,
+            switches: true,
+        }
+    }
+}
+
+/// Persistent history of temperature, humidity, power and on/off samples.
+///
+/// Requires the bridge to be built with the `history` feature.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistorySettings {
+    /// Path of the SQLite database.
+    pub path: String,
+    /// Days after which samples are deleted.
+    #[serde(default = "default_retention_days")]
+    pub retention_days: u32,
+}
+
+fn default_retention_days() -> u32 {
+    30
+}
+
+/// InfluxDB v2 server the temperature, humidity, power and on/off samples are written to.
+///
+/// Requires the bridge to be built with the `influxdb` feature.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InfluxDbSettings {
+    /// Base URL of the server, e.g. `http://localhost:8086`.
+    pub url: String,
+    pub org: String,
+    pub bucket: String,
+    /// API token with write access to the bucket.
+    pub token: String,
+}
+
+/// A URL notified with a JSON `POST` when an event of the bridge matches the filters.
+///
+/// Events matching `events` (all kinds when empty) and `devices` (all ids when empty)
+/// are sent; events not related to a device (e.g. errors) ignore the `devices` filter.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebhookSettings {
+    pub url: String,
+    #[serde(default)]
+    pub events: Vec<EventKind>,
+    #[serde(default)]
+    pub devices: Vec<String>,
+}
+
+impl WebhookSettings {
+    pub fn matches(&self, kind: EventKind, device_id: Option<&str>) -> bool {
+        let kind_matches = self.events.is_empty() || self.events.contains(&kind);
+        let id_matches = match device_id {
+            Some(id) => self.devices.is_empty() || self.devices.iter().any(|d| d == id),
+            None => true,
+        };
+        kind_matches && id_matches
+    }
+}
+
+/// An automation run by the bridge: when the trigger fires, the actions are sent
+/// to the devices in order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleSettings {
+    pub name: String,
+    pub trigger: RuleTrigger,
+    pub actions: Vec<RuleAction>,
+}
+
+/// What fires a rule.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RuleTrigger {
+    /// A device changes status, to `status` when set (e.g. `on`, `off`, `open`).
+    Device {
+        device: String,
+        #[serde(default)]
+        status: Option<String>,
+    },
+    /// A doorbell rings (any doorbell when `device` is not set).
+    Ring {
+        #[serde(default)]
+        device: Option<String>,
+    },
+    /// Every day at a local time, as `HH:MM`.
+    Time { at: String },
+}
+
+impl RuleTrigger {
+    /// Whether an event of the event log fires the trigger, given the new status
+    /// of its device.
+    pub fn matches(&self, kind: EventKind, device_id: Option<&str>, status: Option<&str>) -> bool {
+        match self {
+            RuleTrigger::Device {
+                device,
+                status: wanted,
+            } => {
+                matches!(kind, EventKind::Update | EventKind::DoorOpen)
+                    && device_id == Some(device.as_str())
+                    && wanted
+                        .as_deref()
+                        .is_none_or(|w| status.is_some_and(|s| s.eq_ignore_ascii_case(w)))
+            }
+            RuleTrigger::Ring { device } => {
+                kind == EventKind::Ring && device.as_deref().is_none_or(|d| device_id == Some(d))
+            }
+            RuleTrigger::Time { .. } => false,
+        }
+    }
+}
+
+/// A command sent to a device by a rule, e.g.
+/// `{"device": "DOM#LT#5.1", "action": "off", "delay": 300}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleAction {
+    pub device: String,
+    #[serde(flatten)]
+    pub command: DeviceCommand,
+    /// Seconds to wait before sending the command.
+    #[serde(default)]
+    pub delay: u64,
+}
+
+/// Where the Comelit room is added to accessory names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoomNaming {
+    /// "Cucina - Luce soffitto"
+    Prefix,
+    /// "Luce soffitto - Cucina"
+    Suffix,
+}
+
+/// External MQTT broker the device states are mirrored to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MqttSettings {
+    pub host: String,
+    #[serde(default = "default_mqtt_port")]
+    pub port: u16,
+    pub user: Option<String>,
+    pub password: Option<String>,
+    /// Topics are `<topic_prefix>/<id>/state` and `<topic_prefix>/<id>/set`.
+    #[serde(default = "default_topic_prefix")]
+    pub topic_prefix: String,
+    #[serde(default = "default_mqtt_client_id")]
+    pub client_id: String,
+}
+
+fn default_mqtt_port() -> u16 {
+    1883
+}
+
+fn default_topic_prefix() -> String {
+    "comelit".to_string()
+}
+
+fn default_mqtt_client_id() -> String {
+    "comelit-hub-bridge".to_string()
+}
+
+/// ICONA bridge of the VIP intercom, listened to for calls so that the HomeKit
+/// doorbells ring even when the hub does not report them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ViperSettings {
+    pub host: String,
+    #[serde(default = "default_viper_port")]
+    pub port: u16,
+    /// Token of a user activated on the ICONA bridge (`viper auth --email <email>`).
+    pub token: String,
+    /// Doorbell rung on incoming calls, all the mounted doorbells when not set.
+    pub doorbell: Option<String>,
+}
+
+fn default_viper_port() -> u16 {
+    viper_client::ICONA_BRIDGE_PORT
+}
+
+/// Language of the web UI.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Language {
+    #[default]
+    En,
+    It,
+}
+
+impl Language {
+    /// ISO 639-1 code of the language.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::En => "en",
+            Language::It => "it",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Language> {
+        match code {
+            "en" => Some(Language::En),
+            "it" => Some(Language::It),
+            _ => None,
+        }
+    }
+}
+
+/// Kind of devices that can be assigned to an additional HAP bridge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BridgeDeviceKind {
+    Light,
+    WindowCovering,
+    Thermostat,
+    Door,
+    Switch,
+}
+
+/// An additional HAP bridge, used to split large installations that exceed
+/// the number of accessories HomeKit accepts on a single bridge.
+///
+/// Devices matching `device_types` (all types when empty), `rooms` (all rooms
+/// when empty) and `devices` (all ids when empty) are published on this bridge
+/// instead of the main one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BridgeSettings {
+    pub name: String,
+    pub pairing_code: [u8; 8],
+    pub port: u16,
+    #[serde(default)]
+    pub device_types: Vec<BridgeDeviceKind>,
+    /// Names of the Comelit rooms (zones) whose devices go on this bridge.
+    #[serde(default)]
+    pub rooms: Vec<String>,
+    #[serde(default)]
+    pub devices: Vec<String>,
+}
+
+impl BridgeSettings {
+    pub fn matches(&self, kind: BridgeDeviceKind, device_id: &str, room: Option<&str>) -> bool {
+        let type_matches = self.device_types.is_empty() || self.device_types.contains(&kind);
+        let room_matches = self.rooms.is_empty()
+            || room.is_some_and(|room| self.rooms.iter().any(|r| r.eq_ignore_ascii_case(room)));
+        let id_matches = self.devices.is_empty() || self.devices.iter().any(|d| d == device_id);
+        type_matches && room_matches && id_matches
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Settings {
     pub pairing_code: [u8; 8],
@@ -38,10 +307,39 @@ pub struct Settings {
     pub mount_thermo: Option<bool>,
     pub mount_doors: Option<bool>,
     pub mount_doorbells: Option<bool>,
+    pub mount_others: Option<bool>,
     pub window_covering: WindowCoveringSettings,
     pub door: DoorSettings,
     pub prometheus_url: Option<String>,
     pub prometheus_token: Option<String>,
+    #[serde(default)]
+    pub bridges: Vec<BridgeSettings>,
+    #[serde(default)]
+    pub live_updates: LiveUpdateSettings,
+    /// Add the Comelit room to the names of lights, blinds, thermostats and switches.
+    ///
+    /// Only the names change: HAP has no characteristic for the room of an accessory.
+    pub name_with_room: Option<RoomNaming>,
+    /// Accessory names to use instead of the Comelit descriptions, by device ID.
+    #[serde(default)]
+    pub names: HashMap<String, String>,
+    /// Record samples in a local database to chart them without Prometheus.
+    pub history: Option<HistorySettings>,
+    /// Webhooks notified of doorbell rings, door openings and device changes.
+    #[serde(default)]
+    pub webhooks: Vec<WebhookSettings>,
+    /// Language of the web UI.
+    #[serde(default)]
+    pub language: Language,
+    /// Mirror the device states to an external MQTT broker and accept commands from it.
+    pub mqtt: Option<MqttSettings>,
+    /// Write the samples to InfluxDB (requires the `influxdb` feature).
+    pub influxdb: Option<InfluxDbSettings>,
+    /// Automations run by the bridge.
+    #[serde(default)]
+    pub rules: Vec<RuleSettings>,
+    /// Ring the doorbells on the calls received by the VIP intercom.
+    pub viper: Option<ViperSettings>,
 }
 
 impl Default for Settings {
@@ -53,10 +351,76 @@ impl Default for Settings {
             mount_thermo: Some(true),
             mount_doors: Some(true),
             mount_doorbells: Some(false),
+            mount_others: Some(false),
             window_covering: WindowCoveringSettings::default(),
             door: DoorSettings::default(),
             prometheus_url: None,
             prometheus_token: None,
+            bridges: vec![],
+            live_updates: LiveUpdateSettings::default(),
+            name_with_room: None,
+            names: HashMap::new(),
+            history: None,
+            webhooks: vec![],
+            language: Language::En,
+            mqtt: None,
+            influxdb: None,
+            rules: vec![],
+            viper: None,
+        }
+    }
+}
+
+impl Settings {
+    /// Load the settings from a JSON file, falling back to the defaults when the
+    /// file is not given or cannot be read.
+    pub fn load(path: Option<&str>) -> Result<Settings> {
+        let Some(path) = path else {
+            return Ok(Settings::default());
+        };
+        match std::fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid settings file {path}")),
+            Err(_) => {
+                warn!("Failed to read settings file, using default settings");
+                Ok(Settings::default())
+            }
         }
     }
+
+    /// Write the settings to a JSON file, replacing it atomically.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, content)
+            .with_context(|| format!("Failed to write {}", tmp.display()))?;
+        std::fs::rename(&tmp, path).with_context(|| format!("Failed to replace {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bridge_matches() {
+        let bridge = BridgeSettings {
+            name: "Piano1".to_string(),
+            pairing_code: [1, 1, 1, 2, 2, 3, 3, 3],
+            port: 32011,
+            device_types: vec![BridgeDeviceKind::Light],
+            rooms: vec!["Cucina".to_string()],
+            devices: vec![],
+        };
+        assert!(bridge.matches(BridgeDeviceKind::Light, "DOM#LT#1", Some("cucina")));
+        assert!(!bridge.matches(BridgeDeviceKind::Light, "DOM#LT#1", Some("Bagno")));
+        assert!(!bridge.matches(BridgeDeviceKind::Light, "DOM#LT#1", None));
+        assert!(!bridge.matches(BridgeDeviceKind::Door, "DOM#LT#1", Some("Cucina")));
+
+        let any_room = BridgeSettings {
+            rooms: vec![],
+            ..bridge
+        };
+        assert!(any_room.matches(BridgeDeviceKind::Light, "DOM#LT#1", None));
+    }
 }
@@ -0,0 +1,174 @@
+//! Calls of the VIP intercom, received from its ICONA bridge.
+//!
+//! The hub does not report every call of the external units, so when `viper` is
+//! configured the bridge also keeps a CTPP channel open on the intercom and rings
+//! the HomeKit doorbells on each incoming call. The viper client is blocking, so
+//! the channel is read from a dedicated thread.
+
+use crate::settings::ViperSettings;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+use viper_client::events::ViperEvent;
+use viper_client::{ViperBridge, ViperClient, ViperError};
+
+/// Delay before connecting again to the intercom after an error.
+const RECONNECT_DELAY: Duration = Duration::from_secs(10);
+
+/// How often the listener thread checks whether it should stop.
+const STOP_POLL: Duration = Duration::from_secs(1);
+
+/// The thread listening for calls on the intercom, stopped with [`Intercom::stop`].
+pub struct Intercom {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Intercom {
+    /// Start listening for calls on the configured intercom. The address of the door
+    /// station calling is sent on the returned channel.
+    pub fn spawn(settings: &ViperSettings) -> (Intercom, mpsc::UnboundedReceiver<String>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let settings = settings.clone();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        info!(
+            "Listening for intercom calls on {}:{}",
+            settings.host, settings.port
+        );
+        let thread = thread::spawn(move || {
+            let running = || !stopped.load(Ordering::Relaxed) && !tx.is_closed();
+            while running() {
+                if let Err(e) = listen(&settings, &tx, &running) {
+                    warn!(
+                        "Intercom connection error: {}, retrying in {:?}",
+                        e, RECONNECT_DELAY
+                    );
+                }
+                let retry = Instant::now() + RECONNECT_DELAY;
+                while running() && Instant::now() < retry {
+                    thread::sleep(STOP_POLL);
+                }
+            }
+            debug!("Intercom listener stopped");
+        });
+        (
+            Intercom {
+                stop,
+                thread: Some(thread),
+            },
+            rx,
+        )
+    }
+
+    /// Close the connection to the intercom and wait for the thread to exit,
+    /// which takes up to [`STOP_POLL`].
+    pub fn stop(mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn listen(
+    settings: &ViperSettings,
+    tx: &mpsc::UnboundedSender<String>,
+    running: &impl Fn() -> bool,
+) -> Result<(), ViperError> {
+    let mut client = ViperClient::connect(&settings.host, settings.port)?;
+    let result = forward_calls(&mut client, &settings.token, tx, running);
+    client.shutdown();
+    result
+}
+
+/// Forward the calls until the connection fails or `running` turns false.
+fn forward_calls(
+    bridge: &mut impl ViperBridge,
+    token: &str,
+    tx: &mpsc::UnboundedSender<String>,
+    running: &impl Fn() -> bool,
+) -> Result<(), ViperError> {
+    bridge.authorize(token)?;
+    let vip = bridge.configuration("all")?.vip;
+    let mut events = bridge.listen_events_timeout(&vip, STOP_POLL)?;
+    while running() {
+        match events.next() {
+            Some(Ok(ViperEvent::IncomingCall { from })) => {
+                if tx.send(from).is_err() {
+                    break;
+                }
+            }
+            Some(Ok(event)) => debug!("Intercom event {:?}", event),
+            Some(Err(ViperError::Timeout)) => continue,
+            Some(Err(e)) => return Err(e),
+            None => break,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use viper_client::fake::FakeViperBridge;
+
+    #[test]
+    fn test_forward_calls() {
+        let mut bridge = FakeViperBridge::new(serde_json::json!({
+            "message": "get-configuration",
+            "message-type": "response",
+            "message-id": 2,
+            "response-code": 200,
+            "response-string": "OK",
+            "viper-server": {
+                "local-address": "192.168.1.20",
+                "local-tcp-port": 64100,
+                "local-udp-port": 64100,
+                "remote-address": "",
+                "remote-tcp-port": 64100,
+                "remote-udp-port": 64100
+            },
+            "viper-client": { "description": "SU0001" },
+            "vip": {
+                "enabled": true,
+                "apt-address": "SB000006",
+                "apt-subaddress": 2,
+                "logical-subaddress": 2,
+                "apt-config": {
+                    "description": "",
+                    "call-divert-busy-en": false,
+                    "call-divert-address": "",
+                    "virtual-key-enabled": false
+                },
+                "user-parameters": { "forced": false }
+            }
+        }));
+        bridge.token = Some("TESTTOKEN".to_string());
+        bridge.events.extend([
+            ViperEvent::IncomingCall {
+                from: "SB1000011".to_string(),
+            },
+            ViperEvent::CallEnded {
+                from: "SB1000011".to_string(),
+            },
+            ViperEvent::IncomingCall {
+                from: "SB1000021".to_string(),
+            },
+        ]);
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        forward_calls(&mut bridge, "TESTTOKEN", &tx, &|| true).unwrap();
+        assert_eq!(rx.try_recv().unwrap(), "SB1000011");
+        assert_eq!(rx.try_recv().unwrap(), "SB1000021");
+        assert!(rx.try_recv().is_err());
+
+        assert!(matches!(
+            forward_calls(&mut bridge, "WRONG", &tx, &|| true),
+            Err(ViperError::AuthError { .. })
+        ));
+    }
+}
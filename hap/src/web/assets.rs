@@ -0,0 +1,113 @@
+//! Static assets of the web UI (stylesheet, scripts, icons), embedded in the binary.
+//!
+//! Templates reference them as `{{ base_path }}/static/<file>?v={{ asset_version }}`:
+//! versioned URLs are cached forever by browsers, unversioned ones are revalidated
+//! with their ETag.
+
+use axum::{
+    extract::Path,
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use rust_embed::RustEmbed;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::LazyLock;
+
+#[derive(RustEmbed)]
+#[folder = "static/"]
+struct Assets;
+
+/// Cache-Control of versioned asset URLs.
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+/// Cache-Control of unversioned asset URLs: always revalidated with the ETag.
+const REVALIDATE: &str = "no-cache";
+
+static VERSION: LazyLock<String> = LazyLock::new(|| {
+    let mut hasher = DefaultHasher::new();
+    for path in Assets::iter() {
+        if let Some(file) = Assets::get(&path) {
+            file.metadata.sha256_hash().hash(&mut hasher);
+        }
+    }
+    format!("{:016x}", hasher.finish())
+});
+
+/// Hash of all the assets, added to their URLs so that browsers fetch them again
+/// after an upgrade.
+pub fn version() -> &'static str {
+    &VERSION
+}
+
+/// Static asset endpoint - serves an embedded file with ETag and Cache-Control headers.
+pub async fn asset_handler(
+    Path(path): Path<String>,
+    query: axum::extract::RawQuery,
+    headers: HeaderMap,
+) -> Response {
+    let Some(file) = Assets::get(&path) else {
+        return (StatusCode::NOT_FOUND, "Not found").into_response();
+    };
+
+    let etag = format!("\"{}\"", hex(&file.metadata.sha256_hash()[..16]));
+    let cache_control = if query.0.is_some() {
+        IMMUTABLE
+    } else {
+        REVALIDATE
+    };
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.split(',').any(|tag| tag.trim() == etag));
+    if not_modified {
+        return (
+            StatusCode::NOT_MODIFIED,
+            [
+                (header::ETAG, etag),
+                (header::CACHE_CONTROL, cache_control.to_string()),
+            ],
+        )
+            .into_response();
+    }
+
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, content_type(&path).to_string()),
+            (header::ETAG, etag),
+            (header::CACHE_CONTROL, cache_control.to_string()),
+        ],
+        file.data,
+    )
+        .into_response()
+}
+
+/// Content type of an asset, from its extension.
+fn content_type(path: &str) -> &'static str {
+    match path.rsplit_once('.').map(|(_, ext)| ext) {
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("ico") => "image/x-icon",
+        _ => "application/octet-stream",
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assets_embedded() {
+        assert!(Assets::get("style.css").is_some());
+        assert!(Assets::get("charts.js").is_some());
+        assert_eq!(content_type("style.css"), "text/css; charset=utf-8");
+        assert_eq!(version().len(), 16);
+    }
+}
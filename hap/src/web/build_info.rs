@@ -0,0 +1,8 @@
+//! Version information embedded at build time by `build.rs`.
+
+/// Crate version.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Short hash of the git commit the bridge was built from, or `unknown`.
+pub const GIT_SHA: &str = env!("COMELIT_GIT_SHA");
+/// Version of the compiler used to build the bridge.
+pub const RUSTC_VERSION: &str = env!("COMELIT_RUSTC_VERSION");
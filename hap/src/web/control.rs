@@ -0,0 +1,95 @@
+//! Device commands sent from the web UI and the REST API.
+//!
+//! Commands go straight to the Comelit hub; the resulting status update is then
+//! pushed by the hub to the HomeKit accessories like any other external change.
+
+use comelit_client_rs::{ClimaOnOff, ComelitClientError};
+use thiserror::Error;
+
+pub use crate::model::DeviceCommand;
+use crate::web::state::{BridgeState, DeviceType};
+
+/// Error returned when a command cannot be sent to a device.
+#[derive(Error, Debug)]
+pub enum ControlError {
+    #[error("Device {0} not found")]
+    NotFound(String),
+    #[error("Bridge is not connected to the Comelit hub")]
+    NotConnected,
+    #[error("{0} does not support this command")]
+    Unsupported(&'static str),
+    #[error(transparent)]
+    Client(#[from] ComelitClientError),
+}
+
+/// Send a command to the device with the given ID.
+pub async fn send_command(
+    state: &BridgeState,
+    id: &str,
+    command: DeviceCommand,
+) -> Result<(), ControlError> {
+    let device = state
+        .device(id)
+        .ok_or_else(|| ControlError::NotFound(id.to_string()))?;
+    let client = state.client().ok_or(ControlError::NotConnected)?;
+
+    match (device.device_type, command) {
+        (DeviceType::Light | DeviceType::Switch, DeviceCommand::Toggle) => {
+            client
+                .toggle_device_status(id, device.status != "on")
+                .await?
+        }
+        (DeviceType::Light | DeviceType::Switch, DeviceCommand::On | DeviceCommand::Off) => {
+            client
+                .toggle_device_status(id, command == DeviceCommand::On)
+                .await?
+        }
+        (DeviceType::Thermostat, DeviceCommand::On | DeviceCommand::Off) => {
+            let status = if command == DeviceCommand::On {
+                ClimaOnOff::OnThermo
+            } else {
+                ClimaOnOff::OffThermo
+            };
+            client.toggle_thermostat_status(id, status).await?
+        }
+        (DeviceType::Door, DeviceCommand::Toggle | DeviceCommand::Open) => {
+            client.toggle_device_status(id, true).await?
+        }
+        (DeviceType::WindowCovering, DeviceCommand::Open | DeviceCommand::Close) => {
+            let position = if command == DeviceCommand::Open {
+                100
+            } else {
+                0
+            };
+            client.toggle_blind_position(id, position).await?
+        }
+        (DeviceType::WindowCovering, DeviceCommand::SetTarget { value: position }) => {
+            client
+                .toggle_blind_position(id, position.clamp(0.0, 100.0) as u8)
+                .await?
+        }
+        (DeviceType::Thermostat, DeviceCommand::SetTarget { value: temperature }) => {
+            // The hub expects tenths of degree
+            client
+                .set_thermostat_temperature(id, (temperature * 10.0).round() as i32)
+                .await?
+        }
+        (device_type, _) => return Err(ControlError::Unsupported(device_type.display_name())),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_device_command() {
+        let command: DeviceCommand = serde_json::from_str(r#"{"action": "on"}"#).unwrap();
+        assert_eq!(command, DeviceCommand::On);
+        let command: DeviceCommand =
+            serde_json::from_str(r#"{"action": "set_target", "value": 21.5}"#).unwrap();
+        assert_eq!(command, DeviceCommand::SetTarget { value: 21.5 });
+        assert!(serde_json::from_str::<DeviceCommand>(r#"{"action": "explode"}"#).is_err());
+    }
+}
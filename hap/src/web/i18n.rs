@@ -0,0 +1,165 @@
+//! Translations of the web UI.
+//!
+//! Templates wrap their English strings in `t("...")`, which returns the
+//! translation for the `lang` global of the template environment. Strings without
+//! a translation are shown in English.
+
+use crate::settings::Language;
+use minijinja::State;
+
+/// Italian translations, by English text.
+const ITALIAN: &[(&str, &str)] = &[
+    // Navigation
+    ("Dashboard", "Pannello"),
+    ("Devices", "Dispositivi"),
+    ("Charts", "Grafici"),
+    ("Events", "Eventi"),
+    ("Settings", "Impostazioni"),
+    ("Metrics", "Metriche"),
+    // Dashboard
+    ("Error", "Errore"),
+    ("Bridge Status", "Stato del bridge"),
+    ("Connection", "Connessione"),
+    ("Uptime", "Attivo da"),
+    ("Hub Host", "Indirizzo centrale"),
+    ("Total Devices", "Dispositivi totali"),
+    ("HomeKit Pairing", "Associazione HomeKit"),
+    ("Status", "Stato"),
+    ("Paired", "Associato"),
+    ("Not Paired", "Non associato"),
+    ("Pairing Code", "Codice di associazione"),
+    (
+        "Scan with your iOS device to pair:",
+        "Inquadra con il tuo dispositivo iOS per associare:",
+    ),
+    (
+        "Printable pairing card",
+        "Scheda di associazione stampabile",
+    ),
+    ("Health", "Salute"),
+    ("Ping Count", "Ping inviati"),
+    ("Ping Failures", "Ping falliti"),
+    ("Success Rate", "Tasso di successo"),
+    ("Last Ping", "Ultimo ping"),
+    ("ago", "fa"),
+    ("Never", "Mai"),
+    ("Activity", "Attività"),
+    ("Updates Received", "Aggiornamenti ricevuti"),
+    ("Device Summary", "Riepilogo dispositivi"),
+    // Devices
+    ("All Devices", "Tutti i dispositivi"),
+    ("Lights", "Luci"),
+    ("Thermostats", "Termostati"),
+    ("Window Coverings", "Tapparelle"),
+    ("Window coverings", "Tapparelle"),
+    ("Doors", "Porte"),
+    ("Doorbells", "Campanelli"),
+    ("Switches", "Interruttori"),
+    ("Energy Meters", "Contatori di energia"),
+    ("Power", "Potenza"),
+    ("Other outputs (switches)", "Altre uscite (interruttori)"),
+    ("Name", "Nome"),
+    ("Last Update", "Ultimo aggiornamento"),
+    ("Actions", "Azioni"),
+    ("Toggle", "Commuta"),
+    ("Set", "Imposta"),
+    ("Open", "Apri"),
+    ("Close", "Chiudi"),
+    ("Recent Rings", "Suonate recenti"),
+    ("Doorbell", "Campanello"),
+    ("When", "Quando"),
+    (
+        "No devices registered yet.",
+        "Nessun dispositivo registrato.",
+    ),
+    (
+        "Devices will appear here once the bridge connects to the Comelit HUB.",
+        "I dispositivi compariranno qui quando il bridge si collegherà al Comelit HUB.",
+    ),
+    // Settings
+    ("Settings not saved:", "Impostazioni non salvate:"),
+    (
+        "Settings saved, the bridge is restarting to apply them.",
+        "Impostazioni salvate, il bridge si sta riavviando per applicarle.",
+    ),
+    (
+        "No settings file configured.",
+        "Nessun file di impostazioni configurato.",
+    ),
+    ("Start the bridge with", "Avvia il bridge con"),
+    (
+        "to edit the settings here.",
+        "per modificare qui le impostazioni.",
+    ),
+    ("Language", "Lingua"),
+    ("Mounted Devices", "Dispositivi esposti"),
+    ("Opening time (seconds)", "Tempo di apertura (secondi)"),
+    ("Closing time (seconds)", "Tempo di chiusura (secondi)"),
+    ("Name Overrides", "Nomi personalizzati"),
+    ("One per line, as", "Uno per riga, nel formato"),
+    ("Settings file:", "File delle impostazioni:"),
+    ("Save and restart bridge", "Salva e riavvia il bridge"),
+];
+
+/// Translate an English text of the UI.
+pub fn translate(language: Language, text: &str) -> &str {
+    let table = match language {
+        Language::En => return text,
+        Language::It => ITALIAN,
+    };
+    table
+        .iter()
+        .find(|(en, _)| *en == text)
+        .map(|(_, translated)| *translated)
+        .unwrap_or(text)
+}
+
+/// The `t` template function, translating to the language of the `lang` global.
+pub fn t(state: &State, text: &str) -> String {
+    let language = state
+        .lookup("lang")
+        .and_then(|lang| Language::from_code(lang.as_str()?))
+        .unwrap_or_default();
+    translate(language, text).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEMPLATES: &[&str] = &[
+        include_str!("../../templates/base.html"),
+        include_str!("../../templates/index.html"),
+        include_str!("../../templates/devices.html"),
+        include_str!("../../templates/settings.html"),
+    ];
+
+    #[test]
+    fn test_translate() {
+        assert_eq!(translate(Language::It, "Devices"), "Dispositivi");
+        assert_eq!(translate(Language::En, "Devices"), "Devices");
+        assert_eq!(translate(Language::It, "Not translated"), "Not translated");
+    }
+
+    #[test]
+    fn test_templates_are_translated() {
+        for template in TEMPLATES {
+            for text in template.split("t(\"").skip(1) {
+                let text = &text[..text.find('"').unwrap()];
+                assert!(
+                    ITALIAN.iter().any(|(en, _)| *en == text),
+                    "Missing Italian translation of {text:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_t_function() {
+        let mut env = minijinja::Environment::new();
+        env.add_function("t", t);
+        env.add_global("lang", Language::It.code());
+        let html = env.render_str(r#"{{ t("Settings") }}"#, ()).unwrap();
+        assert_eq!(html, "Impostazioni");
+    }
+}
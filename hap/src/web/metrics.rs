@@ -8,6 +8,8 @@ use metrics::{counter, describe_counter, describe_gauge, gauge};
 use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
 use std::time::Instant;
 
+use crate::web::build_info;
+
 /// Initialize the Prometheus metrics exporter and register all metric descriptions.
 ///
 /// Returns a handle that can be used to render the metrics.
@@ -30,6 +32,10 @@ fn register_metric_descriptions() {
         "comelit_bridge_info",
         "Information about the Comelit bridge (always 1, labels contain version info)"
     );
+    describe_gauge!(
+        "comelit_bridge_build_info",
+        "Bridge build information (labels: version, git_sha, rustc)"
+    );
     describe_gauge!(
         "comelit_bridge_uptime_seconds",
         "Time in seconds since the bridge started"
@@ -79,6 +85,14 @@ fn register_metric_descriptions() {
         "comelit_hap_requests_total",
         "Total number of HomeKit requests received"
     );
+    describe_counter!(
+        "comelit_hap_characteristic_reads_total",
+        "Total number of characteristic reads by HomeKit controllers, by accessory"
+    );
+    describe_counter!(
+        "comelit_hap_characteristic_writes_total",
+        "Total number of characteristic writes by HomeKit controllers, by accessory"
+    );
 
     // Thermostat metrics
     describe_gauge!(
@@ -128,6 +142,17 @@ impl Metrics {
         gauge!("comelit_bridge_info", "version" => version.to_string()).set(1.0);
     }
 
+    /// Set the build info metric with version, git commit and compiler labels.
+    pub fn set_build_info() {
+        gauge!(
+            "comelit_bridge_build_info",
+            "version" => build_info::VERSION,
+            "git_sha" => build_info::GIT_SHA,
+            "rustc" => build_info::RUSTC_VERSION
+        )
+        .set(1.0);
+    }
+
     /// Set whether the bridge is paired.
     pub fn set_paired(paired: bool) {
         gauge!("comelit_bridge_paired").set(if paired { 1.0 } else { 0.0 });
@@ -179,6 +204,38 @@ impl Metrics {
         counter!("comelit_hap_requests_total").increment(1);
     }
 
+    /// Count a read of an accessory characteristic by a HomeKit controller.
+    pub fn inc_hap_read(
+        accessory_type: &'static str,
+        device_id: &str,
+        characteristic: &'static str,
+    ) {
+        Self::inc_hap_requests();
+        counter!(
+            "comelit_hap_characteristic_reads_total",
+            "type" => accessory_type,
+            "device" => device_id.to_string(),
+            "characteristic" => characteristic
+        )
+        .increment(1);
+    }
+
+    /// Count a write of an accessory characteristic by a HomeKit controller.
+    pub fn inc_hap_write(
+        accessory_type: &'static str,
+        device_id: &str,
+        characteristic: &'static str,
+    ) {
+        Self::inc_hap_requests();
+        counter!(
+            "comelit_hap_characteristic_writes_total",
+            "type" => accessory_type,
+            "device" => device_id.to_string(),
+            "characteristic" => characteristic
+        )
+        .increment(1);
+    }
+
     /// Set the current temperature for a thermostat.
     pub fn set_thermostat_temperature(thermostat_name: &str, temperature: f64) {
         gauge!(
@@ -226,10 +283,6 @@ impl Metrics {
 
     /// Set the total power consumption for all devices.
     pub fn set_total_consumption(total_consumption: f64) {
-        gauge!(
-            "comelit_total_consumption",
-            "total_consumption" => total_consumption.to_string()
-        )
-        .set(total_consumption);
+        gauge!("comelit_total_consumption").set(total_consumption);
     }
 }
@@ -0,0 +1,326 @@
+//! Middleware of the web server: client address resolution, access logging, rate
+//! limiting of the control endpoints and rejection of cross-site requests.
+
+use axum::{
+    extract::{ConnectInfo, Extension, Request, State},
+    http::{HeaderMap, Method, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::{info, warn};
+
+/// Number of clients tracked before the idle ones are forgotten.
+const MAX_TRACKED_CLIENTS: usize = 1024;
+
+/// Address of the client that sent a request, set by [`client_ip`].
+#[derive(Debug, Clone, Copy)]
+pub struct ClientIp(pub IpAddr);
+
+/// Resolve the address of the client of each request, taking it from `X-Forwarded-For`
+/// when the request comes from one of the trusted reverse proxies.
+pub async fn client_ip(
+    State(trusted_proxies): State<Arc<[IpAddr]>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let client = forwarded_client(peer.ip(), request.headers(), &trusted_proxies);
+    request.extensions_mut().insert(ClientIp(client));
+    next.run(request).await
+}
+
+/// The last address of `X-Forwarded-For` that is not a trusted proxy, when the peer is one.
+///
+/// Each proxy appends the address it received the request from, so only the entries
+/// after the last untrusted one can be relied on: the ones before may be forged.
+fn forwarded_client(peer: IpAddr, headers: &HeaderMap, trusted_proxies: &[IpAddr]) -> IpAddr {
+    if !trusted_proxies.contains(&peer) {
+        return peer;
+    }
+    let hops: Vec<&str> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .collect();
+    for hop in hops.into_iter().rev() {
+        match hop.trim().parse::<IpAddr>() {
+            Ok(ip) if trusted_proxies.contains(&ip) => continue,
+            Ok(ip) => return ip,
+            Err(_) => break,
+        }
+    }
+    peer
+}
+
+/// Log every request with its client, method, path, status and latency.
+pub async fn access_log(
+    Extension(ClientIp(client)): Extension<ClientIp>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let start = Instant::now();
+
+    let response = next.run(request).await;
+
+    info!(
+        target: "access",
+        client = %client,
+        method = %method,
+        path = %path,
+        status = response.status().as_u16(),
+        latency_ms = start.elapsed().as_millis() as u64,
+        "{} {} {}",
+        method,
+        path,
+        response.status().as_u16()
+    );
+    response
+}
+
+/// Reject the requests of clients that exceeded their rate limit with `429 Too Many Requests`.
+pub async fn rate_limit(
+    State(limiter): State<Arc<RateLimiter>>,
+    Extension(ClientIp(client)): Extension<ClientIp>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if limiter.check(client) {
+        next.run(request).await
+    } else {
+        warn!(
+            "Rate limit exceeded by {} on {}",
+            client,
+            request.uri().path()
+        );
+        (
+            StatusCode::TOO_MANY_REQUESTS,
+            [("retry-after", limiter.retry_after().to_string())],
+            "Too many requests",
+        )
+            .into_response()
+    }
+}
+
+/// Reject with `403 Forbidden` the requests changing state that a browser sent on behalf
+/// of another site, e.g. a form of a malicious page posting to `/devices/{id}/toggle`.
+pub async fn same_origin(request: Request, next: Next) -> Response {
+    let safe = matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    );
+    if safe || is_same_origin(request.headers()) {
+        next.run(request).await
+    } else {
+        warn!(
+            "Rejected cross-site {} request to {}",
+            request.method(),
+            request.uri().path()
+        );
+        (StatusCode::FORBIDDEN, "Cross-site requests are not allowed").into_response()
+    }
+}
+
+/// Whether a request comes from a page of the web UI itself.
+///
+/// Browsers send `Sec-Fetch-Site`, older ones only `Origin`, which must then match the
+/// `Host` the request was sent to. Requests with neither come from other clients
+/// (e.g. curl), which cannot be driven by a web page.
+fn is_same_origin(headers: &HeaderMap) -> bool {
+    if let Some(site) = headers.get("sec-fetch-site") {
+        // `none` is a navigation started by the user, e.g. a bookmark
+        return site == "same-origin" || site == "none";
+    }
+    let Some(origin) = headers.get(header::ORIGIN) else {
+        return true;
+    };
+    let origin_host = origin
+        .to_str()
+        .ok()
+        .and_then(|origin| origin.split_once("://"))
+        .map(|(_, host)| host);
+    let host = headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok());
+    origin_host.is_some_and(|origin_host| Some(origin_host) == host)
+}
+
+/// Token bucket of a client.
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Per-client token bucket rate limiter.
+///
+/// Each client can send up to `per_minute` requests in a burst, then one every
+/// `60 / per_minute` seconds.
+#[derive(Debug)]
+pub struct RateLimiter {
+    per_minute: u32,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(per_minute: u32) -> Self {
+        Self {
+            per_minute: per_minute.max(1),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take a token for a request of the client, returning false when it has none left.
+    pub fn check(&self, client: IpAddr) -> bool {
+        self.check_at(client, Instant::now())
+    }
+
+    /// Seconds a limited client has to wait for its next token.
+    pub fn retry_after(&self) -> u64 {
+        60u64.div_ceil(u64::from(self.per_minute))
+    }
+
+    fn check_at(&self, client: IpAddr, now: Instant) -> bool {
+        let capacity = f64::from(self.per_minute);
+        let refill = |bucket: &Bucket| {
+            let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+            (bucket.tokens + elapsed * capacity / 60.0).min(capacity)
+        };
+
+        let mut buckets = self.buckets.lock();
+        if buckets.len() >= MAX_TRACKED_CLIENTS {
+            // Clients with a full bucket are in the same state as unknown ones
+            buckets.retain(|_, bucket| refill(bucket) < capacity);
+        }
+        let bucket = buckets.entry(client).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+        bucket.tokens = refill(bucket);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_rate_limiter() {
+        let limiter = RateLimiter::new(3);
+        let client: IpAddr = [192, 168, 1, 10].into();
+        let other: IpAddr = [192, 168, 1, 11].into();
+        let now = Instant::now();
+
+        assert!(limiter.check_at(client, now));
+        assert!(limiter.check_at(client, now));
+        assert!(limiter.check_at(client, now));
+        assert!(!limiter.check_at(client, now));
+        // Other clients have their own budget
+        assert!(limiter.check_at(other, now));
+        // One token every 20 seconds
+        assert!(!limiter.check_at(client, now + Duration::from_secs(10)));
+        assert!(limiter.check_at(client, now + Duration::from_secs(21)));
+        assert_eq!(limiter.retry_after(), 20);
+    }
+
+    #[test]
+    fn test_is_same_origin() {
+        let headers = |pairs: &[(&'static str, &'static str)]| {
+            let mut headers = HeaderMap::new();
+            for (name, value) in pairs {
+                headers.insert(*name, value.parse().unwrap());
+            }
+            headers
+        };
+
+        // Clients that are not browsers
+        assert!(is_same_origin(&HeaderMap::new()));
+        assert!(is_same_origin(&headers(&[(
+            "sec-fetch-site",
+            "same-origin"
+        )])));
+        assert!(!is_same_origin(&headers(&[(
+            "sec-fetch-site",
+            "cross-site"
+        )])));
+        assert!(!is_same_origin(&headers(&[(
+            "sec-fetch-site",
+            "same-site"
+        )])));
+        assert!(is_same_origin(&headers(&[
+            ("origin", "http://192.168.1.2:8080"),
+            ("host", "192.168.1.2:8080"),
+        ])));
+        assert!(!is_same_origin(&headers(&[
+            ("origin", "http://evil.example"),
+            ("host", "192.168.1.2:8080"),
+        ])));
+        assert!(!is_same_origin(&headers(&[
+            ("origin", "null"),
+            ("host", "192.168.1.2:8080"),
+        ])));
+    }
+
+    #[tokio::test]
+    async fn test_same_origin() {
+        use axum::{Router, body::Body, middleware::from_fn, routing::post};
+        use tower::ServiceExt;
+
+        let app = Router::new()
+            .route("/settings", post(|| async { "saved" }))
+            .layer(from_fn(same_origin));
+        let request = |site: &str| {
+            axum::http::Request::post("/settings")
+                .header("sec-fetch-site", site)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        // A form of another site cannot rewrite the settings
+        let response = app.clone().oneshot(request("cross-site")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = app.oneshot(request("same-origin")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_forwarded_client() {
+        let proxy: IpAddr = [10, 0, 0, 1].into();
+        let peer: IpAddr = [192, 168, 1, 10].into();
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "1.2.3.4, 192.168.1.20".parse().unwrap());
+
+        // Only trusted proxies can set the client address
+        assert_eq!(forwarded_client(peer, &headers, &[]), peer);
+        assert_eq!(forwarded_client(peer, &headers, &[proxy]), peer);
+        // The client is the last hop that is not a proxy, the ones before may be forged
+        assert_eq!(
+            forwarded_client(proxy, &headers, &[proxy]),
+            IpAddr::from([192, 168, 1, 20])
+        );
+        headers.append("x-forwarded-for", "10.0.0.1".parse().unwrap());
+        assert_eq!(
+            forwarded_client(proxy, &headers, &[proxy]),
+            IpAddr::from([192, 168, 1, 20])
+        );
+        // Falls back to the proxy when the header is missing or malformed
+        headers.insert("x-forwarded-for", "unknown".parse().unwrap());
+        assert_eq!(forwarded_client(proxy, &headers, &[proxy]), proxy);
+        assert_eq!(forwarded_client(proxy, &HeaderMap::new(), &[proxy]), proxy);
+    }
+}
@@ -3,28 +3,44 @@
 //! This module provides a simple web interface for monitoring the bridge
 //! and a Prometheus metrics endpoint for external monitoring.
 
+pub mod assets;
+pub mod build_info;
+pub mod control;
+pub mod i18n;
 pub mod metrics;
+pub mod middleware;
+pub mod openapi;
 pub mod qrcode_template;
 pub mod state;
 
 use axum::{
-    Router,
-    extract::{Query, State},
+    Form, Json, Router,
+    extract::{Path, Query, State},
     http::StatusCode,
-    response::{Html, IntoResponse, Response},
-    routing::get,
+    middleware::{from_fn, from_fn_with_state},
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::{get, post},
 };
 use metrics_exporter_prometheus::PrometheusHandle;
 use minijinja::{Environment, context};
 use parking_lot::RwLock;
+use serde::Deserialize;
 use std::collections::HashMap;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 use tokio::net::TcpListener;
-use tracing::{error, info};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tower_http::compression::CompressionLayer;
+use tower_http::trace::TraceLayer;
+use tracing::{error, info, warn};
 
+use crate::settings::{Language, Settings};
+use crate::web::control::{ControlError, DeviceCommand};
 use crate::web::metrics::Metrics;
-use crate::web::state::{BridgeState, DeviceType};
+use crate::web::state::{BridgeState, DeviceSample, DeviceType, IndexEntry, LogEvent, RingEvent};
 
 /// Application state shared with all route handlers.
 #[derive(Clone)]
@@ -41,28 +57,57 @@ pub struct AppState {
     pub prometheus_token: Option<String>,
     /// HTTP client for proxying Prometheus requests.
     pub http_client: reqwest::Client,
+    /// URL prefix the UI is mounted at (empty when served at `/`).
+    pub base_path: String,
+    /// Settings file edited by the settings page.
+    pub settings_path: Option<PathBuf>,
 }
 
 /// Web server configuration.
 #[derive(Debug, Clone)]
 pub struct WebConfig {
+    /// Address to listen on (e.g. `127.0.0.1` to restrict the UI to localhost).
+    pub bind_addr: IpAddr,
     /// Port to listen on.
     pub port: u16,
+    /// URL prefix the UI is served at, e.g. `/comelit` behind a reverse proxy.
+    pub base_path: String,
     /// Whether to enable the web UI.
     pub enabled: bool,
     /// Optional Prometheus server URL for the charts page.
     pub prometheus_url: Option<String>,
     /// Optional Bearer token for Prometheus authentication.
     pub prometheus_token: Option<String>,
+    /// Settings file edited by the settings page, if any.
+    pub settings_path: Option<PathBuf>,
+    /// Serve `/metrics` and the health probes on this port only, instead of with the UI.
+    pub metrics_port: Option<u16>,
+    /// Requests per minute each client can send to the device control endpoints (unlimited when `None`).
+    pub rate_limit: Option<u32>,
+    /// Log every request (client, method, path, status and latency).
+    pub access_log: bool,
+    /// Reverse proxies whose `X-Forwarded-For` header gives the client address, for the
+    /// access log and the rate limit. The header is ignored when empty.
+    pub trusted_proxies: Vec<IpAddr>,
+    /// Language of the web UI.
+    pub language: Language,
 }
 
 impl Default for WebConfig {
     fn default() -> Self {
         Self {
+            bind_addr: IpAddr::from([0, 0, 0, 0]),
             port: 8080,
+            base_path: String::new(),
             enabled: true,
             prometheus_url: None,
             prometheus_token: None,
+            settings_path: None,
+            metrics_port: None,
+            rate_limit: Some(60),
+            access_log: false,
+            trusted_proxies: vec![],
+            language: Language::En,
         }
     }
 }
@@ -70,14 +115,14 @@ impl Default for WebConfig {
 /// Start the web server.
 ///
 /// This function spawns the web server in the background and returns immediately.
-/// The server will run until the application shuts down.
+/// The server runs until the returned handle is shut down or dropped.
 pub async fn start_web_server(
     config: WebConfig,
     bridge_state: BridgeState,
-) -> Result<(), std::io::Error> {
+) -> Result<Option<WebServerHandle>, std::io::Error> {
     if !config.enabled {
         info!("Web UI is disabled");
-        return Ok(());
+        return Ok(None);
     }
 
     // Initialize Prometheus metrics
@@ -95,6 +140,24 @@ pub async fn start_web_server(
         .expect("Failed to add devices template");
     env.add_template("charts.html", include_str!("../../templates/charts.html"))
         .expect("Failed to add charts template");
+    env.add_template("device.html", include_str!("../../templates/device.html"))
+        .expect("Failed to add device template");
+    env.add_template("events.html", include_str!("../../templates/events.html"))
+        .expect("Failed to add events template");
+    env.add_template("pairing.html", include_str!("../../templates/pairing.html"))
+        .expect("Failed to add pairing template");
+    env.add_template(
+        "settings.html",
+        include_str!("../../templates/settings.html"),
+    )
+    .expect("Failed to add settings template");
+
+    let base_path = normalize_base_path(&config.base_path);
+    env.add_global("base_path", base_path.clone());
+    env.add_global("metrics_on_ui", config.metrics_port.is_none());
+    env.add_global("lang", config.language.code());
+    env.add_global("asset_version", assets::version());
+    env.add_function("t", i18n::t);
 
     let app_state = AppState {
         bridge_state,
@@ -103,32 +166,175 @@ pub async fn start_web_server(
         prometheus_url: config.prometheus_url.clone(),
         prometheus_token: config.prometheus_token.clone(),
         http_client: reqwest::Client::new(),
+        base_path: base_path.clone(),
+        settings_path: config.settings_path.clone(),
+    };
+
+    // Endpoints sending commands to the hub, rate limited per client
+    let control = Router::new()
+        .route("/devices/{id}/toggle", post(toggle_handler))
+        .route("/devices/{id}/target", post(target_handler))
+        .route("/api/devices/{id}/action", post(api_device_action_handler));
+    let control = match config.rate_limit {
+        Some(per_minute) => control.route_layer(from_fn_with_state(
+            Arc::new(middleware::RateLimiter::new(per_minute)),
+            middleware::rate_limit,
+        )),
+        None => control,
     };
 
     // Build router
     let app = Router::new()
         .route("/", get(index_handler))
         .route("/devices", get(devices_handler))
+        .route("/devices/{id}", get(device_handler))
+        .route("/events", get(events_handler))
         .route("/charts", get(charts_handler))
-        .route("/health", get(health_handler))
-        .route("/metrics", get(metrics_handler))
+        .route(
+            "/settings",
+            get(settings_handler).post(save_settings_handler),
+        )
+        .route("/health", get(readyz_handler))
+        .route("/livez", get(livez_handler))
+        .route("/readyz", get(readyz_handler))
         .route("/api/status", get(api_status_handler))
+        .route("/api/openapi.json", get(api_openapi_handler))
+        .route("/api/version", get(api_version_handler))
+        .route("/api/devices/export", get(api_devices_export_handler))
+        .route("/api/devices/{id}", get(api_device_handler))
+        .route("/api/rings", get(api_rings_handler))
+        .route("/api/events", get(api_events_handler))
         .route("/api/prom/query_range", get(prom_proxy_handler))
+        .route("/pairing", get(pairing_handler))
         .route("/qrcode.svg", get(qrcode_handler))
-        .with_state(app_state);
+        .route("/qrcode.png", get(qrcode_png_handler))
+        .route("/static/{*path}", get(assets::asset_handler))
+        .merge(control);
+    #[cfg(feature = "history")]
+    let app = app.route("/api/history/query_range", get(history_query_handler));
+    let app = if config.metrics_port.is_none() {
+        app.route("/metrics", get(metrics_handler))
+    } else {
+        app
+    };
+    // Browsers cannot be used by other sites to change the settings or control devices
+    let app = app
+        .layer(from_fn(middleware::same_origin))
+        .with_state(app_state.clone());
+
+    let app = if base_path.is_empty() {
+        app
+    } else {
+        // Also accept the prefix with a trailing slash, as forwarded by most proxies
+        let root = base_path.clone();
+        Router::new()
+            .route(
+                &format!("{base_path}/"),
+                get(move || async move { Redirect::permanent(&root) }),
+            )
+            .nest(&base_path, app)
+    };
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], config.port));
-    info!("Starting web server on http://{}", addr);
+    // A span per request, exported with the other traces when OTLP is enabled
+    let app = app
+        .layer(CompressionLayer::new())
+        .layer(TraceLayer::new_for_http());
+    let app = if config.access_log {
+        app.layer(from_fn(middleware::access_log))
+    } else {
+        app
+    };
+
+    let addr = SocketAddr::new(config.bind_addr, config.port);
+    info!("Starting web server on http://{}{}/", addr, base_path);
 
     let listener = TcpListener::bind(addr).await?;
 
+    let trusted_proxies: Arc<[IpAddr]> = config.trusted_proxies.into();
+    let (shutdown, shutdown_requested) = watch::channel(());
+    let mut tasks = vec![spawn_server(
+        listener,
+        app,
+        trusted_proxies.clone(),
+        shutdown_requested.clone(),
+    )];
+
+    if let Some(metrics_port) = config.metrics_port {
+        let metrics_app = Router::new()
+            .route("/metrics", get(metrics_handler))
+            .route("/health", get(readyz_handler))
+            .route("/livez", get(livez_handler))
+            .route("/readyz", get(readyz_handler))
+            .with_state(app_state);
+        let metrics_app = if config.access_log {
+            metrics_app.layer(from_fn(middleware::access_log))
+        } else {
+            metrics_app
+        };
+        let metrics_addr = SocketAddr::new(config.bind_addr, metrics_port);
+        info!("Serving metrics on http://{}/metrics", metrics_addr);
+        let metrics_listener = TcpListener::bind(metrics_addr).await?;
+        tasks.push(spawn_server(
+            metrics_listener,
+            metrics_app,
+            trusted_proxies,
+            shutdown_requested,
+        ));
+    }
+
+    Ok(Some(WebServerHandle { shutdown, tasks }))
+}
+
+/// Serve the app on the listener until a shutdown is requested through the channel.
+fn spawn_server(
+    listener: TcpListener,
+    app: Router,
+    trusted_proxies: Arc<[IpAddr]>,
+    mut shutdown_requested: watch::Receiver<()>,
+) -> JoinHandle<()> {
     tokio::spawn(async move {
-        if let Err(e) = axum::serve(listener, app).await {
+        // The client address is used by the access log and the rate limiter
+        let app = app
+            .layer(from_fn_with_state(trusted_proxies, middleware::client_ip))
+            .into_make_service_with_connect_info::<SocketAddr>();
+        let server = axum::serve(listener, app).with_graceful_shutdown(async move {
+            // Resolves on shutdown or when the handle is dropped
+            let _ = shutdown_requested.changed().await;
+        });
+        if let Err(e) = server.await {
             error!("Web server error: {}", e);
         }
-    });
+    })
+}
 
-    Ok(())
+/// Handle to the running web server, used to stop it gracefully.
+///
+/// Dropping the handle also stops the server.
+pub struct WebServerHandle {
+    shutdown: watch::Sender<()>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl WebServerHandle {
+    /// Stop accepting connections and wait, up to `timeout`, for in-flight requests to complete.
+    pub async fn shutdown(self, timeout: Duration) {
+        info!("Stopping web server");
+        let _ = self.shutdown.send(());
+        let tasks = futures::future::join_all(self.tasks);
+        if tokio::time::timeout(timeout, tasks).await.is_err() {
+            warn!("Web server did not stop within {:?}", timeout);
+        }
+    }
+}
+
+/// Normalize a URL prefix to the `/prefix` form, or to an empty string for the root.
+fn normalize_base_path(path: &str) -> String {
+    let path = path.trim_matches('/');
+    if path.is_empty() {
+        String::new()
+    } else {
+        format!("/{path}")
+    }
 }
 
 /// Index page handler - shows bridge overview.
@@ -168,6 +374,7 @@ async fn index_handler(State(state): State<AppState>) -> Response {
         window_covering_count => summary.device_counts.get(&DeviceType::WindowCovering).unwrap_or(&0),
         door_count => summary.device_counts.get(&DeviceType::Door).unwrap_or(&0),
         doorbell_count => summary.device_counts.get(&DeviceType::Doorbell).unwrap_or(&0),
+        switch_count => summary.device_counts.get(&DeviceType::Switch).unwrap_or(&0),
         last_ping_seconds_ago => summary.last_ping_seconds_ago,
         ping_count => summary.ping_count,
         ping_failures => summary.ping_failures,
@@ -211,6 +418,31 @@ async fn devices_handler(State(state): State<AppState>) -> Response {
         .iter()
         .filter(|d| d.device_type == DeviceType::Doorbell)
         .collect();
+    let switches: Vec<_> = devices
+        .iter()
+        .filter(|d| d.device_type == DeviceType::Switch)
+        .collect();
+    // Meters have no status, their latest power sample is shown instead
+    let meters: Vec<_> = devices
+        .iter()
+        .filter(|d| d.device_type == DeviceType::Meter)
+        .map(|d| {
+            let mut map = std::collections::HashMap::new();
+            map.insert("id", d.id.clone());
+            map.insert("path_id", encode_path_segment(&d.id));
+            map.insert("name", d.name.clone());
+            map.insert(
+                "power",
+                state
+                    .bridge_state
+                    .history(&d.id)
+                    .last()
+                    .map(|s| format!("{} W", s.value))
+                    .unwrap_or_else(|| "—".to_string()),
+            );
+            map
+        })
+        .collect();
 
     let templates = state.templates.read();
     let template = match templates.get_template("devices.html") {
@@ -229,6 +461,7 @@ async fn devices_handler(State(state): State<AppState>) -> Response {
                 .map(|d| {
                     let mut map = std::collections::HashMap::new();
                     map.insert("id", d.id.clone());
+                    map.insert("path_id", encode_path_segment(&d.id));
                     map.insert("name", d.name.clone());
                     map.insert("status", d.status.clone());
                     map.insert(
@@ -242,6 +475,19 @@ async fn devices_handler(State(state): State<AppState>) -> Response {
                 .collect()
         };
 
+    let rings: Vec<_> = state
+        .bridge_state
+        .rings()
+        .iter()
+        .map(|ring| {
+            let mut map = std::collections::HashMap::new();
+            map.insert("name", ring.name.clone());
+            map.insert("device_id", ring.device_id.clone());
+            map.insert("ago", format!("{}s ago", seconds_ago(ring.time)));
+            map
+        })
+        .collect();
+
     let html = match template.render(context! {
         title => "Devices - Comelit HUB Bridge",
         lights => to_device_list(lights),
@@ -249,7 +495,11 @@ async fn devices_handler(State(state): State<AppState>) -> Response {
         window_coverings => to_device_list(window_coverings),
         doors => to_device_list(doors),
         doorbells => to_device_list(doorbells),
+        switches => to_device_list(switches),
+        meters => meters,
+        rings => rings,
         total_count => devices.len(),
+        can_control => state.bridge_state.client().is_some(),
     }) {
         Ok(html) => html,
         Err(e) => {
@@ -261,17 +511,432 @@ async fn devices_handler(State(state): State<AppState>) -> Response {
     Html(html).into_response()
 }
 
-/// Health check endpoint.
-async fn health_handler(State(state): State<AppState>) -> Response {
+/// Width of the history sparkline on the device page, in pixels.
+const SPARKLINE_WIDTH: f64 = 300.0;
+/// Height of the history sparkline on the device page, in pixels.
+const SPARKLINE_HEIGHT: f64 = 60.0;
+
+/// Points of an SVG polyline drawing the samples, scaled to fill `width` x `height`.
+fn sparkline_points(samples: &[DeviceSample], width: f64, height: f64) -> String {
+    let min = samples
+        .iter()
+        .map(|s| s.value)
+        .fold(f64::INFINITY, f64::min);
+    let max = samples
+        .iter()
+        .map(|s| s.value)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let step = width / samples.len().saturating_sub(1).max(1) as f64;
+    samples
+        .iter()
+        .enumerate()
+        .map(|(i, sample)| {
+            let y = if max > min {
+                height - (sample.value - min) / (max - min) * height
+            } else {
+                height / 2.0
+            };
+            format!("{:.1},{:.1}", i as f64 * step, y)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Device page handler - shows the last update fields and recent history of a device.
+async fn device_handler(State(state): State<AppState>, Path(id): Path<String>) -> Response {
+    let Some(device) = state.bridge_state.device(&id) else {
+        return (StatusCode::NOT_FOUND, "Device not found").into_response();
+    };
+
+    let templates = state.templates.read();
+    let template = match templates.get_template("device.html") {
+        Ok(t) => t,
+        Err(e) => {
+            error!("Failed to get device template: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Template error").into_response();
+        }
+    };
+
+    let history = state.bridge_state.history(&id);
+    let values = history.iter().map(|s| s.value);
+    let html = match template.render(context! {
+        title => format!("{} - Comelit HUB Bridge", device.name),
+        active_page => "devices",
+        device => context! {
+            id => device.id,
+            path_id => encode_path_segment(&device.id),
+            name => device.name,
+            kind => device.device_type.display_name(),
+            status => device.status,
+            last_update => device
+                .last_update
+                .map(|t| format!("{}s ago", t.elapsed().as_secs()))
+                .unwrap_or_else(|| "never".to_string()),
+        },
+        fields => state.bridge_state.device_fields(&id),
+        history => context! {
+            count => history.len(),
+            latest => history.last().map(|s| s.value),
+            min => values.clone().reduce(f64::min),
+            max => values.reduce(f64::max),
+            since => history.first().map(|s| format!("{}s ago", seconds_ago(s.time))),
+            points => sparkline_points(&history, SPARKLINE_WIDTH, SPARKLINE_HEIGHT),
+            width => SPARKLINE_WIDTH,
+            height => SPARKLINE_HEIGHT,
+        },
+    }) {
+        Ok(html) => html,
+        Err(e) => {
+            error!("Failed to render device template: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Render error").into_response();
+        }
+    };
+
+    Html(html).into_response()
+}
+
+/// Percent-encode a device ID (e.g. `DOM#LT#1.1`) for use as a URL path segment.
+fn encode_path_segment(id: &str) -> String {
+    id.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Form posted by the device target controls.
+#[derive(Debug, Deserialize)]
+struct TargetForm {
+    value: f64,
+}
+
+/// HTTP status code matching a control error.
+fn control_error_status(error: &ControlError) -> StatusCode {
+    match error {
+        ControlError::NotFound(_) => StatusCode::NOT_FOUND,
+        ControlError::NotConnected => StatusCode::SERVICE_UNAVAILABLE,
+        ControlError::Unsupported(_) => StatusCode::BAD_REQUEST,
+        ControlError::Client(_) => StatusCode::BAD_GATEWAY,
+    }
+}
+
+/// Map a control error to an HTTP response.
+fn control_error_response(error: ControlError) -> Response {
+    (control_error_status(&error), error.to_string()).into_response()
+}
+
+/// Toggle handler - switches a light or switch on/off.
+///
+/// Doors are only opened through the JSON API, which other sites cannot call
+/// without a CORS preflight.
+async fn toggle_handler(State(state): State<AppState>, Path(id): Path<String>) -> Response {
+    let is_door = state
+        .bridge_state
+        .device(&id)
+        .is_some_and(|device| device.device_type == DeviceType::Door);
+    if is_door {
+        return (
+            StatusCode::BAD_REQUEST,
+            "Doors can only be opened through the API",
+        )
+            .into_response();
+    }
+    match control::send_command(&state.bridge_state, &id, DeviceCommand::Toggle).await {
+        Ok(()) => Redirect::to(&format!("{}/devices", state.base_path)).into_response(),
+        Err(e) => {
+            warn!("Failed to toggle device {} from the web UI: {}", id, e);
+            control_error_response(e)
+        }
+    }
+}
+
+/// Target handler - moves a window covering or sets a thermostat temperature.
+async fn target_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Form(form): Form<TargetForm>,
+) -> Response {
+    let command = DeviceCommand::SetTarget { value: form.value };
+    match control::send_command(&state.bridge_state, &id, command).await {
+        Ok(()) => Redirect::to(&format!("{}/devices", state.base_path)).into_response(),
+        Err(e) => {
+            warn!(
+                "Failed to set target of device {} from the web UI: {}",
+                id, e
+            );
+            control_error_response(e)
+        }
+    }
+}
+
+/// JSON representation of a device, as returned by the devices API.
+fn device_json(device: &state::DeviceInfo) -> serde_json::Value {
+    serde_json::json!({
+        "id": device.id,
+        "name": device.name,
+        "type": device.device_type.as_str(),
+        "status": device.status,
+        "last_update_seconds_ago": device.last_update.map(|t| t.elapsed().as_secs()),
+    })
+}
+
+/// JSON error response of the REST API.
+fn api_error_response(error: ControlError) -> Response {
+    (
+        control_error_status(&error),
+        [("content-type", "application/json")],
+        serde_json::json!({ "error": error.to_string() }).to_string(),
+    )
+        .into_response()
+}
+
+/// API device endpoint - returns a single device as JSON.
+async fn api_device_handler(State(state): State<AppState>, Path(id): Path<String>) -> Response {
+    match state.bridge_state.device(&id) {
+        Some(device) => (
+            StatusCode::OK,
+            [("content-type", "application/json")],
+            device_json(&device).to_string(),
+        )
+            .into_response(),
+        None => api_error_response(ControlError::NotFound(id)),
+    }
+}
+
+/// Format of the devices export.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ExportFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExportQuery {
+    #[serde(default)]
+    format: ExportFormat,
+}
+
+/// Quote a CSV field when it contains a separator, a quote or a line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Render the hub index as CSV, with a header line.
+fn index_csv(index: &[IndexEntry]) -> String {
+    let mut csv = String::from("id,type,subtype,room,name,status\r\n");
+    for entry in index {
+        let fields = [
+            entry.id.as_str(),
+            &entry.object_type,
+            &entry.subtype,
+            entry.room.as_deref().unwrap_or_default(),
+            &entry.name,
+            &entry.status,
+        ];
+        let line: Vec<_> = fields.iter().map(|f| csv_field(f)).collect();
+        csv.push_str(&line.join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+/// JSON representation of a device of the hub index, as returned by the export API.
+fn index_json(entry: &IndexEntry) -> serde_json::Value {
+    serde_json::json!({
+        "id": entry.id,
+        "type": entry.object_type,
+        "subtype": entry.subtype,
+        "room": entry.room,
+        "name": entry.name,
+        "status": entry.status,
+    })
+}
+
+/// API devices export endpoint - downloads every device of the hub index as JSON or CSV.
+///
+/// Unlike the other device endpoints, this includes the devices not exposed to HomeKit.
+async fn api_devices_export_handler(
+    State(state): State<AppState>,
+    Query(query): Query<ExportQuery>,
+) -> Response {
+    let index = state.bridge_state.index();
+    let (content_type, filename, body) = match query.format {
+        ExportFormat::Json => (
+            "application/json",
+            "comelit-devices.json",
+            serde_json::Value::from(index.iter().map(index_json).collect::<Vec<_>>()).to_string(),
+        ),
+        ExportFormat::Csv => (
+            "text/csv; charset=utf-8",
+            "comelit-devices.csv",
+            index_csv(&index),
+        ),
+    };
+
+    (
+        StatusCode::OK,
+        [
+            ("content-type", content_type.to_string()),
+            (
+                "content-disposition",
+                format!("attachment; filename=\"{filename}\""),
+            ),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+/// API device action endpoint - sends a command to a device.
+///
+/// The body is a JSON object such as `{"action": "toggle"}` or
+/// `{"action": "set_target", "value": 21.5}`.
+async fn api_device_action_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(command): Json<DeviceCommand>,
+) -> Response {
+    match control::send_command(&state.bridge_state, &id, command).await {
+        Ok(()) => (
+            StatusCode::OK,
+            [("content-type", "application/json")],
+            serde_json::json!({ "status": "ok" }).to_string(),
+        )
+            .into_response(),
+        Err(e) => {
+            warn!(
+                "Failed to run {:?} on device {} from the API: {}",
+                command, id, e
+            );
+            api_error_response(e)
+        }
+    }
+}
+
+/// Seconds elapsed since the given wall-clock time.
+fn seconds_ago(time: SystemTime) -> u64 {
+    time.elapsed().map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// JSON representation of a ring event, as returned by the rings API.
+fn ring_json(ring: &RingEvent) -> serde_json::Value {
+    serde_json::json!({
+        "device_id": ring.device_id,
+        "name": ring.name,
+        "timestamp": ring
+            .time
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        "seconds_ago": seconds_ago(ring.time),
+    })
+}
+
+/// API rings endpoint - returns the doorbell ring history as JSON, newest first.
+async fn api_rings_handler(State(state): State<AppState>) -> Response {
+    let rings: Vec<_> = state.bridge_state.rings().iter().map(ring_json).collect();
+
+    (
+        StatusCode::OK,
+        [("content-type", "application/json")],
+        serde_json::Value::from(rings).to_string(),
+    )
+        .into_response()
+}
+
+/// JSON representation of an event log entry, as returned by the events API.
+fn event_json(event: &LogEvent) -> serde_json::Value {
+    serde_json::json!({
+        "kind": event.kind.as_str(),
+        "device_id": event.device_id,
+        "message": event.message,
+        "timestamp": event
+            .time
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        "seconds_ago": seconds_ago(event.time),
+    })
+}
+
+/// API events endpoint - returns the event log as JSON, newest first.
+async fn api_events_handler(State(state): State<AppState>) -> Response {
+    let events: Vec<_> = state.bridge_state.events().iter().map(event_json).collect();
+
+    (
+        StatusCode::OK,
+        [("content-type", "application/json")],
+        serde_json::Value::from(events).to_string(),
+    )
+        .into_response()
+}
+
+/// Events page handler - shows the recent device updates, rings and errors.
+async fn events_handler(State(state): State<AppState>) -> Response {
+    let templates = state.templates.read();
+    let template = match templates.get_template("events.html") {
+        Ok(t) => t,
+        Err(e) => {
+            error!("Failed to get events template: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Template error").into_response();
+        }
+    };
+
+    let events: Vec<_> = state
+        .bridge_state
+        .events()
+        .iter()
+        .map(|event| {
+            let mut map = HashMap::new();
+            map.insert("kind", event.kind.as_str().to_string());
+            map.insert("device_id", event.device_id.clone().unwrap_or_default());
+            map.insert("message", event.message.clone());
+            map.insert("ago", format!("{}s ago", seconds_ago(event.time)));
+            map
+        })
+        .collect();
+
+    let html = match template.render(context! {
+        title => "Events - Comelit HUB Bridge",
+        active_page => "events",
+        events => events,
+    }) {
+        Ok(html) => html,
+        Err(e) => {
+            error!("Failed to render events template: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Render error").into_response();
+        }
+    };
+
+    Html(html).into_response()
+}
+
+/// Liveness probe - always succeeds while the process is running.
+async fn livez_handler() -> Response {
+    (StatusCode::OK, "OK").into_response()
+}
+
+/// Readiness probe, also served as `/health` - succeeds when the bridge is connected
+/// to the hub and pinged it in the last 2 minutes.
+async fn readyz_handler(State(state): State<AppState>) -> Response {
     let summary = state.bridge_state.summary();
 
-    let is_healthy = summary.connection_status == state::ConnectionStatus::Connected
+    let is_ready = summary.connection_status == state::ConnectionStatus::Connected
         && summary
             .last_ping_seconds_ago
             .map(|s| s < 120)
             .unwrap_or(false);
 
-    if is_healthy {
+    if is_ready {
         (StatusCode::OK, "OK").into_response()
     } else {
         (StatusCode::SERVICE_UNAVAILABLE, "UNHEALTHY").into_response()
@@ -313,14 +978,289 @@ async fn qrcode_handler(State(state): State<AppState>) -> Response {
     }
 }
 
+/// Default width in pixels of the PNG QR code.
+const QR_PNG_DEFAULT_SIZE: u32 = 400;
+
+/// Largest width in pixels of the PNG QR code that can be requested.
+const QR_PNG_MAX_SIZE: u32 = 2048;
+
+#[derive(Debug, Deserialize)]
+struct QrQuery {
+    size: Option<u32>,
+}
+
+/// QR code PNG endpoint - returns the pairing QR code as a raster image.
+///
+/// The width in pixels can be set with `?size=`, for browsers and wallboards
+/// that cannot render the SVG version.
+async fn qrcode_png_handler(
+    State(state): State<AppState>,
+    Query(query): Query<QrQuery>,
+) -> Response {
+    let summary = state.bridge_state.summary();
+
+    if summary.pairing_url.is_empty() {
+        return (StatusCode::NOT_FOUND, "Pairing info not available").into_response();
+    }
+
+    let size = query
+        .size
+        .unwrap_or(QR_PNG_DEFAULT_SIZE)
+        .clamp(64, QR_PNG_MAX_SIZE);
+    match qrcode_template::generate_qr_png(&summary.pairing_url, size) {
+        Ok(png) => (StatusCode::OK, [("content-type", "image/png")], png).into_response(),
+        Err(e) => {
+            error!("Failed to generate QR code: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to generate QR code",
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Pairing card handler - printable page with the QR code, PIN and setup ID.
+async fn pairing_handler(State(state): State<AppState>) -> Response {
+    let summary = state.bridge_state.summary();
+
+    let templates = state.templates.read();
+    let template = match templates.get_template("pairing.html") {
+        Ok(t) => t,
+        Err(e) => {
+            error!("Failed to get pairing template: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Template error").into_response();
+        }
+    };
+
+    let html = match template.render(context! {
+        title => "Pairing - Comelit HUB Bridge",
+        active_page => "home",
+        is_paired => summary.is_paired,
+        pairing_pin => summary.pairing_pin,
+        setup_id => qrcode_template::setup_id(&summary.pairing_url),
+        has_qr => !summary.pairing_url.is_empty(),
+    }) {
+        Ok(html) => html,
+        Err(e) => {
+            error!("Failed to render pairing template: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Render error").into_response();
+        }
+    };
+
+    Html(html).into_response()
+}
+
+/// Form posted by the settings page. Checkboxes are only sent when checked.
+#[derive(Debug, Default, Deserialize)]
+struct SettingsForm {
+    mount_lights: Option<String>,
+    mount_window_covering: Option<String>,
+    mount_thermo: Option<String>,
+    mount_doors: Option<String>,
+    mount_doorbells: Option<String>,
+    mount_others: Option<String>,
+    opening_time: String,
+    closing_time: String,
+    #[serde(default)]
+    names: String,
+    #[serde(default)]
+    language: Language,
+}
+
+/// Parse the name overrides, one `DEVICE_ID = Name` per line.
+fn parse_names(text: &str) -> Result<HashMap<String, String>, Vec<String>> {
+    let mut names = HashMap::new();
+    let mut errors = vec![];
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        match line.split_once('=') {
+            Some((id, name)) if !id.trim().is_empty() && !name.trim().is_empty() => {
+                names.insert(id.trim().to_string(), name.trim().to_string());
+            }
+            _ => errors.push(format!(
+                "Line {} of the name overrides is not in the form DEVICE_ID = Name",
+                n + 1
+            )),
+        }
+    }
+    if errors.is_empty() {
+        Ok(names)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Format the name overrides for the settings page, sorted by device ID.
+fn format_names(names: &HashMap<String, String>) -> String {
+    let mut lines: Vec<_> = names
+        .iter()
+        .map(|(id, name)| format!("{id} = {name}"))
+        .collect();
+    lines.sort();
+    lines.join("\n")
+}
+
+/// Parse a blind travel time, in seconds.
+fn parse_travel_time(label: &str, value: &str) -> Result<u64, String> {
+    match value.trim().parse::<u64>() {
+        Ok(secs) if (1..=600).contains(&secs) => Ok(secs),
+        _ => Err(format!(
+            "{label} must be a number of seconds between 1 and 600"
+        )),
+    }
+}
+
+/// Validate the settings form and apply it to the given settings.
+fn apply_settings_form(settings: &mut Settings, form: &SettingsForm) -> Result<(), Vec<String>> {
+    let mut errors = vec![];
+    let opening_time = parse_travel_time("Opening time", &form.opening_time)
+        .map_err(|e| errors.push(e))
+        .ok();
+    let closing_time = parse_travel_time("Closing time", &form.closing_time)
+        .map_err(|e| errors.push(e))
+        .ok();
+    let names = parse_names(&form.names).map_err(|e| errors.extend(e)).ok();
+    let (Some(opening_time), Some(closing_time), Some(names)) = (opening_time, closing_time, names)
+    else {
+        return Err(errors);
+    };
+
+    settings.mount_lights = Some(form.mount_lights.is_some());
+    settings.mount_window_covering = Some(form.mount_window_covering.is_some());
+    settings.mount_thermo = Some(form.mount_thermo.is_some());
+    settings.mount_doors = Some(form.mount_doors.is_some());
+    settings.mount_doorbells = Some(form.mount_doorbells.is_some());
+    settings.mount_others = Some(form.mount_others.is_some());
+    settings.window_covering.opening_time = opening_time;
+    settings.window_covering.closing_time = closing_time;
+    settings.names = names;
+    settings.language = form.language;
+    Ok(())
+}
+
+/// Render the settings page.
+fn render_settings(
+    state: &AppState,
+    settings: &Settings,
+    errors: &[String],
+    saved: bool,
+) -> Response {
+    let templates = state.templates.read();
+    let template = match templates.get_template("settings.html") {
+        Ok(t) => t,
+        Err(e) => {
+            error!("Failed to get settings template: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Template error").into_response();
+        }
+    };
+
+    let flag = |key: &str, label: &str, value: Option<bool>| serde_json::json!({ "key": key, "label": label, "value": value.unwrap_or_default() });
+    let mount_flags = vec![
+        flag("mount_lights", "Lights", settings.mount_lights),
+        flag(
+            "mount_window_covering",
+            "Window coverings",
+            settings.mount_window_covering,
+        ),
+        flag("mount_thermo", "Thermostats", settings.mount_thermo),
+        flag("mount_doors", "Doors", settings.mount_doors),
+        flag("mount_doorbells", "Doorbells", settings.mount_doorbells),
+        flag(
+            "mount_others",
+            "Other outputs (switches)",
+            settings.mount_others,
+        ),
+    ];
+
+    let html = match template.render(context! {
+        title => "Settings - Comelit HUB Bridge",
+        active_page => "settings",
+        settings_path => state.settings_path.as_ref().map(|p| p.display().to_string()),
+        mount_flags => mount_flags,
+        opening_time => settings.window_covering.opening_time,
+        closing_time => settings.window_covering.closing_time,
+        names => format_names(&settings.names),
+        language => settings.language.code(),
+        errors => errors,
+        saved => saved,
+    }) {
+        Ok(html) => html,
+        Err(e) => {
+            error!("Failed to render settings template: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Render error").into_response();
+        }
+    };
+
+    let status = if errors.is_empty() {
+        StatusCode::OK
+    } else {
+        StatusCode::BAD_REQUEST
+    };
+    (status, Html(html)).into_response()
+}
+
+/// Settings page handler - shows the current settings in an editable form.
+async fn settings_handler(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    let path = state.settings_path.as_ref().and_then(|p| p.to_str());
+    match Settings::load(path) {
+        Ok(settings) => render_settings(&state, &settings, &[], params.contains_key("saved")),
+        Err(e) => {
+            error!("Failed to load settings: {:#}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, format!("{e:#}")).into_response()
+        }
+    }
+}
+
+/// Settings save handler - validates and writes the settings, then restarts the bridge.
+///
+/// Cross-site posts are rejected by [`middleware::same_origin`], otherwise any page
+/// open in a browser of the LAN could choose the settings the bridge restarts with.
+async fn save_settings_handler(
+    State(state): State<AppState>,
+    Form(form): Form<SettingsForm>,
+) -> Response {
+    let Some(path) = state.settings_path.clone() else {
+        return (StatusCode::NOT_FOUND, "No settings file configured").into_response();
+    };
+    let mut settings = match Settings::load(path.to_str()) {
+        Ok(settings) => settings,
+        Err(e) => {
+            error!("Failed to load settings: {:#}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, format!("{e:#}")).into_response();
+        }
+    };
+    if let Err(errors) = apply_settings_form(&mut settings, &form) {
+        return render_settings(&state, &settings, &errors, false);
+    }
+    if let Err(e) = settings.save(&path) {
+        error!("Failed to save settings: {:#}", e);
+        return (StatusCode::INTERNAL_SERVER_ERROR, format!("{e:#}")).into_response();
+    }
+
+    info!("Settings saved to {}, reloading bridge", path.display());
+    state
+        .templates
+        .write()
+        .add_global("lang", settings.language.code());
+    state.bridge_state.request_reload();
+    Redirect::to(&format!("{}/settings?saved", state.base_path)).into_response()
+}
+
 /// Charts page handler - shows Prometheus metric charts.
 ///
 /// Only available when a Prometheus URL is configured.
 async fn charts_handler(State(state): State<AppState>) -> Response {
-    let Some(ref prometheus_url) = state.prometheus_url else {
+    let Some((data_path, data_source)) = charts_source(&state) else {
         return (
             StatusCode::NOT_FOUND,
-            "Charts unavailable: no Prometheus URL configured in settings",
+            "Charts unavailable: no Prometheus URL or history database configured in settings",
         )
             .into_response();
     };
@@ -337,8 +1277,8 @@ async fn charts_handler(State(state): State<AppState>) -> Response {
     let html = match template.render(context! {
         title => "Charts - Comelit HUB Bridge",
         active_page => "charts",
-        prometheus_url => prometheus_url,
-        prometheus_token => state.prometheus_token,
+        data_url => format!("{}{}", state.base_path, data_path),
+        data_source => data_source,
     }) {
         Ok(html) => html,
         Err(e) => {
@@ -350,6 +1290,86 @@ async fn charts_handler(State(state): State<AppState>) -> Response {
     Html(html).into_response()
 }
 
+/// Endpoint the charts page reads `query_range` data from, and its description.
+///
+/// Prometheus is preferred; the local history database is used when no Prometheus
+/// server is configured.
+fn charts_source(state: &AppState) -> Option<(&'static str, &'static str)> {
+    if state.prometheus_url.is_some() {
+        return Some(("/api/prom/query_range", "Prometheus"));
+    }
+    #[cfg(feature = "history")]
+    if state.bridge_state.history_store().is_some() {
+        return Some(("/api/history/query_range", "the local history database"));
+    }
+    None
+}
+
+/// History endpoint - answers Prometheus-style `query_range` requests from the
+/// local history database, so the charts page can use it as a drop-in source.
+#[cfg(feature = "history")]
+async fn history_query_handler(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    let prom_error = |status: StatusCode, error: String| {
+        (
+            status,
+            [("content-type", "application/json")],
+            serde_json::json!({ "status": "error", "error": error }).to_string(),
+        )
+            .into_response()
+    };
+    let Some(store) = state.bridge_state.history_store() else {
+        return prom_error(StatusCode::NOT_FOUND, "History is not enabled".to_string());
+    };
+    let param = |name: &str| {
+        params
+            .get(name)
+            .and_then(|v| v.parse::<f64>().ok())
+            .map(|v| v as i64)
+    };
+    let (Some(query), Some(start), Some(end)) = (params.get("query"), param("start"), param("end"))
+    else {
+        return prom_error(
+            StatusCode::BAD_REQUEST,
+            "query, start and end are required".to_string(),
+        );
+    };
+
+    match store.query(query, start, end, param("step").unwrap_or(60)) {
+        Ok(series) => {
+            let result: Vec<_> = series
+                .iter()
+                .map(|s| {
+                    serde_json::json!({
+                        "metric": { "__name__": query, "name": s.name },
+                        "values": s
+                            .values
+                            .iter()
+                            .map(|(t, v)| serde_json::json!([t, v.to_string()]))
+                            .collect::<Vec<_>>(),
+                    })
+                })
+                .collect();
+            (
+                StatusCode::OK,
+                [("content-type", "application/json")],
+                serde_json::json!({
+                    "status": "success",
+                    "data": { "resultType": "matrix", "result": result },
+                })
+                .to_string(),
+            )
+                .into_response()
+        }
+        Err(e) => {
+            error!("History query failed: {:#}", e);
+            prom_error(StatusCode::INTERNAL_SERVER_ERROR, format!("{e:#}"))
+        }
+    }
+}
+
 /// Prometheus proxy endpoint - forwards query_range requests to the configured Prometheus server.
 ///
 /// Avoids CORS issues by making the request server-side.
@@ -402,6 +1422,7 @@ async fn api_status_handler(State(state): State<AppState>) -> Response {
             "window_coverings": summary.device_counts.get(&DeviceType::WindowCovering).unwrap_or(&0),
             "doors": summary.device_counts.get(&DeviceType::Door).unwrap_or(&0),
             "doorbells": summary.device_counts.get(&DeviceType::Doorbell).unwrap_or(&0),
+            "switches": summary.device_counts.get(&DeviceType::Switch).unwrap_or(&0),
         },
         "ping": {
             "last_seconds_ago": summary.last_ping_seconds_ago,
@@ -421,3 +1442,110 @@ async fn api_status_handler(State(state): State<AppState>) -> Response {
     )
         .into_response()
 }
+
+/// API version endpoint - returns the version the bridge was built from.
+async fn api_version_handler() -> Response {
+    (
+        StatusCode::OK,
+        [("content-type", "application/json")],
+        serde_json::json!({
+            "version": build_info::VERSION,
+            "git_sha": build_info::GIT_SHA,
+            "rustc": build_info::RUSTC_VERSION,
+        })
+        .to_string(),
+    )
+        .into_response()
+}
+
+/// OpenAPI endpoint - returns the description of the JSON API.
+async fn api_openapi_handler(State(state): State<AppState>) -> Response {
+    (
+        StatusCode::OK,
+        [("content-type", "application/json")],
+        openapi::document(&state.base_path).to_string(),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_base_path() {
+        assert_eq!(normalize_base_path(""), "");
+        assert_eq!(normalize_base_path("/"), "");
+        assert_eq!(normalize_base_path("comelit"), "/comelit");
+        assert_eq!(normalize_base_path("/comelit/"), "/comelit");
+    }
+
+    #[test]
+    fn test_index_csv() {
+        let index = vec![IndexEntry {
+            id: "DOM#LT#1".to_string(),
+            object_type: "Light".to_string(),
+            subtype: "DimmerLight".to_string(),
+            room: Some("Living, dining".to_string()),
+            name: "Lamp \"big\"".to_string(),
+            status: "1".to_string(),
+        }];
+        assert_eq!(
+            index_csv(&index),
+            "id,type,subtype,room,name,status\r\n\
+             DOM#LT#1,Light,DimmerLight,\"Living, dining\",\"Lamp \"\"big\"\"\",1\r\n"
+        );
+    }
+
+    #[test]
+    fn test_sparkline_points() {
+        let sample = |value| DeviceSample {
+            time: SystemTime::now(),
+            value,
+        };
+        assert_eq!(sparkline_points(&[], 100.0, 10.0), "");
+        assert_eq!(sparkline_points(&[sample(20.0)], 100.0, 10.0), "0.0,5.0");
+        assert_eq!(
+            sparkline_points(&[sample(20.0), sample(22.0), sample(21.0)], 100.0, 10.0),
+            "0.0,10.0 50.0,0.0 100.0,5.0"
+        );
+    }
+
+    #[test]
+    fn test_names_round_trip() {
+        let names = parse_names("DOM#LT#1.1 = Luce cucina\n\n DOM#BL#2.1=Tapparella ").unwrap();
+        assert_eq!(names.len(), 2);
+        assert_eq!(names["DOM#BL#2.1"], "Tapparella");
+        assert_eq!(
+            format_names(&names),
+            "DOM#BL#2.1 = Tapparella\nDOM#LT#1.1 = Luce cucina"
+        );
+        assert!(parse_names("DOM#LT#1.1\n = Nome").is_err());
+    }
+
+    #[test]
+    fn test_apply_settings_form() {
+        let mut settings = Settings::default();
+        let form = SettingsForm {
+            mount_lights: Some("on".to_string()),
+            opening_time: "20".to_string(),
+            closing_time: "25".to_string(),
+            ..Default::default()
+        };
+        apply_settings_form(&mut settings, &form).unwrap();
+        assert_eq!(settings.mount_lights, Some(true));
+        assert_eq!(settings.mount_thermo, Some(false));
+        assert_eq!(settings.window_covering.opening_time, 20);
+        assert_eq!(settings.window_covering.closing_time, 25);
+
+        let invalid = SettingsForm {
+            opening_time: "0".to_string(),
+            closing_time: "abc".to_string(),
+            ..Default::default()
+        };
+        let errors = apply_settings_form(&mut settings, &invalid).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        // Invalid forms leave the settings untouched
+        assert_eq!(settings.mount_lights, Some(true));
+    }
+}
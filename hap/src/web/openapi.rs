@@ -0,0 +1,359 @@
+//! OpenAPI description of the JSON API, served at `/api/openapi.json`.
+//!
+//! The document is written by hand next to the handlers it describes: keep it
+//! in sync when adding or changing an `/api` endpoint.
+
+use serde_json::{Value, json};
+
+use crate::web::build_info;
+
+/// Build the OpenAPI document, with the server URL set to the web UI prefix.
+pub fn document(base_path: &str) -> Value {
+    let server = if base_path.is_empty() { "/" } else { base_path };
+    let device_id = json!({
+        "name": "id",
+        "in": "path",
+        "required": true,
+        "description": "Comelit device ID, URL-encoded (e.g. `DOM%23LT%231.1`)",
+        "schema": { "type": "string" },
+    });
+    let error = |description: &str| {
+        json!({
+            "description": description,
+            "content": {
+                "application/json": { "schema": { "$ref": "#/components/schemas/Error" } }
+            },
+        })
+    };
+
+    #[allow(unused_mut)]
+    let mut paths = json!({
+        "/livez": {
+            "get": {
+                "summary": "Liveness probe",
+                "operationId": "livez",
+                "responses": {
+                    "200": { "description": "The bridge process is running" },
+                },
+            }
+        },
+        "/readyz": {
+            "get": {
+                "summary": "Readiness probe",
+                "operationId": "readyz",
+                "responses": {
+                    "200": { "description": "Connected to the hub and pinged in the last 2 minutes" },
+                    "503": { "description": "Not connected or the hub stopped answering pings" },
+                },
+            }
+        },
+        "/health": {
+            "get": {
+                "summary": "Health check, alias of `/readyz`",
+                "operationId": "health",
+                "responses": {
+                    "200": { "description": "Connected to the hub and pinged in the last 2 minutes" },
+                    "503": { "description": "Not connected or the hub stopped answering pings" },
+                },
+            }
+        },
+        "/api/status": {
+            "get": {
+                "summary": "Bridge status",
+                "operationId": "getStatus",
+                "responses": {
+                    "200": {
+                        "description": "Connection, pairing, device counts and ping statistics",
+                        "content": {
+                            "application/json": { "schema": { "$ref": "#/components/schemas/Status" } }
+                        },
+                    }
+                },
+            }
+        },
+        "/api/version": {
+            "get": {
+                "summary": "Bridge version",
+                "operationId": "getVersion",
+                "responses": {
+                    "200": {
+                        "description": "Version, git commit and compiler the bridge was built with",
+                        "content": {
+                            "application/json": { "schema": { "$ref": "#/components/schemas/Version" } }
+                        },
+                    }
+                },
+            }
+        },
+        "/api/devices/export": {
+            "get": {
+                "summary": "Export every device of the hub index",
+                "operationId": "exportDevices",
+                "parameters": [
+                    { "name": "format", "in": "query", "description": "Download format (default `json`)", "schema": { "type": "string", "enum": ["json", "csv"] } },
+                ],
+                "responses": {
+                    "200": {
+                        "description": "Devices of the hub index, including the ones not exposed to HomeKit",
+                        "content": {
+                            "application/json": {
+                                "schema": { "type": "array", "items": { "$ref": "#/components/schemas/IndexEntry" } }
+                            },
+                            "text/csv": { "schema": { "type": "string" } },
+                        },
+                    },
+                    "400": { "description": "Unknown format" },
+                },
+            }
+        },
+        "/api/devices/{id}": {
+            "get": {
+                "summary": "Get a device",
+                "operationId": "getDevice",
+                "parameters": [device_id],
+                "responses": {
+                    "200": {
+                        "description": "The device",
+                        "content": {
+                            "application/json": { "schema": { "$ref": "#/components/schemas/Device" } }
+                        },
+                    },
+                    "404": error("Device not found"),
+                },
+            }
+        },
+        "/api/devices/{id}/action": {
+            "post": {
+                "summary": "Send a command to a device",
+                "operationId": "sendDeviceAction",
+                "parameters": [device_id],
+                "requestBody": {
+                    "required": true,
+                    "content": {
+                        "application/json": { "schema": { "$ref": "#/components/schemas/DeviceCommand" } }
+                    },
+                },
+                "responses": {
+                    "200": {
+                        "description": "Command sent to the hub",
+                        "content": {
+                            "application/json": {
+                                "schema": {
+                                    "type": "object",
+                                    "properties": { "status": { "type": "string", "enum": ["ok"] } },
+                                }
+                            }
+                        },
+                    },
+                    "400": error("The device does not support this command"),
+                    "404": error("Device not found"),
+                    "502": error("The hub rejected the command"),
+                    "503": error("The bridge is not connected to the hub"),
+                },
+            }
+        },
+        "/api/rings": {
+            "get": {
+                "summary": "Recent doorbell rings, newest first",
+                "operationId": "listRings",
+                "responses": {
+                    "200": {
+                        "description": "Ring history",
+                        "content": {
+                            "application/json": {
+                                "schema": { "type": "array", "items": { "$ref": "#/components/schemas/Ring" } }
+                            }
+                        },
+                    }
+                },
+            }
+        },
+        "/api/events": {
+            "get": {
+                "summary": "Event log, newest first",
+                "operationId": "listEvents",
+                "responses": {
+                    "200": {
+                        "description": "Recent device status changes, doorbell rings and errors",
+                        "content": {
+                            "application/json": {
+                                "schema": { "type": "array", "items": { "$ref": "#/components/schemas/Event" } }
+                            }
+                        },
+                    }
+                },
+            }
+        },
+    });
+    #[cfg(feature = "history")]
+    {
+        paths["/api/history/query_range"] = json!({
+            "get": {
+                "summary": "Samples from the local history database, in the Prometheus query_range format",
+                "operationId": "queryHistory",
+                "parameters": [
+                    { "name": "query", "in": "query", "required": true, "description": "Metric name, e.g. `comelit_thermostat_temperature`", "schema": { "type": "string" } },
+                    { "name": "start", "in": "query", "required": true, "description": "Unix timestamp", "schema": { "type": "number" } },
+                    { "name": "end", "in": "query", "required": true, "description": "Unix timestamp", "schema": { "type": "number" } },
+                    { "name": "step", "in": "query", "description": "Bucket size in seconds (default 60)", "schema": { "type": "number" } },
+                ],
+                "responses": {
+                    "200": { "description": "Prometheus matrix response" },
+                    "400": { "description": "Missing parameters" },
+                },
+            }
+        });
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Comelit HUB Bridge API",
+            "description": "Status and control API of the Comelit HomeKit bridge.",
+            "version": build_info::VERSION,
+        },
+        "servers": [{ "url": server }],
+        "paths": paths,
+        "components": {
+            "schemas": {
+                "Error": {
+                    "type": "object",
+                    "required": ["error"],
+                    "properties": { "error": { "type": "string" } },
+                },
+                "Version": {
+                    "type": "object",
+                    "properties": {
+                        "version": { "type": "string" },
+                        "git_sha": { "type": "string" },
+                        "rustc": { "type": "string" },
+                    },
+                },
+                "Device": {
+                    "type": "object",
+                    "required": ["id", "name", "type", "status"],
+                    "properties": {
+                        "id": { "type": "string" },
+                        "name": { "type": "string" },
+                        "type": {
+                            "type": "string",
+                            "enum": ["light", "thermostat", "window_covering", "door", "doorbell", "switch", "meter"],
+                        },
+                        "status": { "type": "string" },
+                        "last_update_seconds_ago": { "type": "integer", "nullable": true },
+                    },
+                },
+                "IndexEntry": {
+                    "type": "object",
+                    "properties": {
+                        "id": { "type": "string" },
+                        "type": { "type": "string", "description": "Comelit object type, e.g. `Light`" },
+                        "subtype": { "type": "string", "description": "Comelit object subtype, e.g. `DimmerLight`" },
+                        "room": { "type": "string", "nullable": true },
+                        "name": { "type": "string" },
+                        "status": { "type": "string", "description": "Raw status field of the hub, e.g. `0` or `1`" },
+                    },
+                },
+                "DeviceCommand": {
+                    "type": "object",
+                    "required": ["action"],
+                    "properties": {
+                        "action": {
+                            "type": "string",
+                            "enum": ["toggle", "on", "off", "open", "close", "set_target"],
+                        },
+                        "value": {
+                            "type": "number",
+                            "description": "Blind position (0-100) or thermostat temperature in °C, for `set_target`",
+                        },
+                    },
+                },
+                "Ring": {
+                    "type": "object",
+                    "properties": {
+                        "device_id": { "type": "string" },
+                        "name": { "type": "string" },
+                        "timestamp": { "type": "integer", "description": "Unix timestamp" },
+                        "seconds_ago": { "type": "integer" },
+                    },
+                },
+                "Event": {
+                    "type": "object",
+                    "properties": {
+                        "kind": { "type": "string", "enum": ["update", "door_open", "ring", "error"] },
+                        "device_id": { "type": "string", "nullable": true },
+                        "message": { "type": "string" },
+                        "timestamp": { "type": "integer", "description": "Unix timestamp" },
+                        "seconds_ago": { "type": "integer" },
+                    },
+                },
+                "Status": {
+                    "type": "object",
+                    "properties": {
+                        "status": { "type": "string" },
+                        "uptime_seconds": { "type": "integer" },
+                        "connection_status": {
+                            "type": "string",
+                            "enum": ["disconnected", "connecting", "connected", "error"],
+                        },
+                        "is_paired": { "type": "boolean" },
+                        "device_count": { "type": "integer" },
+                        "devices": {
+                            "type": "object",
+                            "additionalProperties": { "type": "integer" },
+                            "description": "Number of devices by type",
+                        },
+                        "ping": {
+                            "type": "object",
+                            "properties": {
+                                "last_seconds_ago": { "type": "integer", "nullable": true },
+                                "total": { "type": "integer" },
+                                "failures": { "type": "integer" },
+                                "success_rate": { "type": "number" },
+                            },
+                        },
+                        "updates_received": { "type": "integer" },
+                        "hub_host": { "type": "string", "nullable": true },
+                        "last_error": { "type": "string", "nullable": true },
+                    },
+                },
+            }
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect_refs<'a>(value: &'a Value, refs: &mut Vec<&'a str>) {
+        match value {
+            Value::Object(map) => {
+                for (key, v) in map {
+                    match (key.as_str(), v) {
+                        ("$ref", Value::String(r)) => refs.push(r),
+                        _ => collect_refs(v, refs),
+                    }
+                }
+            }
+            Value::Array(items) => items.iter().for_each(|v| collect_refs(v, refs)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn test_document() {
+        let doc = document("/comelit");
+        assert_eq!(doc["servers"][0]["url"], "/comelit");
+        assert!(doc["paths"]["/api/devices/{id}/action"]["post"].is_object());
+
+        // Every reference points to a defined schema
+        let mut refs = vec![];
+        collect_refs(&doc, &mut refs);
+        assert!(!refs.is_empty());
+        for r in refs {
+            let name = r.strip_prefix("#/components/schemas/").unwrap();
+            assert!(doc["components"]["schemas"][name].is_object(), "{r}");
+        }
+    }
+}
@@ -3,10 +3,32 @@
 //! This module defines the shared state that is accessible from both
 //! the bridge runtime and the web server.
 
+use comelit_client_rs::ComelitClient;
 use parking_lot::RwLock;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Instant, SystemTime};
+use tokio::sync::{Notify, broadcast};
+
+pub use crate::model::EventKind;
+
+#[cfg(feature = "history")]
+use crate::history::HistoryStore;
+#[cfg(feature = "influxdb")]
+use crate::influxdb::InfluxExporter;
+
+/// Maximum number of doorbell rings kept in the ring history.
+pub const MAX_RING_EVENTS: usize = 20;
+
+/// Maximum number of entries kept in the event log.
+pub const MAX_LOG_EVENTS: usize = 200;
+
+/// Maximum number of samples kept in the history of each device.
+pub const MAX_HISTORY_SAMPLES: usize = 60;
+
+/// Number of events buffered for each subscriber before it starts missing them.
+const EVENT_CHANNEL_CAPACITY: usize = 64;
 
 /// Information about a device.
 #[derive(Debug, Clone)]
@@ -15,7 +37,7 @@ pub struct DeviceInfo {
     pub id: String,
     /// Human-readable device name.
     pub name: String,
-    /// Device type (light, thermostat, window_covering, door, doorbell).
+    /// Device type (light, thermostat, window_covering, door, doorbell, switch).
     pub device_type: DeviceType,
     /// Current status (device-specific).
     pub status: String,
@@ -23,6 +45,56 @@ pub struct DeviceInfo {
     pub last_update: Option<Instant>,
 }
 
+/// A doorbell ring.
+#[derive(Debug, Clone)]
+pub struct RingEvent {
+    /// ID of the doorbell device that rang.
+    pub device_id: String,
+    /// Human-readable doorbell name.
+    pub name: String,
+    /// Wall-clock time of the ring.
+    pub time: SystemTime,
+}
+
+/// Numeric value reported by a device (e.g. a temperature) at a given time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeviceSample {
+    /// Wall-clock time of the sample.
+    pub time: SystemTime,
+    /// Sampled value.
+    pub value: f64,
+}
+
+/// A device of the hub index, whether or not it is exposed to HomeKit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexEntry {
+    /// Comelit device ID.
+    pub id: String,
+    /// Comelit object type (e.g. `Light`, `Thermostat`).
+    pub object_type: String,
+    /// Comelit object subtype (e.g. `DimmerLight`).
+    pub subtype: String,
+    /// Room the device is in, if known.
+    pub room: Option<String>,
+    /// Name configured on the hub.
+    pub name: String,
+    /// Raw `status` field reported by the hub (e.g. `0` or `1`).
+    pub status: String,
+}
+
+/// Entry of the event log.
+#[derive(Debug, Clone)]
+pub struct LogEvent {
+    /// Wall-clock time of the event.
+    pub time: SystemTime,
+    /// Kind of event.
+    pub kind: EventKind,
+    /// ID of the device the event refers to, if any.
+    pub device_id: Option<String>,
+    /// Human-readable description.
+    pub message: String,
+}
+
 /// Type of device.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum DeviceType {
@@ -31,6 +103,8 @@ pub enum DeviceType {
     WindowCovering,
     Door,
     Doorbell,
+    Switch,
+    Meter,
 }
 
 impl DeviceType {
@@ -42,6 +116,8 @@ impl DeviceType {
             DeviceType::WindowCovering => "window_covering",
             DeviceType::Door => "door",
             DeviceType::Doorbell => "doorbell",
+            DeviceType::Switch => "switch",
+            DeviceType::Meter => "meter",
         }
     }
 
@@ -53,6 +129,8 @@ impl DeviceType {
             DeviceType::WindowCovering => "Window Covering",
             DeviceType::Door => "Door",
             DeviceType::Doorbell => "Doorbell",
+            DeviceType::Switch => "Switch",
+            DeviceType::Meter => "Energy Meter",
         }
     }
 }
@@ -82,6 +160,16 @@ impl ConnectionStatus {
     }
 }
 
+/// Client of the running bridge, shared with the web UI to send commands to the hub.
+#[derive(Clone)]
+struct SharedClient(ComelitClient);
+
+impl fmt::Debug for SharedClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ComelitClient")
+    }
+}
+
 /// Internal mutable state.
 #[derive(Debug)]
 struct BridgeStateInner {
@@ -97,6 +185,12 @@ struct BridgeStateInner {
     pairing_url: String,
     /// Registered devices.
     devices: HashMap<String, DeviceInfo>,
+    /// Fields of the last update received for each device.
+    device_fields: HashMap<String, BTreeMap<String, String>>,
+    /// Recent samples of each device, oldest first.
+    history: HashMap<String, VecDeque<DeviceSample>>,
+    /// Devices of the hub index, sorted by ID.
+    index: Vec<IndexEntry>,
     /// Last successful ping time.
     last_ping: Option<Instant>,
     /// Total ping count.
@@ -109,6 +203,64 @@ struct BridgeStateInner {
     hub_host: Option<String>,
     /// Error message if any.
     last_error: Option<String>,
+    /// Most recent doorbell rings, newest first.
+    rings: VecDeque<RingEvent>,
+    /// Most recent device updates, rings and errors, newest first.
+    events: VecDeque<LogEvent>,
+    /// Publishes every new event log entry to the subscribers.
+    events_tx: broadcast::Sender<LogEvent>,
+    /// Client connected to the hub, if any.
+    client: Option<SharedClient>,
+    /// Database the samples are persisted to, if enabled.
+    #[cfg(feature = "history")]
+    history_store: Option<Arc<HistoryStore>>,
+    /// Exporter the samples are written to, if enabled.
+    #[cfg(feature = "influxdb")]
+    influx_exporter: Option<Arc<InfluxExporter>>,
+}
+
+/// Flatten a JSON value into `key = value` strings, using dotted keys for nested objects.
+fn flatten_fields(
+    prefix: Option<&str>,
+    value: &serde_json::Value,
+    out: &mut BTreeMap<String, String>,
+) {
+    let key = |k: &str| match prefix {
+        Some(prefix) => format!("{prefix}.{k}"),
+        None => k.to_string(),
+    };
+    match value {
+        serde_json::Value::Null => {}
+        serde_json::Value::Object(map) => {
+            for (k, v) in map {
+                flatten_fields(Some(&key(k)), v, out);
+            }
+        }
+        serde_json::Value::String(s) => {
+            out.insert(prefix.unwrap_or_default().to_string(), s.clone());
+        }
+        other => {
+            out.insert(prefix.unwrap_or_default().to_string(), other.to_string());
+        }
+    }
+}
+
+impl BridgeStateInner {
+    /// Add an entry to the event log, dropping the oldest one when the log is full.
+    fn push_event(&mut self, kind: EventKind, device_id: Option<String>, message: String) {
+        if self.events.len() == MAX_LOG_EVENTS {
+            self.events.pop_back();
+        }
+        let event = LogEvent {
+            time: SystemTime::now(),
+            kind,
+            device_id,
+            message,
+        };
+        // Sending only fails when nobody is subscribed
+        let _ = self.events_tx.send(event.clone());
+        self.events.push_front(event);
+    }
 }
 
 /// Shared bridge state.
@@ -117,6 +269,8 @@ struct BridgeStateInner {
 #[derive(Debug, Clone)]
 pub struct BridgeState {
     inner: Arc<RwLock<BridgeStateInner>>,
+    /// Signals the running bridge that the settings changed.
+    reload: Arc<Notify>,
 }
 
 impl Default for BridgeState {
@@ -136,13 +290,25 @@ impl BridgeState {
                 pairing_pin: String::new(),
                 pairing_url: String::new(),
                 devices: HashMap::new(),
+                device_fields: HashMap::new(),
+                history: HashMap::new(),
+                index: Vec::new(),
                 last_ping: None,
                 ping_count: 0,
                 ping_failures: 0,
                 update_count: 0,
                 hub_host: None,
                 last_error: None,
+                rings: VecDeque::with_capacity(MAX_RING_EVENTS),
+                events: VecDeque::with_capacity(MAX_LOG_EVENTS),
+                events_tx: broadcast::channel(EVENT_CHANNEL_CAPACITY).0,
+                client: None,
+                #[cfg(feature = "history")]
+                history_store: None,
+                #[cfg(feature = "influxdb")]
+                influx_exporter: None,
             })),
+            reload: Arc::new(Notify::new()),
         }
     }
 
@@ -206,19 +372,201 @@ impl BridgeState {
         self.inner.write().hub_host = Some(host);
     }
 
+    /// Get the client connected to the hub, if the bridge is running.
+    pub fn client(&self) -> Option<ComelitClient> {
+        self.inner.read().client.as_ref().map(|c| c.0.clone())
+    }
+
+    /// Set (or clear, on disconnection) the client connected to the hub.
+    pub fn set_client(&self, client: Option<ComelitClient>) {
+        self.inner.write().client = client.map(SharedClient);
+    }
+
+    /// Ask the running bridge to restart and apply the new settings.
+    ///
+    /// The request is kept until the bridge waits for it, so it is not lost while
+    /// the bridge is starting.
+    pub fn request_reload(&self) {
+        self.reload.notify_one();
+    }
+
+    /// Wait until a settings reload is requested.
+    pub async fn reload_requested(&self) {
+        self.reload.notified().await;
+    }
+
+    /// Get a device by ID.
+    pub fn device(&self, id: &str) -> Option<DeviceInfo> {
+        self.inner.read().devices.get(id).cloned()
+    }
+
     /// Register a device.
     pub fn register_device(&self, device: DeviceInfo) {
         self.inner.write().devices.insert(device.id.clone(), device);
     }
 
-    /// Update a device's status.
+    /// Update a device's status, logging an event when it changes.
+    ///
+    /// A door changing to `open` is logged as [`EventKind::DoorOpen`]. On/off and
+    /// open/closed transitions are exported to InfluxDB, if enabled.
     pub fn update_device_status(&self, id: &str, status: String) {
         let mut inner = self.inner.write();
+        #[cfg(feature = "influxdb")]
+        let exporter = inner.influx_exporter.clone();
+        let mut change = None;
         if let Some(device) = inner.devices.get_mut(id) {
+            if device.status != status {
+                #[cfg(feature = "influxdb")]
+                if let Some(exporter) = exporter {
+                    let value = match status.as_str() {
+                        "on" | "open" => Some(1.0),
+                        "off" | "closed" => Some(0.0),
+                        _ => None,
+                    };
+                    if let Some(value) = value {
+                        exporter.write(
+                            "comelit_device_state",
+                            &[
+                                ("device", &device.name),
+                                ("type", device.device_type.as_str()),
+                            ],
+                            value,
+                        );
+                    }
+                }
+                change = Some(
+                    if device.device_type == DeviceType::Door && status == "open" {
+                        (EventKind::DoorOpen, format!("{} opened", device.name))
+                    } else {
+                        (
+                            EventKind::Update,
+                            format!("{}: {} → {}", device.name, device.status, status),
+                        )
+                    },
+                );
+            }
             device.status = status;
             device.last_update = Some(Instant::now());
         }
         inner.update_count += 1;
+        if let Some((kind, message)) = change {
+            inner.push_event(kind, Some(id.to_string()), message);
+        }
+    }
+
+    /// Store the fields of the last update received for a registered device.
+    ///
+    /// Nested objects are flattened into dotted keys and `null` values are skipped.
+    pub fn set_device_fields(&self, id: &str, data: &serde_json::Value) {
+        let mut fields = BTreeMap::new();
+        flatten_fields(None, data, &mut fields);
+        let mut inner = self.inner.write();
+        if inner.devices.contains_key(id) {
+            inner.device_fields.insert(id.to_string(), fields);
+        }
+    }
+
+    /// Get the fields of the last update received for a device.
+    pub fn device_fields(&self, id: &str) -> BTreeMap<String, String> {
+        self.inner
+            .read()
+            .device_fields
+            .get(id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Record a sample in the history of a device, dropping the oldest one when it is full.
+    pub fn record_sample(&self, id: &str, value: f64) {
+        let mut inner = self.inner.write();
+        let history = inner
+            .history
+            .entry(id.to_string())
+            .or_insert_with(|| VecDeque::with_capacity(MAX_HISTORY_SAMPLES));
+        if history.len() == MAX_HISTORY_SAMPLES {
+            history.pop_front();
+        }
+        history.push_back(DeviceSample {
+            time: SystemTime::now(),
+            value,
+        });
+    }
+
+    /// Get the recent samples of a device, oldest first.
+    pub fn history(&self, id: &str) -> Vec<DeviceSample> {
+        self.inner
+            .read()
+            .history
+            .get(id)
+            .map(|h| h.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Set the devices of the hub index.
+    pub fn set_index(&self, mut index: Vec<IndexEntry>) {
+        index.sort_by(|a, b| a.id.cmp(&b.id));
+        self.inner.write().index = index;
+    }
+
+    /// Get the devices of the hub index, with the status of registered devices kept up to date.
+    ///
+    /// The status is the raw field of the last hub update, not the status shown by the
+    /// bridge, so that all the entries use the same representation.
+    pub fn index(&self) -> Vec<IndexEntry> {
+        let inner = self.inner.read();
+        inner
+            .index
+            .iter()
+            .cloned()
+            .map(|mut entry| {
+                if let Some(status) = inner
+                    .device_fields
+                    .get(&entry.id)
+                    .and_then(|fields| fields.get("status"))
+                {
+                    entry.status = status.clone();
+                }
+                entry
+            })
+            .collect()
+    }
+
+    /// Set the database the samples are persisted to.
+    #[cfg(feature = "history")]
+    pub fn set_history_store(&self, store: Option<Arc<HistoryStore>>) {
+        self.inner.write().history_store = store;
+    }
+
+    /// Get the database the samples are persisted to, if enabled.
+    #[cfg(feature = "history")]
+    pub fn history_store(&self) -> Option<Arc<HistoryStore>> {
+        self.inner.read().history_store.clone()
+    }
+
+    /// Set the exporter the samples are written to.
+    #[cfg(feature = "influxdb")]
+    pub fn set_influx_exporter(&self, exporter: Option<Arc<InfluxExporter>>) {
+        self.inner.write().influx_exporter = exporter;
+    }
+
+    /// Persist a sample of a metric in the history database and export it to
+    /// InfluxDB, if enabled.
+    ///
+    /// `metric` is the name of the matching Prometheus gauge, so that the charts
+    /// can be drawn from either source.
+    pub fn persist_sample(&self, metric: &str, name: &str, value: f64) {
+        #[cfg(feature = "history")]
+        if let Some(store) = self.history_store()
+            && let Err(e) = store.record(metric, name, value)
+        {
+            tracing::warn!("Failed to persist {metric} sample for {name}: {e:#}");
+        }
+        #[cfg(feature = "influxdb")]
+        if let Some(exporter) = self.inner.read().influx_exporter.as_ref() {
+            exporter.write(metric, &[("device", name)], value);
+        }
+        #[cfg(not(any(feature = "history", feature = "influxdb")))]
+        let _ = (metric, name, value);
     }
 
     /// Get all devices.
@@ -288,9 +636,13 @@ impl BridgeState {
         self.inner.read().update_count
     }
 
-    /// Set an error message.
+    /// Set an error message, logging it in the event log.
     pub fn set_error(&self, error: Option<String>) {
-        self.inner.write().last_error = error;
+        let mut inner = self.inner.write();
+        if let Some(message) = &error {
+            inner.push_event(EventKind::Error, None, message.clone());
+        }
+        inner.last_error = error;
     }
 
     /// Get the last error message.
@@ -298,6 +650,32 @@ impl BridgeState {
         self.inner.read().last_error.clone()
     }
 
+    /// Record a doorbell ring, dropping the oldest one when the history is full.
+    pub fn record_ring(&self, event: RingEvent) {
+        let mut inner = self.inner.write();
+        if inner.rings.len() == MAX_RING_EVENTS {
+            inner.rings.pop_back();
+        }
+        let message = format!("{} rang", event.name);
+        inner.push_event(EventKind::Ring, Some(event.device_id.clone()), message);
+        inner.rings.push_front(event);
+    }
+
+    /// Get the ring history, newest first.
+    pub fn rings(&self) -> Vec<RingEvent> {
+        self.inner.read().rings.iter().cloned().collect()
+    }
+
+    /// Get the event log, newest first.
+    pub fn events(&self) -> Vec<LogEvent> {
+        self.inner.read().events.iter().cloned().collect()
+    }
+
+    /// Receive the entries added to the event log from now on.
+    pub fn subscribe_events(&self) -> broadcast::Receiver<LogEvent> {
+        self.inner.read().events_tx.subscribe()
+    }
+
     /// Get a summary of the bridge state for the web UI.
     pub fn summary(&self) -> BridgeStateSummary {
         let inner = self.inner.read();
@@ -440,6 +818,150 @@ mod tests {
         assert!(state.last_ping().is_some());
     }
 
+    #[test]
+    fn test_ring_history() {
+        let state = BridgeState::new();
+        for i in 0..MAX_RING_EVENTS + 5 {
+            state.record_ring(RingEvent {
+                device_id: format!("bell{}", i % 2),
+                name: "Front door".to_string(),
+                time: SystemTime::now(),
+            });
+        }
+        let rings = state.rings();
+        assert_eq!(rings.len(), MAX_RING_EVENTS);
+        assert_eq!(rings[0].device_id, "bell0");
+        assert_eq!(rings[1].device_id, "bell1");
+    }
+
+    #[test]
+    fn test_event_log() {
+        let state = BridgeState::new();
+        state.register_device(DeviceInfo {
+            id: "light1".to_string(),
+            name: "Living Room Light".to_string(),
+            device_type: DeviceType::Light,
+            status: "off".to_string(),
+            last_update: None,
+        });
+        state.update_device_status("light1", "on".to_string());
+        // Unchanged status is not logged
+        state.update_device_status("light1", "on".to_string());
+        state.set_error(Some("Login failed".to_string()));
+        state.set_error(None);
+
+        let events = state.events();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].kind, EventKind::Error);
+        assert_eq!(events[0].message, "Login failed");
+        assert_eq!(events[1].kind, EventKind::Update);
+        assert_eq!(events[1].device_id.as_deref(), Some("light1"));
+        assert_eq!(events[1].message, "Living Room Light: off → on");
+
+        for i in 0..MAX_LOG_EVENTS {
+            state.update_device_status("light1", format!("{i}"));
+        }
+        let events = state.events();
+        assert_eq!(events.len(), MAX_LOG_EVENTS);
+        assert!(events.iter().all(|e| e.kind == EventKind::Update));
+    }
+
+    #[tokio::test]
+    async fn test_reload_requested_before_waiting() {
+        let state = BridgeState::new();
+        state.request_reload();
+        tokio::time::timeout(std::time::Duration::from_secs(1), state.reload_requested())
+            .await
+            .expect("reload request was lost");
+    }
+
+    #[test]
+    fn test_subscribe_events() {
+        let state = BridgeState::new();
+        state.register_device(DeviceInfo {
+            id: "door1".to_string(),
+            name: "Gate".to_string(),
+            device_type: DeviceType::Door,
+            status: "closed".to_string(),
+            last_update: None,
+        });
+        let mut events = state.subscribe_events();
+        state.update_device_status("door1", "open".to_string());
+        state.update_device_status("door1", "closed".to_string());
+
+        let event = events.try_recv().unwrap();
+        assert_eq!(event.kind, EventKind::DoorOpen);
+        assert_eq!(event.message, "Gate opened");
+        assert_eq!(events.try_recv().unwrap().kind, EventKind::Update);
+        assert!(events.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_device_details() {
+        let state = BridgeState::new();
+        state.register_device(DeviceInfo {
+            id: "clima1".to_string(),
+            name: "Living Room".to_string(),
+            device_type: DeviceType::Thermostat,
+            status: "21.5°C".to_string(),
+            last_update: None,
+        });
+        state.set_device_fields(
+            "clima1",
+            &serde_json::json!({
+                "id": "clima1",
+                "temperatura": "215",
+                "status": null,
+                "auto_man": {"mode": 1},
+                "values": [1, 2],
+            }),
+        );
+        let fields = state.device_fields("clima1");
+        assert_eq!(fields["temperatura"], "215");
+        assert_eq!(fields["auto_man.mode"], "1");
+        assert_eq!(fields["values"], "[1,2]");
+        assert!(!fields.contains_key("status"));
+        state.set_device_fields("unknown", &serde_json::json!({"id": "unknown"}));
+        assert!(state.device_fields("unknown").is_empty());
+
+        for i in 0..MAX_HISTORY_SAMPLES + 3 {
+            state.record_sample("clima1", i as f64);
+        }
+        let history = state.history("clima1");
+        assert_eq!(history.len(), MAX_HISTORY_SAMPLES);
+        assert_eq!(history[0].value, 3.0);
+        assert!(state.history("unknown").is_empty());
+    }
+
+    #[test]
+    fn test_index() {
+        let state = BridgeState::new();
+        let entry = |id: &str, status: &str| IndexEntry {
+            id: id.to_string(),
+            object_type: "Light".to_string(),
+            subtype: "DigitalLight".to_string(),
+            room: None,
+            name: id.to_string(),
+            status: status.to_string(),
+        };
+        state.set_index(vec![entry("light2", "0"), entry("light1", "0")]);
+        state.register_device(DeviceInfo {
+            id: "light1".to_string(),
+            name: "Kitchen".to_string(),
+            device_type: DeviceType::Light,
+            status: "off".to_string(),
+            last_update: None,
+        });
+        state.update_device_status("light1", "on".to_string());
+        state.set_device_fields(
+            "light1",
+            &serde_json::json!({ "id": "light1", "status": 1 }),
+        );
+
+        let index = state.index();
+        assert_eq!(index, vec![entry("light1", "1"), entry("light2", "0")]);
+    }
+
     #[test]
     fn test_uptime_display() {
         let summary = BridgeStateSummary {
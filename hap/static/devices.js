@@ -0,0 +1,28 @@
+(function () {
+    // Doors are opened through the JSON API: unlike a form, a JSON POST cannot be
+    // sent by another site without a CORS preflight
+    document.querySelectorAll('.door-open').forEach(function (btn) {
+        btn.addEventListener('click', function () {
+            btn.disabled = true;
+            fetch(btn.dataset.url, {
+                method: 'POST',
+                headers: { 'Content-Type': 'application/json' },
+                body: JSON.stringify({ action: 'open' }),
+            })
+                .then(function (res) {
+                    if (!res.ok) {
+                        return res.json().catch(function () {
+                            return {};
+                        }).then(function (body) {
+                            throw new Error(body.error || res.statusText);
+                        });
+                    }
+                    window.location.reload();
+                })
+                .catch(function (err) {
+                    alert(err.message);
+                    btn.disabled = false;
+                });
+        });
+    });
+})();
@@ -1,6 +1,17 @@
+mod secrets;
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
 use anyhow::Result;
+use async_trait::async_trait;
+use clap::Parser;
 use comelit_client_rs::{
-    ComelitClient, ComelitClientError, ComelitOptions, DeviceStatus, get_secrets,
+    ClimaMode, ClimaOnOff, ComelitClient, ComelitClientError, ComelitOptions, DeviceStatus,
+    HomeDeviceData, ROOT_ID, State, StatusUpdate, ThermoSeason, ThermostatDeviceData,
+    WindowCoveringDeviceData, WindowCoveringStatus, get_secrets,
 };
 use ratatui::{
     DefaultTerminal,
@@ -18,6 +29,7 @@ use ratatui::{
         StatefulWidget, Widget, Wrap,
     },
 };
+use tokio::sync::mpsc;
 
 const TODO_HEADER_STYLE: Style = Style::new().fg(SLATE.c100).bg(BLUE.c800);
 const NORMAL_ROW_BG: Color = SLATE.c950;
@@ -25,29 +37,75 @@ const ALT_ROW_BG_COLOR: Color = SLATE.c900;
 const SELECTED_STYLE: Style = Style::new().bg(SLATE.c800).add_modifier(Modifier::BOLD);
 const TEXT_FG_COLOR: Color = SLATE.c200;
 const COMPLETED_TEXT_FG_COLOR: Color = GREEN.c500;
+/// How long to wait for a key before applying the updates pushed by the hub
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Change of the thermostat setpoint for each +/- press, in tenths of degree
+const SETPOINT_STEP: i32 = 5;
+/// Frames of the spinner shown next to a moving blind, one every 100ms
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+#[derive(Parser, Debug)]
+#[command(version)]
+struct Params {
+    /// User name for the Comelit hub (falls back to COMELIT_USER_FILE, COMELIT_USER, then "admin")
+    #[clap(long)]
+    user: Option<String>,
+    /// Password for the Comelit hub (falls back to COMELIT_PASSWORD_FILE, COMELIT_PASSWORD,
+    /// the password saved with `comelit-hub-cli login --save`, then "admin")
+    #[clap(long)]
+    password: Option<String>,
+    /// Hostname or IP address of the hub (if not set, it will scan the network to find it)
+    #[clap(long, env = "COMELIT_HOST")]
+    host: Option<String>,
+    /// MQTT port of the hub (default: 1883)
+    #[clap(long, env = "COMELIT_PORT")]
+    port: Option<u16>,
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
     color_eyre::install().expect("Failed to install Color Eyre");
-    let terminal = ratatui::init();
-    let app_result = App::new(
-        "admin",
-        "admin",
-        Some("192.168.0.66".to_string()),
-        Some(1883),
+    let params = Params::parse();
+    let user = secrets::resolve(params.user, secrets::USER_VAR)?;
+    let password = match secrets::resolve(params.password, secrets::PASSWORD_VAR)? {
+        Some(password) => Some(password),
+        None => secrets::load_password(user.clone(), params.host.clone()).await,
+    };
+    // Log in before taking over the terminal, so that errors are readable
+    let app = App::new(
+        user.as_deref().unwrap_or("admin"),
+        password.as_deref().unwrap_or("admin"),
+        params.host,
+        params.port,
     )
     .await?;
 
-    app_result.run(terminal)?;
+    let terminal = ratatui::init();
+    let app_result = app.run(terminal).await;
     ratatui::restore();
-    Ok(())
+    app_result
 }
 
-#[allow(dead_code)]
 struct App {
     should_exit: bool,
     accessory_list: AccessoryList,
     client: ComelitClient,
+    /// Outcome of the last command, shown in the footer
+    message: Option<String>,
+    updates: mpsc::UnboundedReceiver<HomeDeviceData>,
+}
+
+/// Forwards the updates pushed by the hub to the app, so that the changes made
+/// from the Home app or the wall switches show up in the list
+struct UpdateForwarder {
+    tx: mpsc::UnboundedSender<HomeDeviceData>,
+}
+
+#[async_trait]
+impl StatusUpdate for UpdateForwarder {
+    async fn status_update(&self, device: &HomeDeviceData) {
+        let _ = self.tx.send(device.clone());
+    }
 }
 
 #[derive(Default)]
@@ -56,26 +114,107 @@ struct AccessoryList {
     state: ListState,
 }
 
-#[derive(Debug, Default)]
-#[allow(dead_code)]
+#[derive(Debug)]
 struct AccessoryItem {
     id: String,
     description: String,
     status: DeviceStatus,
+    device: HomeDeviceData,
+    /// When the blind started moving, while it moves
+    moving_since: Option<Instant>,
+}
+
+impl AccessoryItem {
+    /// The lights, blinds and thermostats are listed, the other devices are skipped
+    fn from_device(device: HomeDeviceData) -> Option<AccessoryItem> {
+        if !matches!(
+            device,
+            HomeDeviceData::Light(_)
+                | HomeDeviceData::Thermostat(_)
+                | HomeDeviceData::WindowCovering(_)
+        ) {
+            return None;
+        }
+        let status = status_of(&device).unwrap_or_default();
+        Some(AccessoryItem {
+            id: device.id(),
+            description: device.name(),
+            moving_since: (status == DeviceStatus::Running).then(Instant::now),
+            status,
+            device,
+        })
+    }
+
+    /// Apply an update pushed by the hub, which may not carry all the fields
+    fn update(&mut self, mut device: HomeDeviceData) {
+        if let Some(status) = status_of(&device) {
+            self.status = status;
+        }
+        match (&self.device, &mut device) {
+            (HomeDeviceData::Thermostat(old), HomeDeviceData::Thermostat(new)) => {
+                new.temperature = new.temperature.take().or(old.temperature.clone());
+                new.active_threshold = new.active_threshold.take().or(old.active_threshold.clone());
+                new.humidity = new.humidity.take().or(old.humidity.clone());
+                new.auto_man = new.auto_man.take().or(old.auto_man.clone());
+                new.season = new.season.take().or(old.season.clone());
+            }
+            (HomeDeviceData::WindowCovering(old), HomeDeviceData::WindowCovering(new)) => {
+                new.power_status = new.power_status.take().or(old.power_status.clone());
+            }
+            _ => {}
+        }
+        self.device = device;
+        self.moving_since = match self.status {
+            DeviceStatus::Running => self.moving_since.or(Some(Instant::now())),
+            _ => None,
+        };
+    }
+
+    /// Direction of the moving blind and for how long it has been moving
+    fn movement(&self) -> Option<(&'static str, Duration)> {
+        let HomeDeviceData::WindowCovering(blind) = &self.device else {
+            return None;
+        };
+        let direction = match blind.power_status {
+            Some(WindowCoveringStatus::GoingUp) => "opening",
+            Some(WindowCoveringStatus::GoingDown) => "closing",
+            _ => return None,
+        };
+        Some((direction, self.moving_since?.elapsed()))
+    }
+}
+
+/// Status of the device shown in the list, a moving blind being shown as running
+fn status_of(device: &HomeDeviceData) -> Option<DeviceStatus> {
+    match device {
+        HomeDeviceData::Light(light) => light.status.clone(),
+        HomeDeviceData::Thermostat(thermostat) => thermostat.status.clone(),
+        HomeDeviceData::WindowCovering(blind) => blind.power_status.as_ref().map(|s| match s {
+            WindowCoveringStatus::GoingUp | WindowCoveringStatus::GoingDown => {
+                DeviceStatus::Running
+            }
+            WindowCoveringStatus::Stopped => DeviceStatus::Off,
+        }),
+        _ => None,
+    }
 }
 
 impl App {
-    fn run(mut self, mut terminal: DefaultTerminal) -> Result<()> {
+    async fn run(mut self, mut terminal: DefaultTerminal) -> Result<()> {
         while !self.should_exit {
             terminal.draw(|frame| frame.render_widget(&mut self, frame.area()))?;
-            if let Event::Key(key) = event::read()? {
-                self.handle_key(key);
-            };
+            if event::poll(POLL_INTERVAL)?
+                && let Event::Key(key) = event::read()?
+            {
+                self.handle_key(key).await;
+            }
+            self.apply_updates();
         }
+        self.client.disconnect().await?;
         Ok(())
     }
 
-    fn handle_key(&mut self, key: KeyEvent) {
+    async fn handle_key(&mut self, key: KeyEvent) {
         if key.kind != KeyEventKind::Press {
             return;
         }
@@ -87,8 +226,15 @@ impl App {
             KeyCode::Char('g') | KeyCode::Home => self.select_first(),
             KeyCode::Char('G') | KeyCode::End => self.select_last(),
             KeyCode::Char('l') | KeyCode::Right | KeyCode::Enter => {
-                self.toggle_status();
+                self.toggle_status().await;
             }
+            KeyCode::Char('+') => self.change_setpoint(SETPOINT_STEP).await,
+            KeyCode::Char('-') => self.change_setpoint(-SETPOINT_STEP).await,
+            KeyCode::Char('m') => self.switch_mode().await,
+            KeyCode::Char('s') => self.switch_season().await,
+            KeyCode::Char('o') => self.move_blind(true).await,
+            KeyCode::Char('c') => self.move_blind(false).await,
+            KeyCode::Char(' ') => self.stop_blind().await,
             _ => {}
         }
     }
@@ -112,13 +258,174 @@ impl App {
         self.accessory_list.state.select_last();
     }
 
-    /// Changes the status of the selected list item
-    fn toggle_status(&mut self) {
-        if let Some(i) = self.accessory_list.state.selected() {
-            self.accessory_list.items[i].status = match self.accessory_list.items[i].status {
-                DeviceStatus::On => DeviceStatus::Off,
-                DeviceStatus::Off => DeviceStatus::On,
-                DeviceStatus::Running => DeviceStatus::Running,
+    fn selected_item(&mut self) -> Option<&mut AccessoryItem> {
+        self.accessory_list
+            .state
+            .selected()
+            .and_then(|i| self.accessory_list.items.get_mut(i))
+    }
+
+    /// The selected thermostat, with its id and name
+    fn selected_thermostat(&mut self) -> Option<(String, String, &mut ThermostatDeviceData)> {
+        let item = self.selected_item()?;
+        match &mut item.device {
+            HomeDeviceData::Thermostat(thermostat) => {
+                Some((item.id.clone(), item.description.clone(), thermostat))
+            }
+            _ => None,
+        }
+    }
+
+    /// Turns the selected light or thermostat on or off
+    async fn toggle_status(&mut self) {
+        let client = self.client.clone();
+        let Some(item) = self.selected_item() else {
+            return;
+        };
+        let on = item.status != DeviceStatus::On;
+        let result = match item.device {
+            HomeDeviceData::Light(_) => client.toggle_device_status(&item.id, on).await,
+            HomeDeviceData::Thermostat(_) => {
+                let status = if on {
+                    ClimaOnOff::OnThermo
+                } else {
+                    ClimaOnOff::OffThermo
+                };
+                client.toggle_thermostat_status(&item.id, status).await
+            }
+            _ => return,
+        };
+        self.message = Some(match result {
+            Ok(()) => {
+                item.status = if on {
+                    DeviceStatus::On
+                } else {
+                    DeviceStatus::Off
+                };
+                format!(
+                    "{} turned {}",
+                    item.description,
+                    if on { "on" } else { "off" }
+                )
+            }
+            Err(e) => format!("{}: {}", item.description, e),
+        });
+    }
+
+    /// The selected blind, with its id and name
+    fn selected_blind(&mut self) -> Option<(String, String, &mut WindowCoveringDeviceData)> {
+        let item = self.selected_item()?;
+        match &mut item.device {
+            HomeDeviceData::WindowCovering(blind) => {
+                Some((item.id.clone(), item.description.clone(), blind))
+            }
+            _ => None,
+        }
+    }
+
+    /// Opens or closes the selected blind. The hub only moves the blinds all
+    /// the way, so there is no intermediate position.
+    async fn move_blind(&mut self, open: bool) {
+        let client = self.client.clone();
+        let Some((id, name, _)) = self.selected_blind() else {
+            return;
+        };
+        let position = if open { 100 } else { 0 };
+        self.message = Some(match client.toggle_blind_position(&id, position).await {
+            Ok(()) if open => format!("{} opening", name),
+            Ok(()) => format!("{} closing", name),
+            Err(e) => format!("{}: {}", name, e),
+        });
+    }
+
+    /// Stops the selected blind, with the command of the opposite direction
+    async fn stop_blind(&mut self) {
+        let client = self.client.clone();
+        let Some((id, name, blind)) = self.selected_blind() else {
+            return;
+        };
+        let result = match blind.power_status {
+            Some(WindowCoveringStatus::GoingUp) => client.toggle_device_status(&id, false).await,
+            Some(WindowCoveringStatus::GoingDown) => client.toggle_device_status(&id, true).await,
+            _ => {
+                self.message = Some(format!("{} is not moving", name));
+                return;
+            }
+        };
+        self.message = Some(match result {
+            Ok(()) => format!("{} stopped", name),
+            Err(e) => format!("{}: {}", name, e),
+        });
+    }
+
+    /// Raises or lowers the target temperature of the selected thermostat
+    async fn change_setpoint(&mut self, delta: i32) {
+        let client = self.client.clone();
+        let Some((id, name, thermostat)) = self.selected_thermostat() else {
+            return;
+        };
+        let Some(target) = tenths(&thermostat.active_threshold) else {
+            self.message = Some(format!("{}: no target temperature", name));
+            return;
+        };
+        let target = target + delta;
+        let result = client.set_thermostat_temperature(&id, target).await;
+        if result.is_ok() {
+            thermostat.active_threshold = Some(target.to_string());
+        }
+        self.message = Some(match result {
+            Ok(()) => format!("{} target set to {}", name, celsius(Some(target))),
+            Err(e) => format!("{}: {}", name, e),
+        });
+    }
+
+    /// Switches the selected thermostat between automatic and manual mode
+    async fn switch_mode(&mut self) {
+        let client = self.client.clone();
+        let Some((id, name, thermostat)) = self.selected_thermostat() else {
+            return;
+        };
+        let mode = match thermostat.auto_man {
+            Some(ClimaMode::Auto) => ClimaMode::Manual,
+            _ => ClimaMode::Auto,
+        };
+        let result = client.set_thermostat_mode(&id, mode.clone()).await;
+        self.message = Some(match result {
+            Ok(()) => {
+                let message = format!("{} mode set to {:?}", name, mode);
+                thermostat.auto_man = Some(mode);
+                message
+            }
+            Err(e) => format!("{}: {}", name, e),
+        });
+    }
+
+    /// Switches the selected thermostat between summer and winter
+    async fn switch_season(&mut self) {
+        let client = self.client.clone();
+        let Some((id, name, thermostat)) = self.selected_thermostat() else {
+            return;
+        };
+        let season = match thermostat.season {
+            Some(ThermoSeason::Summer) => ThermoSeason::Winter,
+            _ => ThermoSeason::Summer,
+        };
+        let result = client.set_thermostat_season(&id, season.clone()).await;
+        self.message = Some(match result {
+            Ok(()) => {
+                let message = format!("{} season set to {:?}", name, season);
+                thermostat.season = Some(season);
+                message
+            }
+            Err(e) => format!("{}: {}", name, e),
+        });
+    }
+
+    fn apply_updates(&mut self) {
+        while let Ok(device) = self.updates.try_recv() {
+            let id = device.id();
+            if let Some(item) = self.accessory_list.items.iter_mut().find(|i| i.id == id) {
+                item.update(device);
             }
         }
     }
@@ -139,12 +446,28 @@ impl App {
             .port(port)
             .build()
             .map_err(|e| ComelitClientError::Generic(e.to_string()))?;
-        let client = ComelitClient::new(options, None).await?;
+        let (tx, updates) = mpsc::unbounded_channel();
+        let client = ComelitClient::new(options, Some(Arc::new(UpdateForwarder { tx }))).await?;
+        client.login(State::Disconnected).await?;
+        client.subscribe(ROOT_ID).await?;
+
+        let mut items: Vec<AccessoryItem> = client
+            .fetch_index(1)
+            .await?
+            .into_iter()
+            .filter_map(|(_, device)| AccessoryItem::from_device(device))
+            .collect();
+        items.sort_by(|a, b| a.description.cmp(&b.description));
 
         Ok(Self {
             should_exit: false,
-            accessory_list: AccessoryList::default(),
+            accessory_list: AccessoryList {
+                items,
+                state: ListState::default(),
+            },
             client,
+            message: None,
+            updates,
         })
     }
 }
@@ -162,7 +485,7 @@ impl Widget for &mut App {
             Layout::vertical([Constraint::Fill(1), Constraint::Fill(1)]).areas(main_area);
 
         App::render_header(header_area, buf);
-        App::render_footer(footer_area, buf);
+        self.render_footer(footer_area, buf);
         self.render_list(list_area, buf);
         self.render_selected_item(item_area, buf);
     }
@@ -171,21 +494,22 @@ impl Widget for &mut App {
 /// Rendering logic for the app
 impl App {
     fn render_header(area: Rect, buf: &mut Buffer) {
-        Paragraph::new("Ratatui List Example")
+        Paragraph::new("Comelit HUB")
             .bold()
             .centered()
             .render(area, buf);
     }
 
-    fn render_footer(area: Rect, buf: &mut Buffer) {
-        Paragraph::new("Use ↓↑ to move, ← to unselect, → to change status, g/G to go top/bottom.")
-            .centered()
-            .render(area, buf);
+    fn render_footer(&self, area: Rect, buf: &mut Buffer) {
+        let text = self.message.as_deref().unwrap_or(
+            "Use ↓↑ to move, ← to unselect, → to turn on/off, g/G to go top/bottom, q to quit.",
+        );
+        Paragraph::new(text).centered().render(area, buf);
     }
 
     fn render_list(&mut self, area: Rect, buf: &mut Buffer) {
         let block = Block::new()
-            .title(Line::raw("TODO List").centered())
+            .title(Line::raw("Accessories").centered())
             .borders(Borders::TOP)
             .border_set(symbols::border::EMPTY)
             .border_style(TODO_HEADER_STYLE)
@@ -197,9 +521,9 @@ impl App {
             .items
             .iter()
             .enumerate()
-            .map(|(i, todo_item)| {
+            .map(|(i, item)| {
                 let color = alternate_colors(i);
-                ListItem::from(todo_item).bg(color)
+                ListItem::from(item).bg(color)
             })
             .collect();
 
@@ -218,12 +542,27 @@ impl App {
     fn render_selected_item(&self, area: Rect, buf: &mut Buffer) {
         // We get the info depending on the item's state.
         let info = if let Some(i) = self.accessory_list.state.selected() {
-            match self.accessory_list.items[i].status {
-                DeviceStatus::On => format!("✓ ON: {}", self.accessory_list.items[i].description),
-                DeviceStatus::Off => format!("☐ OFF: {}", self.accessory_list.items[i].description),
-                DeviceStatus::Running => {
-                    format!("▶ RUNNING: {}", self.accessory_list.items[i].description)
+            let item = &self.accessory_list.items[i];
+            let status = match item.status {
+                DeviceStatus::On => format!("✓ ON: {}", item.description),
+                DeviceStatus::Off => format!("☐ OFF: {}", item.description),
+                DeviceStatus::Running => format!("▶ RUNNING: {}", item.description),
+            };
+            match &item.device {
+                HomeDeviceData::Thermostat(thermostat) => thermostat_details(status, thermostat),
+                HomeDeviceData::WindowCovering(_) => {
+                    let movement = match item.movement() {
+                        Some((direction, elapsed)) => {
+                            format!("{} for {}s", direction, elapsed.as_secs())
+                        }
+                        None => "stopped".to_string(),
+                    };
+                    format!(
+                        "{}\n\nMovement: {}\n\no open, c close, space stop",
+                        item.description, movement
+                    )
                 }
+                _ => status,
             }
         } else {
             "Nothing selected...".to_string()
@@ -231,7 +570,7 @@ impl App {
 
         // We show the list item's info under the list in this paragraph
         let block = Block::new()
-            .title(Line::raw("TODO Info").centered())
+            .title(Line::raw("Details").centered())
             .borders(Borders::TOP)
             .border_set(symbols::border::EMPTY)
             .border_style(TODO_HEADER_STYLE)
@@ -247,6 +586,34 @@ impl App {
     }
 }
 
+/// Temperatures are sent by the hub in tenths of degree
+fn tenths(value: &Option<String>) -> Option<i32> {
+    value.as_deref().and_then(|v| v.parse().ok())
+}
+
+fn celsius(value: Option<i32>) -> String {
+    value
+        .map(|v| format!("{:.1}°C", v as f32 / 10.0))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+fn thermostat_details(status: String, thermostat: &ThermostatDeviceData) -> String {
+    let humidity = thermostat
+        .humidity
+        .as_deref()
+        .map(|h| format!("{}%", h))
+        .unwrap_or_else(|| "-".to_string());
+    format!(
+        "{}\n\nTemperature: {}\nTarget: {}\nHumidity: {}\nMode: {:?}\nSeason: {:?}\n\n+/- change the target, m the mode, s the season",
+        status,
+        celsius(tenths(&thermostat.temperature)),
+        celsius(tenths(&thermostat.active_threshold)),
+        humidity,
+        thermostat.auto_man.clone().unwrap_or_default(),
+        thermostat.season.clone().unwrap_or_default(),
+    )
+}
+
 const fn alternate_colors(i: usize) -> Color {
     if i.is_multiple_of(2) {
         NORMAL_ROW_BG
@@ -257,6 +624,21 @@ const fn alternate_colors(i: usize) -> Color {
 
 impl From<&AccessoryItem> for ListItem<'_> {
     fn from(value: &AccessoryItem) -> Self {
+        if let Some((direction, elapsed)) = value.movement() {
+            let arrow = if direction == "opening" { '▲' } else { '▼' };
+            let spinner = SPINNER[(elapsed.as_millis() / 100) as usize % SPINNER.len()];
+            return ListItem::new(Line::styled(
+                format!(
+                    " {} {} {} {} {}s",
+                    arrow,
+                    value.description,
+                    spinner,
+                    direction,
+                    elapsed.as_secs()
+                ),
+                COMPLETED_TEXT_FG_COLOR,
+            ));
+        }
         let line = match value.status {
             DeviceStatus::Off => Line::styled(format!(" ☐ {}", value.description), TEXT_FG_COLOR),
             DeviceStatus::On => {
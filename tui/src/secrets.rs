@@ -0,0 +1,47 @@
+//! Credentials of the hub, read like the other binaries do: from the flags, from
+//! the file pointed by `<var>_FILE` or the `<var>` environment variable, then from
+//! the password saved in the OS keyring by `comelit-hub-cli login --save`.
+
+use anyhow::{Context, Result};
+use keyring::Entry;
+use std::env;
+
+/// Environment variable holding the Comelit hub user.
+pub const USER_VAR: &str = "COMELIT_USER";
+/// Environment variable holding the Comelit hub password.
+pub const PASSWORD_VAR: &str = "COMELIT_PASSWORD";
+/// Keyring service of the passwords saved by `comelit-hub-cli`
+const SERVICE: &str = "comelit-hub-cli";
+
+/// Resolve a secret, in order of precedence, from the given explicit value,
+/// from the file pointed by `<var>_FILE` or from the `<var>` environment variable.
+pub fn resolve(explicit: Option<String>, var: &str) -> Result<Option<String>> {
+    if explicit.is_some() {
+        return Ok(explicit);
+    }
+    let file_var = format!("{var}_FILE");
+    if let Ok(path) = env::var(&file_var) {
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {file_var} from {path}"))?;
+        return Ok(Some(content.trim().to_string()));
+    }
+    Ok(env::var(var).ok())
+}
+
+/// Password saved with `comelit-hub-cli login --save`, by user and hub address,
+/// the hub found on the network being "default".
+pub async fn load_password(user: Option<String>, host: Option<String>) -> Option<String> {
+    tokio::task::spawn_blocking(move || {
+        let account = format!(
+            "{}@{}",
+            user.unwrap_or_default(),
+            host.as_deref().unwrap_or("default")
+        );
+        Entry::new(SERVICE, &account)
+            .and_then(|entry| entry.get_password())
+            .ok()
+    })
+    .await
+    .ok()
+    .flatten()
+}
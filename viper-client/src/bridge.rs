@@ -0,0 +1,84 @@
+use crate::JSONResult;
+use crate::client::ViperClient;
+use crate::command_response::{
+    AuthResponse, ConfigurationResponse, EntrySelector, FaceRecognitionParams,
+    FaceRecognitionResponse, InfoResponse, VipResponse,
+};
+use crate::events::ViperEvent;
+use std::time::Duration;
+
+// Events of `ViperBridge::listen_events`, blocking until the next one arrives
+pub type EventStream<'a> = Box<dyn Iterator<Item = JSONResult<ViperEvent>> + 'a>;
+
+// The operations of an ICONA bridge, implemented by `ViperClient` and by
+// `fake::FakeViperBridge` so that the code using them can be tested without
+// a door station
+pub trait ViperBridge {
+    fn authorize(&mut self, token: &str) -> JSONResult<AuthResponse>;
+
+    fn configuration(&mut self, addressbooks: &str) -> JSONResult<ConfigurationResponse>;
+
+    fn info(&mut self) -> JSONResult<InfoResponse>;
+
+    fn face_recognition_params(&mut self) -> JSONResult<FaceRecognitionResponse>;
+
+    fn set_face_recognition_params(&mut self, params: &FaceRecognitionParams) -> JSONResult<()>;
+
+    fn open_door_by(&mut self, vip: &VipResponse, selector: &EntrySelector) -> JSONResult<()>;
+
+    fn open_actuator_by(&mut self, vip: &VipResponse, selector: &EntrySelector) -> JSONResult<()>;
+
+    fn listen_events(&mut self, vip: &VipResponse) -> JSONResult<EventStream<'_>>;
+
+    // Like `listen_events`, yielding `ViperError::Timeout` when no event
+    // arrives in time, e.g. to check whether the listener should stop
+    fn listen_events_timeout(
+        &mut self,
+        vip: &VipResponse,
+        timeout: Duration,
+    ) -> JSONResult<EventStream<'_>>;
+}
+
+impl ViperBridge for ViperClient {
+    fn authorize(&mut self, token: &str) -> JSONResult<AuthResponse> {
+        ViperClient::authorize(self, token)
+    }
+
+    fn configuration(&mut self, addressbooks: &str) -> JSONResult<ConfigurationResponse> {
+        ViperClient::configuration(self, addressbooks)
+    }
+
+    fn info(&mut self) -> JSONResult<InfoResponse> {
+        ViperClient::info(self)
+    }
+
+    fn face_recognition_params(&mut self) -> JSONResult<FaceRecognitionResponse> {
+        ViperClient::face_recognition_params(self)
+    }
+
+    fn set_face_recognition_params(&mut self, params: &FaceRecognitionParams) -> JSONResult<()> {
+        ViperClient::set_face_recognition_params(self, params)
+    }
+
+    fn open_door_by(&mut self, vip: &VipResponse, selector: &EntrySelector) -> JSONResult<()> {
+        ViperClient::open_door_by(self, vip, selector)
+    }
+
+    fn open_actuator_by(&mut self, vip: &VipResponse, selector: &EntrySelector) -> JSONResult<()> {
+        ViperClient::open_actuator_by(self, vip, selector)
+    }
+
+    fn listen_events(&mut self, vip: &VipResponse) -> JSONResult<EventStream<'_>> {
+        Ok(Box::new(ViperClient::listen_events(self, vip)?))
+    }
+
+    fn listen_events_timeout(
+        &mut self,
+        vip: &VipResponse,
+        timeout: Duration,
+    ) -> JSONResult<EventStream<'_>> {
+        let mut events = ViperClient::listen_events(self, vip)?;
+        events.set_timeout(Some(timeout));
+        Ok(Box::new(events))
+    }
+}
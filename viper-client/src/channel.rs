@@ -13,6 +13,10 @@ impl Channel {
         }
     }
 
+    pub fn control(&self) -> &[u8; 2] {
+        &self.control
+    }
+
     pub fn open(&self) -> Vec<u8> {
         Command::channel(&self.command, &self.control, None)
     }
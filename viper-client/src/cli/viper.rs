@@ -0,0 +1,351 @@
+use clap::{Args, Parser, Subcommand};
+use dotenvy::dotenv;
+use std::path::PathBuf;
+use std::process::Command;
+use viper_client::command_response::{EntrySelector, UserParametersResponse};
+use viper_client::credentials::CredentialStore;
+use viper_client::discovery::DiscoveryCache;
+use viper_client::events::ViperEvent;
+use viper_client::{ICONA_BRIDGE_PORT, ViperClient, ViperError};
+
+#[derive(Args, Debug, Clone)]
+struct Selector {
+    /// Position in the address book, as listed by `config`
+    #[arg(long, conflicts_with_all = ["id", "address"])]
+    index: Option<usize>,
+    #[arg(long, conflicts_with = "address")]
+    id: Option<String>,
+    /// Apt address of the entry, with --output
+    #[arg(long, requires = "output")]
+    address: Option<String>,
+    #[arg(long, requires = "address")]
+    output: Option<u8>,
+    /// Door station, by name or apt address, as listed by `config`: selects its first entry
+    #[arg(long, conflicts_with_all = ["index", "id", "address"])]
+    station: Option<String>,
+}
+
+impl Selector {
+    fn entry(self, params: &UserParametersResponse) -> EntrySelector {
+        if let Some(station) = self.station {
+            let address = params
+                .stations()
+                .into_iter()
+                .find(|s| s.name == station)
+                .map_or(station, |s| s.apt_address);
+            return EntrySelector::Station(address);
+        }
+        match (self.index, self.id, self.address, self.output) {
+            (_, Some(id), _, _) => EntrySelector::Id(id),
+            (_, _, Some(apt_address), Some(output_index)) => EntrySelector::Address {
+                apt_address,
+                output_index,
+            },
+            (index, ..) => EntrySelector::Index(index.unwrap_or(0)),
+        }
+    }
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum Commands {
+    /// Find the ICONA bridge on the local network (--ip is ignored), and remember its address
+    Scan,
+    /// Activate a new user on the bridge, print its token and save it for the next runs
+    Auth {
+        #[arg(long)]
+        email: String,
+    },
+    /// Print the server info
+    Info,
+    /// Print the door stations, doors, actuators and cameras of the configuration
+    Config {
+        /// Print the whole configuration as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Open a door (the first one by default)
+    OpenDoor {
+        #[command(flatten)]
+        selector: Selector,
+    },
+    /// Doors of the configuration, by name
+    Door {
+        #[command(subcommand)]
+        command: DoorCommands,
+    },
+    /// Incoming calls of the door stations
+    Doorbell {
+        #[command(subcommand)]
+        command: DoorbellCommands,
+    },
+    /// Activate an actuator (the first one by default)
+    OpenActuator {
+        #[command(flatten)]
+        selector: Selector,
+    },
+    /// Print the face recognition parameters, after applying the given changes
+    FaceRecognition {
+        /// Turn face recognition on or off
+        #[arg(long)]
+        enabled: Option<bool>,
+        /// Minimum similarity for a face to be recognized, in percent
+        #[arg(long, value_parser = clap::value_parser!(u8).range(0..=100))]
+        threshold: Option<u8>,
+        /// Change another parameter by its name, the value is parsed as JSON
+        #[arg(long, value_name = "NAME=VALUE", value_parser = parse_param)]
+        set: Vec<(String, serde_json::Value)>,
+    },
+    /// Print the calls as they happen
+    Listen {
+        /// Also post each event as JSON to this URL
+        #[arg(long, env = "VIPER_WEBHOOK_URL")]
+        webhook: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum DoorCommands {
+    /// Open the door with this name, as listed by `config`
+    Open { name: String },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum DoorbellCommands {
+    /// Print the incoming calls and ring the terminal bell, until interrupted
+    Watch {
+        /// Run this shell command on each call, with VIPER_EVENT, VIPER_FROM and
+        /// VIPER_STATION set in its environment
+        #[arg(long)]
+        exec: Option<String>,
+    },
+}
+
+#[derive(Parser, Debug)]
+struct Params {
+    /// Address of the bridge [default: the last one found, scanning if it does not answer]
+    #[clap(short, long, env = "ICONA_IP")]
+    ip: Option<String>,
+
+    #[clap(short, long, env = "ICONA_PORT")]
+    port: Option<u16>,
+
+    /// Token to use instead of the one saved by `auth` or in the keyring
+    #[clap(short, long, env = "ICONA_TOKEN")]
+    token: Option<String>,
+
+    /// File where the tokens are saved [default: ~/.config/viper/credentials.json]
+    #[clap(long, env = "VIPER_CREDENTIALS")]
+    credentials: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Commands,
+}
+
+// Token saved by `comelit-hub-cli login --save --viper-token` for the bridge
+fn keyring_token(bridge: &str) -> Option<String> {
+    keyring::Entry::new("viper", &format!("token@{}", bridge))
+        .and_then(|entry| entry.get_password())
+        .ok()
+}
+
+#[tokio::main]
+async fn main() -> Result<(), ViperError> {
+    dotenv().ok();
+
+    let params = Params::parse();
+    let cache = DiscoveryCache::default_path().map(DiscoveryCache::new);
+    // `scan` always looks for the bridge on the network, ignoring --ip
+    let scan = matches!(params.command, Commands::Scan);
+    let found = match (params.ip.filter(|_| !scan), &cache) {
+        (Some(ip), _) => Some((ip, params.port.unwrap_or(ICONA_BRIDGE_PORT))),
+        (None, Some(cache)) if scan => cache.rescan().await,
+        (None, Some(cache)) => cache.locate().await,
+        (None, None) => ViperClient::scan().await,
+    };
+    let Some((ip, port)) = found else {
+        eprintln!("No device found");
+        return Err(ViperError::NotFound(String::from("ICONA bridge")));
+    };
+
+    if let Commands::Scan = params.command {
+        println!("ICONA bridge found at {}:{}", ip, port);
+        return Ok(());
+    }
+
+    let mut client = ViperClient::connect(&ip, port)?;
+    let store = params
+        .credentials
+        .or_else(CredentialStore::default_path)
+        .map(CredentialStore::new);
+    client.set_credential_store(store.clone());
+    if let Commands::Auth { email } = &params.command {
+        let user = client.sign_up(email)?;
+        println!("Token is {}", user.user_token);
+        if let Some(store) = &store {
+            println!("Saved to {}", store.path().display());
+        }
+        return Ok(());
+    }
+
+    // The token given on the command line, then the one saved for this bridge
+    // in the credentials file, then the one saved in the keyring
+    match params.token {
+        Some(token) => {
+            client.authorize(&token)?;
+        }
+        None => match client.authorize_stored() {
+            Ok(_) => {}
+            Err(e @ ViperError::NotFound(_)) => {
+                let bridge = format!("{}:{}", ip, port);
+                let saved = tokio::task::spawn_blocking(move || keyring_token(&bridge))
+                    .await
+                    .ok()
+                    .flatten();
+                let Some(token) = saved else {
+                    eprintln!("Token is not provided, run `viper auth --email <email>` first");
+                    return Err(e);
+                };
+                client.authorize(&token)?;
+            }
+            Err(e) => return Err(e),
+        },
+    }
+
+    match params.command {
+        Commands::Scan | Commands::Auth { .. } => {}
+        Commands::Info => print_json(&client.info()?),
+        Commands::Config { json: true } => print_json(&client.configuration("all")?),
+        Commands::Config { json: false } => {
+            let params = client.configuration("all")?.vip.user_parameters;
+            for (title, entries) in [
+                ("Stations", params.stations()),
+                ("Doors", params.doors()),
+                ("Actuators", params.actuators()),
+                ("Cameras", params.cameras()),
+            ] {
+                println!("{}:", title);
+                for (i, entry) in entries.iter().enumerate() {
+                    println!("  [{}] {} (id {})", i, entry, entry.id);
+                }
+            }
+        }
+        Commands::OpenDoor { selector } => {
+            let vip = client.configuration("all")?.vip;
+            let selector = selector.entry(&vip.user_parameters);
+            client.open_door_by(&vip, &selector)?;
+            println!("Door opened");
+        }
+        Commands::Door {
+            command: DoorCommands::Open { name },
+        } => {
+            let vip = client.configuration("all")?.vip;
+            client.open_door_by(&vip, &EntrySelector::Name(name.clone()))?;
+            println!("Door {} opened", name);
+        }
+        Commands::OpenActuator { selector } => {
+            let vip = client.configuration("all")?.vip;
+            let selector = selector.entry(&vip.user_parameters);
+            client.open_actuator_by(&vip, &selector)?;
+            println!("Actuator activated");
+        }
+        Commands::FaceRecognition {
+            enabled,
+            threshold,
+            set,
+        } => {
+            let mut params = client.face_recognition_params()?.params;
+            if enabled.is_some() || threshold.is_some() || !set.is_empty() {
+                for (name, value) in set {
+                    params.set(&name, value)?;
+                }
+                params.enabled = enabled.or(params.enabled);
+                params.threshold = threshold.or(params.threshold);
+                client.set_face_recognition_params(&params)?;
+                params = client.face_recognition_params()?.params;
+            }
+            print_json(&params);
+        }
+        Commands::Listen { webhook } => {
+            client.set_webhook(webhook);
+            let vip = client.configuration("all")?.vip;
+            println!("Listening for calls, press Ctrl+C to stop");
+            for event in client.listen_events(&vip)? {
+                let event = event?;
+                match vip.user_parameters.station_of(event.source()) {
+                    Some(station) => println!("{:?} from {}", event, station.name),
+                    None => println!("{:?}", event),
+                }
+            }
+        }
+        Commands::Doorbell {
+            command: DoorbellCommands::Watch { exec },
+        } => {
+            let vip = client.configuration("all")?.vip;
+            println!("Watching for calls, press Ctrl+C to stop");
+            for event in client.listen_events(&vip)? {
+                let event = event?;
+                if !matches!(event, ViperEvent::IncomingCall { .. }) {
+                    continue;
+                }
+                let station = vip
+                    .user_parameters
+                    .station_of(event.source())
+                    .map(|s| s.name);
+                // The BEL character rings the terminal bell
+                println!(
+                    "\x07Call from {}",
+                    station.as_deref().unwrap_or(event.source())
+                );
+                if let Some(command) = &exec {
+                    run_hook(command, &event, station.as_deref());
+                }
+            }
+        }
+    }
+
+    client.shutdown();
+    Ok(())
+}
+
+// Run the command of `doorbell watch --exec` without waiting for it, so the
+// next calls are not missed
+fn run_hook(command: &str, event: &ViperEvent, station: Option<&str>) {
+    let name = serde_json::to_value(event)
+        .ok()
+        .and_then(|v| v["event"].as_str().map(String::from))
+        .unwrap_or_default();
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell
+        .arg(command)
+        .env("VIPER_EVENT", name)
+        .env("VIPER_FROM", event.source())
+        .env("VIPER_STATION", station.unwrap_or_default());
+    match shell.spawn() {
+        Ok(mut child) => {
+            std::thread::spawn(move || child.wait());
+        }
+        Err(e) => eprintln!("Failed to run {}: {}", command, e),
+    }
+}
+
+// NAME=VALUE, with a VALUE that is not valid JSON taken as a string
+fn parse_param(param: &str) -> Result<(String, serde_json::Value), String> {
+    let (name, value) = param
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=VALUE, got {}", param))?;
+    let value = serde_json::from_str(value)
+        .unwrap_or_else(|_| serde_json::Value::String(value.to_string()));
+    Ok((name.to_string(), value))
+}
+
+fn print_json<T: serde::Serialize>(value: &T) {
+    println!("{}", serde_json::to_string_pretty(value).unwrap());
+}
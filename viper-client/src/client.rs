@@ -1,25 +1,71 @@
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use serde::Deserialize;
-use tracing::debug;
+use tracing::{debug, warn};
 
 use crate::{
     JSONResult, ViperError,
     channel::Channel,
     command::CommandKind,
     command_response::{
-        ActivateUserResponse, AuthResponse, ConfigurationResponse, InfoResponse, VipResponse,
+        ActivateUserResponse, AddressBookEntry, AuthResponse, BaseResponse, ConfigurationResponse,
+        EntrySelector, FaceRecognitionParams, FaceRecognitionResponse, InfoResponse, VipResponse,
     },
+    credentials::{CredentialStore, Credentials},
     ctpp_channel::CTPPChannel,
+    events::EventListener,
     helper::Helper,
     stream_wrapper::StreamWrapper,
+    webhook::Webhook,
 };
 
+#[cfg(feature = "unstable")]
+use crate::{command::Command, raw::RawChannel};
+
 pub const ICONA_BRIDGE_PORT: u16 = 64100;
 
+// Start of the reply to a channel opening accepted by the bridge
+const CHANNEL_OPENED: [u8; 2] = [0xcd, 0xab];
+
+// Timeouts of the connection to the bridge
+#[derive(Debug, Clone, Copy)]
+pub struct ViperClientConfig {
+    // Time to establish the TCP connection
+    pub connect_timeout: Duration,
+    // Time to wait for each read or write
+    pub read_timeout: Duration,
+    // Time to wait for an operation made of several exchanges, like the CTPP handshake
+    pub command_timeout: Duration,
+    // How often a keepalive is sent while listening for events
+    pub keepalive_interval: Duration,
+}
+
+impl Default for ViperClientConfig {
+    fn default() -> Self {
+        ViperClientConfig {
+            connect_timeout: Duration::from_secs(5),
+            read_timeout: Duration::from_millis(1000),
+            command_timeout: Duration::from_secs(10),
+            keepalive_interval: Duration::from_secs(30),
+        }
+    }
+}
+
 pub struct ViperClient {
-    stream: StreamWrapper,
+    pub(crate) stream: StreamWrapper,
     control: [u8; 2],
+    // Token of the last successful authorization, used to authorize again
+    token: Option<String>,
+    // URL the events of `listen_events` are posted to
+    webhook_url: Option<String>,
+    // Where the token of `sign_up` and `authorize` is saved
+    store: Option<CredentialStore>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct ResponseCode {
+    response_code: Option<u16>,
 }
 
 impl ViperClient {
@@ -45,31 +91,55 @@ impl ViperClient {
     }
 
     pub fn new(ip: &str, port: u16) -> ViperClient {
+        ViperClient::with_config(ip, port, ViperClientConfig::default())
+    }
+
+    pub fn with_config(ip: &str, port: u16, config: ViperClientConfig) -> ViperClient {
+        ViperClient::connect_with_config(ip, port, config).expect("Doorbell unavailable")
+    }
+
+    // Like `new`, returning an error instead of panicking when the bridge
+    // can't be reached, e.g. to retry later
+    pub fn connect(ip: &str, port: u16) -> JSONResult<ViperClient> {
+        ViperClient::connect_with_config(ip, port, ViperClientConfig::default())
+    }
+
+    pub fn connect_with_config(
+        ip: &str,
+        port: u16,
+        config: ViperClientConfig,
+    ) -> JSONResult<ViperClient> {
         let doorbell = format!("{}:{}", ip, port);
 
-        ViperClient {
-            stream: StreamWrapper::new(doorbell),
+        Ok(ViperClient {
+            stream: StreamWrapper::open(doorbell, config)?,
             control: Helper::control(),
-        }
+            token: None,
+            webhook_url: None,
+            store: None,
+        })
     }
 
     pub fn sign_up(&mut self, email: &str) -> JSONResult<ActivateUserResponse> {
         let fact_channel = self.channel("FACT");
-        self.stream.execute(&fact_channel.open())?;
+        self.open_channel(fact_channel.control(), &fact_channel.open(), "FACT")?;
         let activate_user = CommandKind::ActivateUser(String::from(email));
         let act_bytes = self.stream.execute(&fact_channel.com(activate_user))?;
-        let json_response = Self::json(&act_bytes);
+        let json_response: JSONResult<ActivateUserResponse> = Self::json(&act_bytes);
 
-        self.stream.execute(&fact_channel.close())?;
+        self.close_channel(fact_channel.control(), &fact_channel.close())?;
+        if let Ok(user) = &json_response {
+            self.save_credentials(Some(email), &user.user_token);
+        }
         json_response
     }
 
     pub fn remove_all_users(&mut self, email: &String) -> JSONResult<serde_json::Value> {
         let fact_channel = self.channel("FACT");
-        self.stream.execute(&fact_channel.open())?;
+        self.open_channel(fact_channel.control(), &fact_channel.open(), "FACT")?;
         let remove_all_users = CommandKind::RemoveAllUsers(String::from(email));
         let rem_bytes = self.stream.execute(&fact_channel.com(remove_all_users))?;
-        self.stream.execute(&fact_channel.close())?;
+        self.close_channel(fact_channel.control(), &fact_channel.close())?;
 
         Self::json(&rem_bytes)
     }
@@ -77,83 +147,318 @@ impl ViperClient {
     pub fn authorize(&mut self, token: &str) -> JSONResult<AuthResponse> {
         let uaut = CommandKind::UAUT(token.into());
         let uaut_channel = self.channel("UAUT");
-        self.stream.execute(&uaut_channel.open())?;
+        self.open_channel(uaut_channel.control(), &uaut_channel.open(), "UAUT")?;
         let uaut_bytes = self.stream.execute(&uaut_channel.com(uaut))?;
 
         let json_response = Self::json(&uaut_bytes);
-        self.stream.execute(&uaut_channel.close())?;
+        self.close_channel(uaut_channel.control(), &uaut_channel.close())?;
+        if json_response.is_ok() {
+            self.token = Some(token.to_string());
+            self.save_credentials(None, token);
+        }
         json_response
     }
 
+    // Save the token obtained from `sign_up` and accepted by `authorize` in the
+    // store, and read it back with `authorize_stored`
+    pub fn set_credential_store(&mut self, store: Option<CredentialStore>) {
+        self.store = store;
+    }
+
+    // Authorize with the token saved for this bridge in the credential store
+    pub fn authorize_stored(&mut self) -> JSONResult<AuthResponse> {
+        let bridge = self.stream.address().to_string();
+        let credentials = match &self.store {
+            Some(store) => store.load(&bridge)?,
+            None => None,
+        };
+        match credentials {
+            Some(credentials) => self.authorize(&credentials.token),
+            None => Err(ViperError::NotFound(format!("token for {}", bridge))),
+        }
+    }
+
+    // Keep the email of a previous `sign_up` when only the token is known
+    fn save_credentials(&self, email: Option<&str>, token: &str) {
+        let Some(store) = &self.store else {
+            return;
+        };
+        let bridge = self.stream.address();
+        let email = match email {
+            Some(email) => Some(email.to_string()),
+            None => store.load(bridge).ok().flatten().and_then(|c| c.email),
+        };
+        let credentials = Credentials {
+            email,
+            token: token.to_string(),
+        };
+        if let Err(e) = store.save(bridge, &credentials) {
+            warn!(
+                "Failed to save the credentials to {}: {}",
+                store.path().display(),
+                e
+            );
+        }
+    }
+
+    // Run the authorization again with the stored token, if any
+    fn reauthorize(&mut self) -> JSONResult<()> {
+        if let Some(token) = self.token.clone() {
+            self.authorize(&token)?;
+        }
+        Ok(())
+    }
+
     pub fn configuration(&mut self, addressbooks: &str) -> JSONResult<ConfigurationResponse> {
+        self.with_retry(|client| client.fetch_configuration(addressbooks))
+    }
+
+    fn fetch_configuration(&mut self, addressbooks: &str) -> JSONResult<ConfigurationResponse> {
         let ucfg = CommandKind::UCFG(addressbooks.into());
         let ucfg_channel = self.channel("UCFG");
-        self.stream.execute(&ucfg_channel.open())?;
+        self.open_channel(ucfg_channel.control(), &ucfg_channel.open(), "UCFG")?;
         let ucfg_bytes = self.stream.execute(&ucfg_channel.com(ucfg))?;
 
         let str = String::from_utf8_lossy(&ucfg_bytes);
         debug!("Configuration response: {}", str);
         let json_response = Self::json(&ucfg_bytes);
-        self.stream.execute(&ucfg_channel.close())?;
+        self.close_channel(ucfg_channel.control(), &ucfg_channel.close())?;
         json_response
     }
 
+    // Doors that can be opened, from the configuration
+    pub fn list_doors(&mut self) -> JSONResult<Vec<AddressBookEntry>> {
+        Ok(self.configuration("all")?.vip.user_parameters.doors())
+    }
+
+    // Actuators (e.g. gates and lights), from the configuration
+    pub fn list_actuators(&mut self) -> JSONResult<Vec<AddressBookEntry>> {
+        Ok(self.configuration("all")?.vip.user_parameters.actuators())
+    }
+
+    // Cameras, including the RTSP ones, from the configuration
+    pub fn list_cameras(&mut self) -> JSONResult<Vec<AddressBookEntry>> {
+        Ok(self.configuration("all")?.vip.user_parameters.cameras())
+    }
+
     pub fn info(&mut self) -> JSONResult<InfoResponse> {
+        self.with_retry(Self::fetch_info)
+    }
+
+    fn fetch_info(&mut self) -> JSONResult<InfoResponse> {
         let info = CommandKind::INFO;
         let info_channel = self.channel("INFO");
-        self.stream.execute(&info_channel.open())?;
+        self.open_channel(info_channel.control(), &info_channel.open(), "INFO")?;
 
         let info_bytes = self.stream.execute(&info_channel.com(info))?;
         let json_response = Self::json(&info_bytes);
-        self.stream.execute(&info_channel.close())?;
+        self.close_channel(info_channel.control(), &info_channel.close())?;
         json_response
     }
 
-    pub fn face_recognition_params(&mut self) -> JSONResult<serde_json::Value> {
+    pub fn face_recognition_params(&mut self) -> JSONResult<FaceRecognitionResponse> {
+        self.with_retry(Self::fetch_face_recognition_params)
+    }
+
+    fn fetch_face_recognition_params(&mut self) -> JSONResult<FaceRecognitionResponse> {
         let frcg = CommandKind::FRCG;
         let frcg_channel = self.channel("FRCG");
-        self.stream.execute(&frcg_channel.open())?;
+        self.open_channel(frcg_channel.control(), &frcg_channel.open(), "FRCG")?;
 
         let frcg_bytes = self.stream.execute(&frcg_channel.com(frcg))?;
         let json_response = Self::json(&frcg_bytes);
-        self.stream.execute(&frcg_channel.close())?;
+        self.close_channel(frcg_channel.control(), &frcg_channel.close())?;
         json_response
     }
 
-    // TODO: This function is not finished
-    pub fn open_door(&mut self, vip: &VipResponse) -> Result<(), std::io::Error> {
+    // Change the face recognition settings, e.g. the params read with
+    // `face_recognition_params` after changing some of them. Not retried,
+    // like the other commands changing the state of the bridge.
+    pub fn set_face_recognition_params(
+        &mut self,
+        params: &FaceRecognitionParams,
+    ) -> JSONResult<()> {
+        let frcg = CommandKind::FRCGSet(params.clone());
+        let frcg_channel = self.channel("FRCG");
+        self.open_channel(frcg_channel.control(), &frcg_channel.open(), "FRCG")?;
+
+        let frcg_bytes = self.stream.execute(&frcg_channel.com(frcg))?;
+        let json_response: JSONResult<BaseResponse> = Self::json(&frcg_bytes);
+        self.close_channel(frcg_channel.control(), &frcg_channel.close())?;
+        json_response.map(|_| ())
+    }
+
+    // Run a read-only command again on a new connection when the current one
+    // fails, or after authorizing again when the token was rejected. The command
+    // opens its own channel, so retrying it is safe.
+    fn with_retry<T>(
+        &mut self,
+        command: impl Fn(&mut ViperClient) -> JSONResult<T>,
+    ) -> JSONResult<T> {
+        match command(self) {
+            Err(e @ (ViperError::IOError(_) | ViperError::Timeout)) => {
+                warn!("Viper connection failed: {}, reconnecting", e);
+                self.stream.reconnect()?;
+                self.reauthorize()?;
+                command(self)
+            }
+            Err(ViperError::AuthError { code }) if self.token.is_some() => {
+                warn!("Viper token rejected ({}), authorizing again", code);
+                self.reauthorize()?;
+                command(self)
+            }
+            result => result,
+        }
+    }
+
+    // Open the first door of the address book
+    pub fn open_door(&mut self, vip: &VipResponse) -> JSONResult<()> {
+        self.open_door_by(vip, &EntrySelector::Index(0))
+    }
+
+    // Open the door matching the selector
+    pub fn open_door_by(&mut self, vip: &VipResponse, selector: &EntrySelector) -> JSONResult<()> {
+        let doors = vip.user_parameters.doors();
+        let door = selector.find("door", &doors)?;
+        self.link_actuators(vip, door)
+    }
+
+    // Activate the actuator matching the selector
+    pub fn open_actuator_by(
+        &mut self,
+        vip: &VipResponse,
+        selector: &EntrySelector,
+    ) -> JSONResult<()> {
+        let actuators = vip.user_parameters.actuators();
+        let actuator = selector.find("actuator", &actuators)?;
+        self.link_actuators(vip, actuator)
+    }
+
+    // Activate the entry and wait for the bridge to confirm it. Fails with
+    // `ViperError::NotConfirmed` when no confirmation arrives in time.
+    fn link_actuators(&mut self, vip: &VipResponse, entry: &AddressBookEntry) -> JSONResult<()> {
         let addr = vip.apt_address.to_string();
         let sub = format!("{}{}", addr, vip.apt_subaddress);
-        let act = vip.user_parameters.opendoor_address_book[0]
-            .apt_address
-            .to_string();
+        // Like the apartment, an output is addressed by appending its index
+        // to the address of the station, e.g. SB1000012 for the output 2
+        let act = match entry.output_index {
+            Some(output_index) => format!("{}{}", entry.apt_address, output_index),
+            None => entry.apt_address.to_string(),
+        };
 
         let mut ctpp_channel = self.ctpp_channel();
-        self.stream.execute(&ctpp_channel.open(&sub))?;
-        self.stream.write(&ctpp_channel.connect_hs(&sub, &addr))?;
+        self.ctpp_handshake(&mut ctpp_channel, &sub, &addr)?;
+        self.stream
+            .write(&ctpp_channel.link_actuators(&act, &sub))?;
+
+        // Other frames, e.g. the acks of the handshake, may come first
+        let deadline = Instant::now() + self.stream.config().command_timeout;
+        let confirmed = loop {
+            if Instant::now() > deadline {
+                break false;
+            }
+            match self
+                .stream
+                .read_on(ctpp_channel.control())
+                .map_err(ViperError::from)
+            {
+                Ok(resp) if ctpp_channel.confirm(&resp) => break true,
+                Ok(resp) => debug!("{:02x?}", resp),
+                Err(ViperError::Timeout) => break false,
+                Err(e) => return Err(e),
+            }
+        };
+
+        // Close the remaining channels
+        self.close_channel(ctpp_channel.control(), &ctpp_channel.close())?;
+        if confirmed {
+            Ok(())
+        } else {
+            Err(ViperError::NotConfirmed(entry.name.clone()))
+        }
+    }
+
+    // Keep a CTPP channel open and iterate over the calls pushed by the
+    // bridge. Iterating blocks until the next event arrives.
+    pub fn listen_events(&mut self, vip: &VipResponse) -> JSONResult<EventListener<'_>> {
+        let addr = vip.apt_address.to_string();
+        let sub = format!("{}{}", addr, vip.apt_subaddress);
+
+        let mut ctpp_channel = self.ctpp_channel();
+        self.ctpp_handshake(&mut ctpp_channel, &sub, &addr)?;
+        let webhook = self.webhook_url.clone().map(|url| Webhook::new(url, vip));
+        EventListener::new(self, ctpp_channel, sub, addr, webhook)
+    }
 
+    // Post each event of `listen_events` as JSON to the URL, with the name of
+    // the door station when known. Useful as a standalone doorbell notifier.
+    pub fn set_webhook(&mut self, url: Option<String>) {
+        self.webhook_url = url;
+    }
+
+    // Open a channel by name (e.g. "UCFG"), with the optional extra bytes of
+    // the opening request, to exchange raw frames on it
+    #[cfg(feature = "unstable")]
+    pub fn raw_channel(&mut self, name: &str, extra: Option<&[u8]>) -> JSONResult<RawChannel<'_>> {
+        self.tick();
+        let control = self.control;
+        let request = Command::channel(&String::from(name), &control, extra);
+        self.open_channel(&control, &request, name)?;
+        Ok(RawChannel::new(self, control))
+    }
+
+    // Send a frame on a new channel and return the body of the first reply
+    #[cfg(feature = "unstable")]
+    pub fn raw_command(&mut self, name: &str, body: &[u8]) -> JSONResult<Vec<u8>> {
+        let mut channel = self.raw_channel(name, None)?;
+        channel.send(body)?;
+        let reply = channel.read()?;
+        channel.close()?;
+        Ok(reply)
+    }
+
+    // Open a CTPP channel and register the apartment address on it
+    fn ctpp_handshake(
+        &mut self,
+        ctpp_channel: &mut CTPPChannel,
+        sub: &String,
+        addr: &String,
+    ) -> JSONResult<()> {
+        self.open_channel(ctpp_channel.control(), &ctpp_channel.open(sub), "CTPP")?;
+        self.stream.write(&ctpp_channel.connect_hs(sub, addr))?;
+
+        let deadline = Instant::now() + self.stream.config().command_timeout;
         loop {
-            let resp = self.stream.read()?;
+            if Instant::now() > deadline {
+                return Err(ViperError::Timeout);
+            }
+            let resp = self.stream.read_on(ctpp_channel.control())?;
             debug!("{:02x?}", resp);
             if ctpp_channel.confirm_handshake(&resp) {
                 break;
             }
         }
 
-        self.stream.write(&ctpp_channel.ack(0x00, &sub, &addr))?;
-        self.stream.write(&ctpp_channel.ack(0x20, &sub, &addr))?;
-        self.stream
-            .write(&ctpp_channel.link_actuators(&act, &sub))?;
+        self.stream.write(&ctpp_channel.ack(0x00, sub, addr))?;
+        self.stream.write(&ctpp_channel.ack(0x20, sub, addr))?;
+        Ok(())
+    }
 
-        let resp = self.stream.read()?;
-        if ctpp_channel.confirm(&resp) {
-            // ????
+    // Open a channel, checking that the bridge accepted it
+    fn open_channel(&mut self, control: &[u8; 2], request: &[u8], name: &str) -> JSONResult<()> {
+        let resp = self.stream.execute_on(control, request)?;
+        if resp.starts_with(&CHANNEL_OPENED) {
+            self.stream.opened(control);
+            Ok(())
         } else {
-            // raise an error
+            Err(ViperError::ChannelError(String::from(name)))
         }
+    }
 
-        // Close the remaining channels
-        self.stream.execute(&ctpp_channel.close())?;
+    // Close a channel, dropping the frames still queued for it
+    fn close_channel(&mut self, control: &[u8; 2], request: &[u8]) -> JSONResult<()> {
+        self.stream.execute_on(control, request)?;
+        self.stream.discard(control);
         Ok(())
     }
 
@@ -170,6 +475,24 @@ impl ViperClient {
     }
 
     fn json<'a, T: Deserialize<'a>>(bytes: &'a [u8]) -> JSONResult<T> {
+        if let Ok(ResponseCode {
+            response_code: Some(code @ (401 | 403)),
+        }) = serde_json::from_slice(bytes)
+        {
+            return Err(ViperError::AuthError { code });
+        }
+        if let Ok(BaseResponse {
+            response_code,
+            response_string,
+            ..
+        }) = serde_json::from_slice(bytes)
+            && response_code != 200
+        {
+            return Err(ViperError::ProtocolError(format!(
+                "{} ({})",
+                response_string, response_code
+            )));
+        }
         match serde_json::from_slice(bytes) {
             Ok(json) => Ok(json),
             Err(e) => Err(ViperError::JSONError(e)),
@@ -180,18 +503,44 @@ impl ViperClient {
         self.stream.die();
     }
 
-    // Move the control byte 1 ahead
+    // Move to the control of the next channel. Once the counter wrapped around,
+    // the controls of the channels still open (e.g. the one of a long-running
+    // `listen_events`) are skipped, so their frames are not mixed up.
     fn tick(&mut self) {
-        self.control[0] += 1
+        for _ in 0..=u8::MAX {
+            self.control[0] = self.control[0].wrapping_add(1);
+            if !self.stream.is_open(&self.control) {
+                break;
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{command::Command, test_helper::SimpleTcpListener};
+    use crate::{command::Command, events::ViperEvent, test_helper::SimpleTcpListener};
+    use std::io::prelude::*;
+    use std::str;
     use std::thread;
 
+    fn vip() -> VipResponse {
+        serde_json::from_value(serde_json::json!({
+            "enabled": true,
+            "apt-address": "SB000006",
+            "apt-subaddress": 2,
+            "logical-subaddress": 2,
+            "apt-config": {
+                "description": "",
+                "call-divert-busy-en": false,
+                "call-divert-address": "",
+                "virtual-key-enabled": false
+            },
+            "user-parameters": { "forced": false }
+        }))
+        .unwrap()
+    }
+
     #[test]
     fn test_tick() {
         let _listener = SimpleTcpListener::new("127.0.0.1:3340");
@@ -200,7 +549,18 @@ mod tests {
         let c = client.control;
         client.tick();
 
-        assert_eq!(c[0] + 1, client.control[0])
+        assert_eq!(c[0] + 1, client.control[0]);
+
+        // The counter wraps around, skipping the channels still open
+        client.control[0] = 0xff;
+        client.stream.opened(&[0x00, c[1]]);
+        client.tick();
+        assert_eq!(client.control, [0x01, c[1]]);
+
+        client.stream.discard(&[0x00, c[1]]);
+        client.control[0] = 0xff;
+        client.tick();
+        assert_eq!(client.control, [0x00, c[1]]);
     }
 
     #[test]
@@ -232,4 +592,413 @@ mod tests {
         assert_eq!(resp.response.response_string, "Access Granted");
         assert_eq!(resp.response.response_code, 200)
     }
+
+    #[test]
+    fn test_authorize_stored() {
+        let listener = SimpleTcpListener::new("127.0.0.1:3349");
+        let mut client = ViperClient::new("127.0.0.1", 3349);
+        let dir = std::env::temp_dir().join(format!("viper-client-{}", std::process::id()));
+        let store = CredentialStore::new(dir.join("credentials.json"));
+        client.set_credential_store(Some(store.clone()));
+        assert!(matches!(
+            client.authorize_stored(),
+            Err(ViperError::NotFound(_))
+        ));
+        store
+            .save(
+                "127.0.0.1:3349",
+                &Credentials {
+                    email: Some(String::from("me@example.com")),
+                    token: String::from("TESTTOKEN"),
+                },
+            )
+            .unwrap();
+
+        let server = thread::spawn(move || {
+            let mut stream = listener.accept();
+            SimpleTcpListener::read_frame(&mut stream); // Opening the channel
+            stream
+                .write_all(&Command::make(&[0xcd, 0xab, 0x02, 0x00], &[0, 0]))
+                .unwrap();
+            let request = SimpleTcpListener::read_frame(&mut stream);
+            let granted = r#"{"message":"access","message-type":"response","message-id":5,"response-code":200,"response-string":"Access Granted"}"#;
+            stream
+                .write_all(&Command::make(granted.as_bytes(), &[0, 0]))
+                .unwrap();
+            SimpleTcpListener::read_frame(&mut stream); // Closing the channel
+            stream.write_all(&Command::make(&[], &[0, 0])).unwrap();
+            String::from_utf8_lossy(&request).into_owned()
+        });
+
+        client.authorize_stored().unwrap();
+        assert!(server.join().unwrap().contains("TESTTOKEN"));
+        // The email of the activation is kept
+        assert_eq!(
+            store
+                .load("127.0.0.1:3349")
+                .unwrap()
+                .unwrap()
+                .email
+                .as_deref(),
+            Some("me@example.com")
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_set_face_recognition_params() {
+        let listener = SimpleTcpListener::new("127.0.0.1:3350");
+        let mut client = ViperClient::new("127.0.0.1", 3350);
+
+        let server = thread::spawn(move || {
+            let mut stream = listener.accept();
+            SimpleTcpListener::read_frame(&mut stream); // Opening the channel
+            stream
+                .write_all(&Command::make(&[0xcd, 0xab, 0x02, 0x00], &[0, 0]))
+                .unwrap();
+            let request = SimpleTcpListener::read_frame(&mut stream);
+            let done = r#"{"message":"rcg-set-params","message-type":"response","message-id":121,"response-code":200,"response-string":"OK"}"#;
+            stream
+                .write_all(&Command::make(done.as_bytes(), &[0, 0]))
+                .unwrap();
+            SimpleTcpListener::read_frame(&mut stream); // Closing the channel
+            stream.write_all(&Command::make(&[], &[0, 0])).unwrap();
+            request
+        });
+
+        let params = FaceRecognitionParams {
+            enabled: Some(true),
+            ..FaceRecognitionParams::default()
+        };
+        client.set_face_recognition_params(&params).unwrap();
+
+        let request = server.join().unwrap();
+        let json = str::from_utf8(&request).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&json[json.find('{').unwrap()..]).unwrap();
+        assert_eq!(json["message"], "rcg-set-params");
+        assert_eq!(json["params"]["enabled"], true);
+    }
+
+    #[test]
+    fn test_listen_events() {
+        let listener = SimpleTcpListener::new("127.0.0.1:3342");
+        let mut client = ViperClient::new("127.0.0.1", 3342);
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept_ctpp();
+            let mut call = vec![0xc0, 0x18, 0x11, 0x22, 0x33, 0x44, 0x00, 0x18, 0x00, 0x01];
+            call.extend_from_slice(b"SB0000062\x00SB000006\x00");
+            stream.write_all(&Command::make(&call, &[0, 0])).unwrap();
+            SimpleTcpListener::read_frame(&mut stream); // Ack of the call
+        });
+
+        let mut events = client.listen_events(&vip()).unwrap();
+        assert_eq!(
+            events.next().unwrap().unwrap(),
+            ViperEvent::IncomingCall {
+                from: String::from("SB0000062")
+            }
+        );
+    }
+
+    #[test]
+    fn test_command_while_listening() {
+        let listener = SimpleTcpListener::new("127.0.0.1:3351");
+        let mut client = ViperClient::new("127.0.0.1", 3351);
+
+        thread::spawn(move || {
+            let (mut stream, ctpp) = listener.accept_ctpp();
+            SimpleTcpListener::read_frame(&mut stream); // Opening the INFO channel
+            stream
+                .write_all(&Command::make(&[0xcd, 0xab, 0x02, 0x00], &[0, 0]))
+                .unwrap();
+            let (info, _) = SimpleTcpListener::read_request(&mut stream);
+            // A call arrives on the CTPP channel before the reply to the request
+            let mut call = vec![0xc0, 0x18, 0x11, 0x22, 0x33, 0x44, 0x00, 0x18, 0x00, 0x01];
+            call.extend_from_slice(b"SB0000062\x00SB000006\x00");
+            stream.write_all(&Command::make(&call, &ctpp)).unwrap();
+            let reply = r#"{"message":"server-info","message-type":"response","message-id":1,"response-code":200,"response-string":"OK","model":"ICONA","version":"1.0","serial-code":"0001","capabilities":[]}"#;
+            stream
+                .write_all(&Command::make(reply.as_bytes(), &info))
+                .unwrap();
+            SimpleTcpListener::read_frame(&mut stream); // Closing the INFO channel
+            stream.write_all(&Command::make(&[], &info)).unwrap();
+            SimpleTcpListener::read_frame(&mut stream); // Ack of the call
+        });
+
+        let mut events = client.listen_events(&vip()).unwrap();
+        let info = events.with_client(|client| client.info()).unwrap();
+        assert_eq!(info.unwrap().model, "ICONA");
+        // The call received meanwhile was queued
+        assert_eq!(
+            events.next().unwrap().unwrap(),
+            ViperEvent::IncomingCall {
+                from: String::from("SB0000062")
+            }
+        );
+    }
+
+    #[test]
+    fn test_open_door() {
+        let listener = SimpleTcpListener::new("127.0.0.1:3353");
+        let config = ViperClientConfig {
+            read_timeout: Duration::from_millis(100),
+            command_timeout: Duration::from_millis(300),
+            ..ViperClientConfig::default()
+        };
+        let mut client = ViperClient::with_config("127.0.0.1", 3353, config);
+        let mut vip = vip();
+        vip.user_parameters.opendoor_address_book = serde_json::from_value(serde_json::json!([
+            { "id": 1, "name": "Gate", "apt-address": "SB100001", "output-index": 2, "secure-mode": false },
+            { "id": 2, "name": "Garage", "apt-address": "SB100001", "output-index": 3, "secure-mode": false }
+        ]))
+        .unwrap();
+
+        let server = thread::spawn(move || {
+            let mut links = Vec::new();
+            for confirmed in [true, true, false] {
+                let (mut stream, ctpp) = listener.accept_ctpp();
+                let link = SimpleTcpListener::read_frame(&mut stream);
+                let mask = &link[2..6];
+                // An unrelated frame comes before the confirmation
+                stream
+                    .write_all(&Command::make(&[0x00, 0x18, 0x01, 0x02, 0x03, 0x04], &ctpp))
+                    .unwrap();
+                if confirmed {
+                    let confirm = [0x00, 0x18, mask[0] + 0x80, mask[1], mask[3], mask[2] + 1];
+                    stream.write_all(&Command::make(&confirm, &ctpp)).unwrap();
+                }
+                SimpleTcpListener::read_frame(&mut stream); // Closing the CTPP channel
+                stream.write_all(&Command::make(&[], &ctpp)).unwrap();
+                links.push(link);
+            }
+            links
+        });
+
+        client.open_door(&vip).unwrap();
+        let mut client = ViperClient::with_config("127.0.0.1", 3353, config);
+        let garage = EntrySelector::Address {
+            apt_address: String::from("SB100001"),
+            output_index: 3,
+        };
+        client.open_door_by(&vip, &garage).unwrap();
+        let mut client = ViperClient::with_config("127.0.0.1", 3353, config);
+        assert!(matches!(
+            client.open_door(&vip),
+            Err(ViperError::NotConfirmed(name)) if name == "Gate"
+        ));
+
+        // The output of the door follows the address of the station
+        let links = server.join().unwrap();
+        assert_eq!(&links[0][10..19], b"SB1000012");
+        assert_eq!(&links[1][10..19], b"SB1000013");
+    }
+
+    #[cfg(feature = "unstable")]
+    #[test]
+    fn test_raw_command() {
+        let listener = SimpleTcpListener::new("127.0.0.1:3354");
+        let mut client = ViperClient::new("127.0.0.1", 3354);
+
+        let server = thread::spawn(move || {
+            let mut stream = listener.accept();
+            let open = SimpleTcpListener::read_frame(&mut stream);
+            stream
+                .write_all(&Command::make(&[0xcd, 0xab, 0x02, 0x00], &[0, 0]))
+                .unwrap();
+            let (control, body) = SimpleTcpListener::read_request(&mut stream);
+            stream
+                .write_all(&Command::make(&[0x01, 0x02], &control))
+                .unwrap();
+            SimpleTcpListener::read_frame(&mut stream); // Closing the channel
+            stream.write_all(&Command::make(&[], &control)).unwrap();
+            (open, body)
+        });
+
+        let reply = client.raw_command("UCFG", &[0xde, 0xad]).unwrap();
+        assert_eq!(reply, vec![0x01, 0x02]);
+        let (open, body) = server.join().unwrap();
+        assert_eq!(&open[8..12], b"UCFG");
+        assert_eq!(body, vec![0xde, 0xad]);
+    }
+
+    #[test]
+    fn test_retry_after_disconnect() {
+        let listener = SimpleTcpListener::new("127.0.0.1:3343");
+        let mut client = ViperClient::new("127.0.0.1", 3343);
+
+        thread::spawn(move || {
+            // The first connection is dropped by the bridge
+            drop(listener.accept());
+
+            let mocked_json = r#"{
+                "message":"server-info",
+                "message-type":"response",
+                "message-id":1,
+                "response-code":200,
+                "response-string":"OK",
+                "model":"ICONA",
+                "version":"1.0",
+                "serial-code":"0001",
+                "capabilities":[]
+            }"#;
+            listener.mock_server(vec![
+                Command::make(&[0xcd, 0xab, 0x02, 0x00], &[0, 0]),
+                Command::make(mocked_json.as_bytes(), &[0, 0]),
+                Command::make(&[], &[0, 0]), // Closing the channel
+            ])
+        });
+
+        let resp = client.info().unwrap();
+        assert_eq!(resp.model, "ICONA");
+    }
+
+    #[test]
+    fn test_reauthorize() {
+        let listener = SimpleTcpListener::new("127.0.0.1:3344");
+        let mut client = ViperClient::new("127.0.0.1", 3344);
+
+        thread::spawn(move || {
+            let mocked_open = [
+                0xcd, 0xab, 0x02, 0x00, 0x04, 0x00, 0x00, 0x00, 0x1a, 0x12, 0x00, 0x00,
+            ];
+            let granted = r#"{
+                "message":"access",
+                "message-type":"response",
+                "message-id":1,
+                "response-code":200,
+                "response-string":"Access Granted"
+            }"#;
+            let expired = r#"{
+                "message":"server-info",
+                "message-type":"response",
+                "message-id":1,
+                "response-code":401,
+                "response-string":"Unauthorized"
+            }"#;
+            let info = r#"{
+                "message":"server-info",
+                "message-type":"response",
+                "message-id":1,
+                "response-code":200,
+                "response-string":"OK",
+                "model":"ICONA",
+                "version":"1.0",
+                "serial-code":"0001",
+                "capabilities":[]
+            }"#;
+
+            let mut responses = vec![];
+            for json in [granted, expired, granted, info] {
+                responses.push(Command::make(&mocked_open, &[0, 0]));
+                responses.push(Command::make(json.as_bytes(), &[0, 0]));
+                responses.push(Command::make(&[], &[0, 0])); // Closing the channel
+            }
+            listener.mock_server(responses)
+        });
+
+        client.authorize("TESTTOKEN").unwrap();
+        let resp = client.info().unwrap();
+        assert_eq!(resp.model, "ICONA");
+    }
+
+    #[test]
+    fn test_errors() {
+        let listener = SimpleTcpListener::new("127.0.0.1:3345");
+        let mut client = ViperClient::new("127.0.0.1", 3345);
+
+        thread::spawn(move || listener.mock_server(vec![Command::make(&[0; 12], &[0, 0])]));
+
+        assert!(matches!(
+            client.info(),
+            Err(ViperError::ChannelError(channel)) if channel == "INFO"
+        ));
+        assert!(matches!(
+            client.open_door(&vip()),
+            Err(ViperError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_timeout() {
+        let listener = SimpleTcpListener::new("127.0.0.1:3346");
+        let config = ViperClientConfig {
+            read_timeout: Duration::from_millis(100),
+            ..ViperClientConfig::default()
+        };
+        let mut client = ViperClient::with_config("127.0.0.1", 3346, config);
+
+        // The bridge accepts the connections but never answers
+        thread::spawn(move || {
+            let _first = listener.accept();
+            let _second = listener.accept();
+            thread::sleep(Duration::from_secs(2));
+        });
+
+        let start = Instant::now();
+        assert!(matches!(client.info(), Err(ViperError::Timeout)));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_keepalive() {
+        let listener = SimpleTcpListener::new("127.0.0.1:3347");
+        let config = ViperClientConfig {
+            keepalive_interval: Duration::from_millis(50),
+            ..ViperClientConfig::default()
+        };
+        let mut client = ViperClient::with_config("127.0.0.1", 3347, config);
+
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept_ctpp();
+            let keepalive = SimpleTcpListener::read_frame(&mut stream);
+            let mut call = vec![0xc0, 0x18, 0x11, 0x22, 0x33, 0x44, 0x00, 0x18, 0x00, 0x01];
+            call.extend_from_slice(b"SB0000062\x00SB000006\x00");
+            stream.write_all(&Command::make(&call, &[0, 0])).unwrap();
+            let ack = SimpleTcpListener::read_frame(&mut stream);
+            let next = SimpleTcpListener::read_frame(&mut stream);
+            // Keep the connection open until the listener is done
+            (stream, keepalive, ack, next)
+        });
+
+        let mut events = client.listen_events(&vip()).unwrap();
+        assert!(events.next().unwrap().is_ok());
+        events.set_timeout(Some(Duration::from_millis(200)));
+        assert!(matches!(events.next(), Some(Err(ViperError::Timeout))));
+        let (_stream, keepalive, ack, next) = server.join().unwrap();
+        assert_eq!(&keepalive[0..2], &[0x20, 0x18]);
+        assert_eq!(str::from_utf8(&keepalive[12..21]).unwrap(), "SB0000062");
+        // The keepalives repeat the last ack sent on the channel
+        assert_eq!(next, ack);
+    }
+
+    #[test]
+    fn test_connect() {
+        // Nothing listens on this port
+        assert!(matches!(
+            ViperClient::connect("127.0.0.1", 3355),
+            Err(ViperError::IOError(_))
+        ));
+    }
+
+    #[test]
+    fn test_listen_timeout() {
+        let listener = SimpleTcpListener::new("127.0.0.1:3356");
+        let mut client = ViperClient::new("127.0.0.1", 3356);
+
+        // The bridge keeps the channel open without sending any event
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept_ctpp();
+            thread::sleep(Duration::from_millis(500));
+            drop(stream);
+        });
+
+        let mut events = client.listen_events(&vip()).unwrap();
+        events.set_timeout(Some(Duration::from_millis(100)));
+        assert!(matches!(events.next(), Some(Err(ViperError::Timeout))));
+        assert!(matches!(events.next(), Some(Err(ViperError::Timeout))));
+        drop(events);
+        server.join().unwrap();
+    }
 }
@@ -1,5 +1,7 @@
 use serde::Serialize;
 
+use crate::command_response::FaceRecognitionParams;
+
 const OPEN: [u8; 8] = [0xcd, 0xab, 0x01, 0x00, 0x07, 0x00, 0x00, 0x00];
 const CLOSE: [u8; 8] = [0xef, 0x01, 0x03, 0x00, 0x02, 0x00, 0x00, 0x00];
 
@@ -10,6 +12,7 @@ pub enum CommandKind {
     ActivateUser(String),
     INFO,
     FRCG,
+    FRCGSet(FaceRecognitionParams),
 }
 
 pub struct Command {}
@@ -50,6 +53,14 @@ struct UCFG {
     addressbooks: String,
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct SetFaceRecognition {
+    #[serde(flatten)]
+    base: Base,
+    params: FaceRecognitionParams,
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "kebab-case")]
 struct RemoveAllUsers {
@@ -100,6 +111,18 @@ impl Command {
                 serde_json::to_string(&frcg).unwrap()
             }
 
+            // Named after the `rcg-get-params` request, like the other pairs of
+            // the protocol. Not confirmed by a capture: a bridge not knowing the
+            // request replies with an error code, returned as a `ProtocolError`.
+            CommandKind::FRCGSet(params) => {
+                let frcg = SetFaceRecognition {
+                    base: Base::request("rcg-set-params", 121),
+                    params,
+                };
+
+                serde_json::to_string(&frcg).unwrap()
+            }
+
             CommandKind::RemoveAllUsers(requester) => {
                 let fact = RemoveAllUsers {
                     base: Base::request("remove-all-users", 1),
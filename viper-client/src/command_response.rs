@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
+use std::fmt::{self, Display};
+
+use crate::ViperError;
 
 #[allow(dead_code)]
 #[derive(Deserialize, Serialize, Debug)]
@@ -9,7 +12,7 @@ pub struct BaseResponse {
     pub message: String,
     pub message_type: String,
     pub message_id: u8,
-    pub response_code: u8,
+    pub response_code: u16,
     pub response_string: String,
 }
 
@@ -45,6 +48,45 @@ pub struct ActivateUserResponse {
     pub response: BaseResponse,
 }
 
+// Face recognition settings of the door stations that support it. The
+// parameters not modelled here are kept as they are, so that the params read
+// with `face_recognition_params` can be written back with
+// `set_face_recognition_params` after changing some of them.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct FaceRecognitionParams {
+    // Face recognition turned on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    // Minimum similarity for a face to be recognized, in percent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<u8>,
+
+    #[serde(flatten)]
+    pub other: HashMap<String, Value>,
+}
+
+impl FaceRecognitionParams {
+    // Change a parameter by its name in the JSON, checking the type of the
+    // parameters modelled above
+    pub fn set(&mut self, name: &str, value: Value) -> Result<(), ViperError> {
+        let mut params = serde_json::to_value(&*self).map_err(ViperError::JSONError)?;
+        params[name] = value;
+        *self = serde_json::from_value(params).map_err(ViperError::JSONError)?;
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct FaceRecognitionResponse {
+    #[serde(default)]
+    pub params: FaceRecognitionParams,
+
+    #[serde(flatten)]
+    pub response: BaseResponse,
+}
+
 #[allow(dead_code)]
 #[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "kebab-case")]
@@ -150,6 +192,228 @@ pub struct UserParametersResponse {
     pub additional_actuator: Vec<Actuator>,
 }
 
+impl UserParametersResponse {
+    pub fn doors(&self) -> Vec<AddressBookEntry> {
+        self.opendoor_address_book.iter().map(Into::into).collect()
+    }
+
+    pub fn actuators(&self) -> Vec<AddressBookEntry> {
+        self.actuator_address_book
+            .iter()
+            .chain(&self.additional_actuator)
+            .map(Into::into)
+            .collect()
+    }
+
+    pub fn entrances(&self) -> Vec<AddressBookEntry> {
+        self.entrance_address_book.iter().map(Into::into).collect()
+    }
+
+    pub fn cameras(&self) -> Vec<AddressBookEntry> {
+        self.camera_address_book
+            .iter()
+            .chain(&self.rtsp_camera_address_book)
+            .map(AddressBookEntry::from_camera)
+            .collect()
+    }
+
+    // The door stations (VIP units) of the installation, one per apt address:
+    // the entrances, then the cameras, doors and actuators at another address.
+    // A station is named after the first entry found at its address.
+    pub fn stations(&self) -> Vec<AddressBookEntry> {
+        let mut stations: Vec<AddressBookEntry> = Vec::new();
+        let entries = self
+            .entrances()
+            .into_iter()
+            .chain(self.cameras())
+            .chain(self.doors())
+            .chain(self.actuators());
+        for mut entry in entries {
+            if !entry.apt_address.is_empty()
+                && !stations.iter().any(|s| s.apt_address == entry.apt_address)
+            {
+                entry.output_index = None;
+                stations.push(entry);
+            }
+        }
+        stations
+    }
+
+    // The station an event comes from, given the address of the event
+    pub fn station_of(&self, from: &str) -> Option<AddressBookEntry> {
+        find_station(&self.stations(), from).cloned()
+    }
+}
+
+// Addresses in events are the address of the station followed by a subaddress
+pub(crate) fn find_station<'a>(
+    stations: &'a [AddressBookEntry],
+    from: &str,
+) -> Option<&'a AddressBookEntry> {
+    stations
+        .iter()
+        .find(|s| !s.apt_address.is_empty() && from.starts_with(&s.apt_address))
+}
+
+// An entry of the address books, as listed by `ViperClient::list_doors`,
+// `list_actuators` and `list_cameras`
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct AddressBookEntry {
+    pub id: String,
+    pub name: String,
+    pub apt_address: String,
+    // Output of the door or actuator, not set for cameras
+    pub output_index: Option<u8>,
+}
+
+impl AddressBookEntry {
+    // Cameras are not typed in the configuration, read the common fields
+    fn from_camera(camera: &HashMap<String, Value>) -> AddressBookEntry {
+        let field = |key: &str| match camera.get(key) {
+            Some(Value::String(s)) => s.clone(),
+            Some(Value::Null) | None => String::new(),
+            Some(value) => value.to_string(),
+        };
+
+        AddressBookEntry {
+            id: field("id"),
+            name: field("name"),
+            apt_address: field("apt-address"),
+            output_index: None,
+        }
+    }
+}
+
+impl Display for AddressBookEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}", self.name, self.apt_address)?;
+        if let Some(output_index) = self.output_index {
+            write!(f, "/{}", output_index)?;
+        }
+        write!(f, ")")
+    }
+}
+
+// Selects an entry of an address book
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntrySelector {
+    // Position in the address book, starting from 0
+    Index(usize),
+    // The `id` of the entry
+    Id(String),
+    // The apt address and output of the entry
+    Address {
+        apt_address: String,
+        output_index: u8,
+    },
+    // The first entry of the station with this apt address, e.g. the door
+    // of the station calling
+    Station(String),
+    // The display name of the entry, ignoring case. Names are not unique, so
+    // it fails when several entries have the name.
+    Name(String),
+}
+
+impl EntrySelector {
+    // Find the selected entry, or fail listing the available ones
+    pub fn find<'a>(
+        &self,
+        kind: &str,
+        entries: &'a [AddressBookEntry],
+    ) -> Result<&'a AddressBookEntry, ViperError> {
+        let available = || {
+            let available: Vec<String> = entries
+                .iter()
+                .enumerate()
+                .map(|(i, e)| format!("[{}] {}", i, e))
+                .collect();
+            if available.is_empty() {
+                String::from("none configured")
+            } else {
+                available.join(", ")
+            }
+        };
+        let found = match self {
+            EntrySelector::Index(index) => entries.get(*index),
+            EntrySelector::Id(id) => entries.iter().find(|e| &e.id == id),
+            EntrySelector::Address {
+                apt_address,
+                output_index,
+            } => entries
+                .iter()
+                .find(|e| &e.apt_address == apt_address && e.output_index == Some(*output_index)),
+            EntrySelector::Station(apt_address) => {
+                entries.iter().find(|e| &e.apt_address == apt_address)
+            }
+            EntrySelector::Name(name) => {
+                let mut named = entries.iter().filter(|e| e.name.eq_ignore_ascii_case(name));
+                let first = named.next();
+                if first.is_some() && named.next().is_some() {
+                    return Err(ViperError::NotFound(format!(
+                        "single {} {}, available: {}",
+                        kind,
+                        self,
+                        available()
+                    )));
+                }
+                first
+            }
+        };
+        found.ok_or_else(|| {
+            ViperError::NotFound(format!("{} {}, available: {}", kind, self, available()))
+        })
+    }
+}
+
+impl Display for EntrySelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EntrySelector::Index(index) => write!(f, "#{}", index),
+            EntrySelector::Id(id) => write!(f, "with id {}", id),
+            EntrySelector::Address {
+                apt_address,
+                output_index,
+            } => write!(f, "at {}/{}", apt_address, output_index),
+            EntrySelector::Station(apt_address) => write!(f, "of station {}", apt_address),
+            EntrySelector::Name(name) => write!(f, "named {}", name),
+        }
+    }
+}
+
+impl From<&Opendoor> for AddressBookEntry {
+    fn from(door: &Opendoor) -> Self {
+        AddressBookEntry {
+            id: door.id.to_string(),
+            name: door.name.clone(),
+            apt_address: door.apt_address.clone(),
+            output_index: Some(door.output_index),
+        }
+    }
+}
+
+impl From<&Entrance> for AddressBookEntry {
+    fn from(entrance: &Entrance) -> Self {
+        AddressBookEntry {
+            id: entrance.id.clone(),
+            name: entrance.name.clone(),
+            apt_address: entrance.apt_address.clone(),
+            output_index: None,
+        }
+    }
+}
+
+impl From<&Actuator> for AddressBookEntry {
+    fn from(actuator: &Actuator) -> Self {
+        AddressBookEntry {
+            id: actuator.id.clone(),
+            name: actuator.name.clone(),
+            apt_address: actuator.apt_address.clone(),
+            output_index: Some(actuator.output_index),
+        }
+    }
+}
+
 #[allow(dead_code)]
 #[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "kebab-case")]
@@ -173,3 +437,155 @@ pub struct ConfigurationResponse {
     #[serde(flatten)]
     pub response: BaseResponse,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_address_books() {
+        let params: UserParametersResponse = serde_json::from_value(serde_json::json!({
+            "forced": false,
+            "opendoor-address-book": [
+                { "id": 1, "name": "Gate", "apt-address": "SB100001", "output-index": 2, "secure-mode": false }
+            ],
+            "actuator-address-book": [
+                { "id": "3", "name": "Garage", "apt-address": "SB200001", "module-index": 0, "output-index": 1 }
+            ],
+            "camera-address-book": [
+                { "id": 7, "name": "Entrance", "apt-address": "SB000001" }
+            ]
+        }))
+        .unwrap();
+
+        assert_eq!(
+            params.doors(),
+            vec![AddressBookEntry {
+                id: String::from("1"),
+                name: String::from("Gate"),
+                apt_address: String::from("SB100001"),
+                output_index: Some(2),
+            }]
+        );
+        assert_eq!(params.actuators()[0].name, "Garage");
+        assert_eq!(params.actuators()[0].output_index, Some(1));
+        assert_eq!(
+            params.cameras(),
+            vec![AddressBookEntry {
+                id: String::from("7"),
+                name: String::from("Entrance"),
+                apt_address: String::from("SB000001"),
+                output_index: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_face_recognition_params() {
+        let response: FaceRecognitionResponse = serde_json::from_value(serde_json::json!({
+            "message": "rcg-get-params",
+            "message-type": "response",
+            "message-id": 121,
+            "response-code": 200,
+            "response-string": "OK",
+            "params": { "enabled": false, "threshold": 80, "max-faces": 10 }
+        }))
+        .unwrap();
+        let mut params = response.params;
+        assert_eq!(params.enabled, Some(false));
+        assert_eq!(params.threshold, Some(80));
+
+        // Unknown parameters are written back unchanged
+        params.enabled = Some(true);
+        params.set("threshold", serde_json::json!(90)).unwrap();
+        assert_eq!(
+            serde_json::to_value(&params).unwrap(),
+            serde_json::json!({ "enabled": true, "threshold": 90, "max-faces": 10 })
+        );
+        assert!(params.set("threshold", serde_json::json!("high")).is_err());
+        assert_eq!(params.threshold, Some(90));
+    }
+
+    #[test]
+    fn test_stations() {
+        let params: UserParametersResponse = serde_json::from_value(serde_json::json!({
+            "forced": false,
+            "opendoor-address-book": [
+                { "id": 1, "name": "Gate", "apt-address": "SB100001", "output-index": 2, "secure-mode": false },
+                { "id": 2, "name": "Back door", "apt-address": "SB100002", "output-index": 1, "secure-mode": false },
+                { "id": 3, "name": "Side gate", "apt-address": "SB100003", "output-index": 1, "secure-mode": false }
+            ],
+            "entrance-address-book": [
+                { "id": "5", "name": "Street", "apt-address": "SB100001" }
+            ],
+            "camera-address-book": [
+                { "id": 7, "name": "Street camera", "apt-address": "SB100001" },
+                { "id": 8, "name": "Garden", "apt-address": "SB100002" }
+            ]
+        }))
+        .unwrap();
+
+        let stations = params.stations();
+        assert_eq!(
+            stations.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(),
+            vec!["Street", "Garden", "Side gate"]
+        );
+        // Stations are not outputs
+        assert_eq!(stations[2].output_index, None);
+        assert_eq!(params.station_of("SB1000021").unwrap().name, "Garden");
+        assert_eq!(params.station_of("SB0000062"), None);
+
+        let doors = params.doors();
+        let door = EntrySelector::Station(String::from("SB100002"))
+            .find("door", &doors)
+            .unwrap();
+        assert_eq!(door.name, "Back door");
+        let door = EntrySelector::Name(String::from("back door"))
+            .find("door", &doors)
+            .unwrap();
+        assert_eq!(door.apt_address, "SB100002");
+    }
+
+    #[test]
+    fn test_entry_selector() {
+        let entries = vec![
+            AddressBookEntry {
+                id: String::from("1"),
+                name: String::from("Gate"),
+                apt_address: String::from("SB100001"),
+                output_index: Some(2),
+            },
+            AddressBookEntry {
+                id: String::from("2"),
+                name: String::from("Gate"),
+                apt_address: String::from("SB100001"),
+                output_index: Some(3),
+            },
+        ];
+
+        let find = |selector: EntrySelector| selector.find("door", &entries).map(|e| e.id.clone());
+        assert_eq!(find(EntrySelector::Index(1)).unwrap(), "2");
+        assert_eq!(find(EntrySelector::Id(String::from("1"))).unwrap(), "1");
+        assert_eq!(
+            find(EntrySelector::Address {
+                apt_address: String::from("SB100001"),
+                output_index: 3,
+            })
+            .unwrap(),
+            "2"
+        );
+
+        assert_eq!(
+            find(EntrySelector::Name(String::from("gate")))
+                .unwrap_err()
+                .to_string(),
+            "Not found: single door named gate, available: [0] Gate (SB100001/2), [1] Gate (SB100001/3)"
+        );
+
+        let error = find(EntrySelector::Index(5)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Not found: door #5, available: [0] Gate (SB100001/2), [1] Gate (SB100001/3)"
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ViperError;
+
+// User activated on a bridge with `ViperClient::sign_up`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub email: Option<String>,
+    pub token: String,
+}
+
+// Credentials saved in a JSON file, by bridge address (ip:port), so that the
+// activation on the bridge is only done once. The file is only readable by its
+// owner, since the token gives access to the intercom.
+#[derive(Debug, Clone)]
+pub struct CredentialStore {
+    path: PathBuf,
+}
+
+impl CredentialStore {
+    pub fn new(path: impl Into<PathBuf>) -> CredentialStore {
+        CredentialStore { path: path.into() }
+    }
+
+    // $XDG_CONFIG_HOME/viper/credentials.json, or ~/.config/viper/credentials.json
+    pub fn default_path() -> Option<PathBuf> {
+        Some(config_dir()?.join("credentials.json"))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn load(&self, bridge: &str) -> Result<Option<Credentials>, ViperError> {
+        Ok(self.read()?.remove(bridge))
+    }
+
+    pub fn save(&self, bridge: &str, credentials: &Credentials) -> Result<(), ViperError> {
+        let mut all = self.read()?;
+        all.insert(bridge.to_string(), credentials.clone());
+        self.write(&all)
+    }
+
+    // Forget the credentials of a bridge, e.g. after the user was removed from it
+    pub fn remove(&self, bridge: &str) -> Result<(), ViperError> {
+        let mut all = self.read()?;
+        if all.remove(bridge).is_some() {
+            self.write(&all)?;
+        }
+        Ok(())
+    }
+
+    fn read(&self) -> Result<HashMap<String, Credentials>, ViperError> {
+        match fs::read(&self.path) {
+            Ok(content) => serde_json::from_slice(&content).map_err(ViperError::JSONError),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(HashMap::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn write(&self, all: &HashMap<String, Credentials>) -> Result<(), ViperError> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let content = serde_json::to_vec_pretty(all).map_err(ViperError::JSONError)?;
+        // Write the file with restricted permissions, then move it in place
+        let tmp = self.path.with_extension("json.tmp");
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        io::Write::write_all(&mut options.open(&tmp)?, &content)?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+// Directory of the files saved by the client, $XDG_CONFIG_HOME/viper or ~/.config/viper
+pub(crate) fn config_dir() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_dir.join("viper"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("viper-credentials-{}", std::process::id()));
+        let store = CredentialStore::new(dir.join("credentials.json"));
+        assert_eq!(store.load("192.168.1.20:64100").unwrap(), None);
+
+        let credentials = Credentials {
+            email: Some(String::from("me@example.com")),
+            token: String::from("TESTTOKEN"),
+        };
+        store.save("192.168.1.20:64100", &credentials).unwrap();
+        store
+            .save(
+                "192.168.1.21:64100",
+                &Credentials {
+                    email: None,
+                    token: String::from("OTHERTOKEN"),
+                },
+            )
+            .unwrap();
+        assert_eq!(store.load("192.168.1.20:64100").unwrap(), Some(credentials));
+
+        store.remove("192.168.1.21:64100").unwrap();
+        assert_eq!(store.load("192.168.1.21:64100").unwrap(), None);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(store.path()).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -39,6 +39,10 @@ impl CTPPChannel {
         }
     }
 
+    pub fn control(&self) -> &[u8; 2] {
+        &self.control
+    }
+
     pub fn open(&self, sub: &String) -> Vec<u8> {
         Command::channel(&String::from("CTPP"), &self.control, Some(sub.as_bytes()))
     }
@@ -68,9 +72,10 @@ impl CTPPChannel {
     }
 
     pub fn confirm(&self, r: &[u8]) -> bool {
-        self.bitmask[0] + 0x80 == r[2]
+        r.len() >= 6
+            && self.bitmask[0] + 0x80 == r[2]
             && self.bitmask[1] == r[3]
-            && self.bitmask[2] == r[5] - 1
+            && self.bitmask[2] + 1 == r[5]
             && self.bitmask[3] == r[4]
     }
 
@@ -89,6 +94,19 @@ impl CTPPChannel {
         Command::make(&req, &self.control)
     }
 
+    // Sent periodically on a long-lived channel, so the bridge doesn't drop it
+    // when idle. It repeats the confirmation of the last frame.
+    pub fn keepalive(&self, a1: &String, a2: &String) -> Vec<u8> {
+        let mut req = [&ACK_TEMPLATE[..], &TAIL_TEMPLATE[..]].concat();
+
+        CTPPChannel::set_bytes(&mut req, &[0x20], 0);
+        CTPPChannel::set_bytes(&mut req, &self.bitmask, 2);
+        CTPPChannel::set_bytes(&mut req, a1.as_bytes(), 12);
+        CTPPChannel::set_bytes(&mut req, a2.as_bytes(), 22);
+
+        Command::make(&req, &self.control)
+    }
+
     pub fn link_actuators(&mut self, a1: &String, a2: &String) -> Vec<u8> {
         let mut req = [&OPEN_DOOR_TEMPLATE[..], &TAIL_TEMPLATE[..]].concat();
 
@@ -105,8 +123,9 @@ impl CTPPChannel {
         Command::make(&req, &self.control)
     }
 
+    // Wraps around on long-lived channels, e.g. while listening for events
     fn tick_mask(&mut self) {
-        self.bitmask[3] += 1;
+        self.bitmask[3] = self.bitmask[3].wrapping_add(1);
     }
 
     fn set_bytes(template: &mut [u8], bytes: &[u8], offset: usize) {
@@ -173,6 +192,19 @@ mod tests {
         assert_eq!(&conn_2[10..14], &conn[10..14]);
     }
 
+    #[test]
+    fn test_keepalive() {
+        let mut ctpp = CTPPChannel::new(&[1, 2]);
+        let sub = String::from("SB0000062");
+        let addr = String::from("SB000006");
+        ctpp.ack(0x00, &sub, &addr);
+        let ack = ctpp.ack(0x20, &sub, &addr);
+
+        // The keepalive repeats the last ack, without moving the mask
+        assert_eq!(ctpp.keepalive(&sub, &addr), ack);
+        assert_eq!(ctpp.keepalive(&sub, &addr), ack);
+    }
+
     #[test]
     fn test_confirm_handshake() {
         let ctpp = CTPPChannel {
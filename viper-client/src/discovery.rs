@@ -0,0 +1,103 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::ViperError;
+use crate::client::ViperClient;
+use crate::credentials::config_dir;
+use crate::device::Device;
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CachedAddress {
+    ip: String,
+    port: u16,
+}
+
+// Address of the last bridge found by `ViperClient::scan`, saved as JSON so
+// that the next runs connect directly, also where broadcasts are blocked
+#[derive(Debug, Clone)]
+pub struct DiscoveryCache {
+    path: PathBuf,
+}
+
+impl DiscoveryCache {
+    pub fn new(path: impl Into<PathBuf>) -> DiscoveryCache {
+        DiscoveryCache { path: path.into() }
+    }
+
+    // $XDG_CONFIG_HOME/viper/bridge.json, or ~/.config/viper/bridge.json
+    pub fn default_path() -> Option<PathBuf> {
+        Some(config_dir()?.join("bridge.json"))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn load(&self) -> Option<(String, u16)> {
+        let content = fs::read(&self.path).ok()?;
+        let cached: CachedAddress = serde_json::from_slice(&content).ok()?;
+        Some((cached.ip, cached.port))
+    }
+
+    pub fn save(&self, ip: &str, port: u16) -> Result<(), ViperError> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let cached = CachedAddress {
+            ip: ip.to_string(),
+            port,
+        };
+        let content = serde_json::to_vec_pretty(&cached).map_err(ViperError::JSONError)?;
+        fs::write(&self.path, content)?;
+        Ok(())
+    }
+
+    // The cached address while the bridge answers on it, scanning otherwise
+    pub async fn locate(&self) -> Option<(String, u16)> {
+        if let Some((ip, port)) = self.load() {
+            if Device::poll(&ip, port) {
+                return Some((ip, port));
+            }
+            debug!("Bridge not answering at {}:{}, scanning", ip, port);
+        }
+        self.rescan().await
+    }
+
+    // Scan the network and cache the address found
+    pub async fn rescan(&self) -> Option<(String, u16)> {
+        let (ip, port) = ViperClient::scan().await?;
+        if let Err(e) = self.save(&ip, port) {
+            warn!(
+                "Failed to cache the bridge address in {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+        Some((ip, port))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helper::SimpleTcpListener;
+
+    #[tokio::test]
+    async fn test_locate_cached() {
+        let _listener = SimpleTcpListener::new("127.0.0.1:3352");
+        let dir = std::env::temp_dir().join(format!("viper-discovery-{}", std::process::id()));
+        let cache = DiscoveryCache::new(dir.join("bridge.json"));
+        assert_eq!(cache.load(), None);
+
+        cache.save("127.0.0.1", 3352).unwrap();
+        // The bridge answers at the cached address, no scan needed
+        assert_eq!(
+            cache.locate().await,
+            Some((String::from("127.0.0.1"), 3352))
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,234 @@
+use crate::ViperError;
+use crate::webhook::Webhook;
+use std::io;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::client::ViperClient;
+use crate::ctpp_channel::CTPPChannel;
+
+// Action codes of the frames pushed by the bridge on an open CTPP channel
+// (bytes 6 and 7, like 0x0011 for the handshake). The door openings are not
+// decoded: no capture shows the frame pushed when a door is opened elsewhere.
+const ACTION_CALL: u16 = 0x0018;
+const ACTION_HANG_UP: u16 = 0x002d;
+
+// Addresses are NUL terminated strings like SB0000062
+const MIN_ADDRESS_LEN: usize = 4;
+
+// Shortest read timeout, a zero timeout is rejected by the socket
+const MIN_WAIT: Duration = Duration::from_millis(1);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ViperEvent {
+    // Someone rang from the given door station
+    IncomingCall { from: String },
+    // The door station hung up, or the call was answered elsewhere
+    CallEnded { from: String },
+}
+
+impl ViperEvent {
+    // Address of the station or apartment the event comes from
+    pub fn source(&self) -> &str {
+        match self {
+            ViperEvent::IncomingCall { from } | ViperEvent::CallEnded { from } => from,
+        }
+    }
+
+    // Parse an unsolicited CTPP frame, `None` for acks and unknown frames
+    pub fn parse(frame: &[u8]) -> Option<ViperEvent> {
+        if frame.len() < 8 || frame[1] != 0x18 {
+            return None;
+        }
+        let action = u16::from_be_bytes([frame[6], frame[7]]);
+        let from = addresses(&frame[8..])
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+        match action {
+            ACTION_CALL => Some(ViperEvent::IncomingCall { from }),
+            ACTION_HANG_UP => Some(ViperEvent::CallEnded { from }),
+            _ => None,
+        }
+    }
+}
+
+// The addresses contained in a frame, in order
+fn addresses(bytes: &[u8]) -> Vec<String> {
+    bytes
+        .split(|b| !b.is_ascii_alphanumeric())
+        .filter(|s| s.len() >= MIN_ADDRESS_LEN)
+        .map(|s| String::from_utf8_lossy(s).into_owned())
+        .collect()
+}
+
+// Iterator over the events of a CTPP channel kept open by
+// `ViperClient::listen_events`. Blocks until the next event arrives, sending
+// the keepalives meanwhile; the channel is closed when the listener is dropped.
+pub struct EventListener<'a> {
+    client: &'a mut ViperClient,
+    channel: CTPPChannel,
+    sub: String,
+    addr: String,
+    // When the next keepalive is due
+    keepalive: Instant,
+    webhook: Option<Webhook>,
+    // Time to wait for the next event, forever when `None`
+    timeout: Option<Duration>,
+}
+
+impl<'a> EventListener<'a> {
+    pub(crate) fn new(
+        client: &'a mut ViperClient,
+        channel: CTPPChannel,
+        sub: String,
+        addr: String,
+        webhook: Option<Webhook>,
+    ) -> Result<EventListener<'a>, ViperError> {
+        let keepalive = Instant::now() + client.stream.config().keepalive_interval;
+        Ok(EventListener {
+            client,
+            channel,
+            sub,
+            addr,
+            keepalive,
+            webhook,
+            timeout: None,
+        })
+    }
+
+    // Stop waiting for the next event after the given time: the iteration
+    // then yields `ViperError::Timeout` and can go on, e.g. after checking
+    // whether the listener should stop
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    // Run other commands while the channel stays open, e.g. to open the door
+    // of the station calling. The events received meanwhile are queued for the
+    // next iterations. A command reconnecting after an error closes the channel.
+    pub fn with_client<T>(
+        &mut self,
+        command: impl FnOnce(&mut ViperClient) -> T,
+    ) -> Result<T, ViperError> {
+        self.client.stream.set_read_timeout(true)?;
+        Ok(command(self.client))
+    }
+
+    // Send the keepalive when due, built from the current state of the channel
+    // since the acks move its mask. Returns how long to wait for the next one.
+    fn keepalive(&mut self) -> Result<Duration, io::Error> {
+        let now = Instant::now();
+        if now >= self.keepalive {
+            let frame = self.channel.keepalive(&self.sub, &self.addr);
+            self.client.stream.write(&frame)?;
+            self.keepalive = now + self.client.stream.config().keepalive_interval;
+        }
+        Ok(self.keepalive.saturating_duration_since(now))
+    }
+}
+
+impl Iterator for EventListener<'_> {
+    type Item = Result<ViperEvent, ViperError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            // Everything is written from here, so the keepalives can't be
+            // interleaved with the acks
+            let mut wait = match self.keepalive() {
+                Ok(wait) => wait,
+                Err(e) => return Some(Err(e.into())),
+            };
+            if let Some(deadline) = deadline {
+                let now = Instant::now();
+                if now >= deadline {
+                    return Some(Err(ViperError::Timeout));
+                }
+                wait = wait.min(deadline - now);
+            }
+            let read = self
+                .client
+                .stream
+                .set_timeout(Some(wait.max(MIN_WAIT)))
+                .and_then(|_| self.client.stream.read_on(self.channel.control()));
+            let frame = match read {
+                Ok(frame) => frame,
+                Err(e) if is_timeout(&e) => continue,
+                Err(e) => return Some(Err(e.into())),
+            };
+            if let Some(event) = ViperEvent::parse(&frame) {
+                // Confirm the frame, or the bridge sends it again
+                let ack = self.channel.ack(0x20, &self.sub, &self.addr);
+                if let Err(e) = self.client.stream.write(&ack) {
+                    return Some(Err(e.into()));
+                }
+                if let Some(webhook) = &self.webhook {
+                    webhook.notify(&event);
+                }
+                return Some(Ok(event));
+            }
+        }
+    }
+}
+
+fn is_timeout(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
+
+impl Drop for EventListener<'_> {
+    fn drop(&mut self) {
+        let _ = self.client.stream.set_read_timeout(true);
+        let _ = self.client.stream.write(&self.channel.close());
+        self.client.stream.discard(self.channel.control());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(action: u16, from: &str) -> Vec<u8> {
+        let mut frame = vec![0xc0, 0x18, 0x11, 0x22, 0x33, 0x44];
+        frame.extend_from_slice(&action.to_be_bytes());
+        frame.extend_from_slice(&[0x00, 0x01]);
+        frame.extend_from_slice(from.as_bytes());
+        frame.push(0x00);
+        frame.extend_from_slice(b"SB000006");
+        frame.extend_from_slice(&[0x00, 0x00]);
+        frame
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            ViperEvent::parse(&frame(ACTION_CALL, "SB0000062")),
+            Some(ViperEvent::IncomingCall {
+                from: String::from("SB0000062")
+            })
+        );
+        assert_eq!(
+            ViperEvent::parse(&frame(ACTION_HANG_UP, "SB0000062")),
+            Some(ViperEvent::CallEnded {
+                from: String::from("SB0000062")
+            })
+        );
+        // Acks and unknown actions, like the link of the actuators, are not events
+        assert_eq!(ViperEvent::parse(&frame(0x0011, "SB0000062")), None);
+        assert_eq!(ViperEvent::parse(&frame(0x0028, "SB0000062")), None);
+        assert_eq!(ViperEvent::parse(&[0x60, 0x18, 0xc2]), None);
+    }
+
+    #[test]
+    fn test_addresses() {
+        assert_eq!(
+            addresses(b"\x00\x01SB0000062\x00SB000006\x00\x00"),
+            vec!["SB0000062", "SB000006"]
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+
+use crate::bridge::{EventStream, ViperBridge};
+use crate::command_response::{
+    AddressBookEntry, AuthResponse, ConfigurationResponse, EntrySelector, FaceRecognitionParams,
+    FaceRecognitionResponse, InfoResponse, VipResponse,
+};
+use crate::events::ViperEvent;
+use crate::{JSONResult, ViperError};
+
+// In-memory `ViperBridge` serving canned responses, like `SimpleTcpListener`
+// does for the tests of the client but at the API level. The replies are the
+// JSON sent by a real bridge, e.g. saved with `viper config --json`.
+#[derive(Debug, Default)]
+pub struct FakeViperBridge {
+    // Token accepted by `authorize`, any token when not set
+    pub token: Option<String>,
+    pub configuration: Option<Value>,
+    pub info: Option<Value>,
+    pub face_recognition: Option<Value>,
+    // Events returned by `listen_events`, the stream ends after the last one
+    pub events: VecDeque<ViperEvent>,
+    // Doors and actuators opened, in order
+    pub opened: Vec<AddressBookEntry>,
+}
+
+impl FakeViperBridge {
+    pub fn new(configuration: Value) -> FakeViperBridge {
+        FakeViperBridge {
+            configuration: Some(configuration),
+            ..FakeViperBridge::default()
+        }
+    }
+
+    fn reply<T: DeserializeOwned>(reply: &Option<Value>, name: &str) -> JSONResult<T> {
+        match reply {
+            Some(value) => serde_json::from_value(value.clone()).map_err(ViperError::JSONError),
+            None => Err(ViperError::NotFound(format!("{} reply", name))),
+        }
+    }
+}
+
+impl ViperBridge for FakeViperBridge {
+    fn authorize(&mut self, token: &str) -> JSONResult<AuthResponse> {
+        if self.token.as_ref().is_some_and(|t| t != token) {
+            return Err(ViperError::AuthError { code: 401 });
+        }
+        let reply = json!({
+            "message": "access",
+            "message-type": "response",
+            "message-id": 1,
+            "response-code": 200,
+            "response-string": "Access Granted"
+        });
+        FakeViperBridge::reply(&Some(reply), "access")
+    }
+
+    fn configuration(&mut self, _addressbooks: &str) -> JSONResult<ConfigurationResponse> {
+        FakeViperBridge::reply(&self.configuration, "configuration")
+    }
+
+    fn info(&mut self) -> JSONResult<InfoResponse> {
+        FakeViperBridge::reply(&self.info, "info")
+    }
+
+    fn face_recognition_params(&mut self) -> JSONResult<FaceRecognitionResponse> {
+        FakeViperBridge::reply(&self.face_recognition, "face recognition")
+    }
+
+    fn set_face_recognition_params(&mut self, params: &FaceRecognitionParams) -> JSONResult<()> {
+        let reply = self.face_recognition.get_or_insert_with(|| {
+            json!({
+                "message": "rcg-get-params",
+                "message-type": "response",
+                "message-id": 121,
+                "response-code": 200,
+                "response-string": "OK"
+            })
+        });
+        reply["params"] = serde_json::to_value(params).map_err(ViperError::JSONError)?;
+        Ok(())
+    }
+
+    fn open_door_by(&mut self, vip: &VipResponse, selector: &EntrySelector) -> JSONResult<()> {
+        let doors = vip.user_parameters.doors();
+        let door = selector.find("door", &doors)?;
+        self.opened.push(door.clone());
+        Ok(())
+    }
+
+    fn open_actuator_by(&mut self, vip: &VipResponse, selector: &EntrySelector) -> JSONResult<()> {
+        let actuators = vip.user_parameters.actuators();
+        let actuator = selector.find("actuator", &actuators)?;
+        self.opened.push(actuator.clone());
+        Ok(())
+    }
+
+    fn listen_events(&mut self, _vip: &VipResponse) -> JSONResult<EventStream<'_>> {
+        Ok(Box::new(
+            std::mem::take(&mut self.events).into_iter().map(Ok),
+        ))
+    }
+
+    fn listen_events_timeout(
+        &mut self,
+        vip: &VipResponse,
+        _timeout: Duration,
+    ) -> JSONResult<EventStream<'_>> {
+        self.listen_events(vip)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn configuration() -> Value {
+        json!({
+            "message": "get-configuration",
+            "message-type": "response",
+            "message-id": 2,
+            "response-code": 200,
+            "response-string": "OK",
+            "viper-server": {
+                "local-address": "192.168.1.20",
+                "local-tcp-port": 64100,
+                "local-udp-port": 64100,
+                "remote-address": "",
+                "remote-tcp-port": 64100,
+                "remote-udp-port": 64100
+            },
+            "viper-client": { "description": "SU0001" },
+            "vip": {
+                "enabled": true,
+                "apt-address": "SB000006",
+                "apt-subaddress": 2,
+                "logical-subaddress": 2,
+                "apt-config": {
+                    "description": "",
+                    "call-divert-busy-en": false,
+                    "call-divert-address": "",
+                    "virtual-key-enabled": false
+                },
+                "user-parameters": {
+                    "forced": false,
+                    "opendoor-address-book": [
+                        { "id": 1, "name": "Gate", "apt-address": "SB100001", "output-index": 2, "secure-mode": false }
+                    ]
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn test_fake_bridge() {
+        let mut bridge = FakeViperBridge {
+            token: Some(String::from("TESTTOKEN")),
+            events: VecDeque::from([ViperEvent::IncomingCall {
+                from: String::from("SB1000011"),
+            }]),
+            ..FakeViperBridge::new(configuration())
+        };
+
+        assert!(matches!(
+            bridge.authorize("WRONG"),
+            Err(ViperError::AuthError { code: 401 })
+        ));
+        bridge.authorize("TESTTOKEN").unwrap();
+        assert!(matches!(bridge.info(), Err(ViperError::NotFound(_))));
+
+        let vip = bridge.configuration("all").unwrap().vip;
+        bridge.open_door_by(&vip, &EntrySelector::Index(0)).unwrap();
+        assert_eq!(bridge.opened[0].name, "Gate");
+
+        let events: Vec<ViperEvent> = bridge
+            .listen_events(&vip)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(events.len(), 1);
+
+        let params = FaceRecognitionParams {
+            enabled: Some(true),
+            ..FaceRecognitionParams::default()
+        };
+        bridge.set_face_recognition_params(&params).unwrap();
+        assert_eq!(
+            bridge.face_recognition_params().unwrap().params.enabled,
+            Some(true)
+        );
+    }
+}
@@ -1,13 +1,22 @@
+pub mod bridge;
 mod channel;
 mod client;
 pub mod command;
 pub mod command_response;
+pub mod credentials;
 mod ctpp_channel;
 pub mod device;
+pub mod discovery;
+pub mod events;
+pub mod fake;
 mod helper;
+#[cfg(feature = "unstable")]
+pub mod raw;
 mod stream_wrapper;
+mod webhook;
 
-pub use client::{ICONA_BRIDGE_PORT, ViperClient};
+pub use bridge::ViperBridge;
+pub use client::{ICONA_BRIDGE_PORT, ViperClient, ViperClientConfig};
 
 #[cfg(test)]
 mod test_helper;
@@ -20,6 +29,18 @@ type JSONResult<T> = Result<T, ViperError>;
 pub enum ViperError {
     IOError(io::Error),
     JSONError(serde_json::Error),
+    // The bridge refused to open the named channel
+    ChannelError(String),
+    // The bridge rejected the token (response code 401 or 403)
+    AuthError { code: u16 },
+    // The requested entry (e.g. a door) is not in the configuration
+    NotFound(String),
+    // The bridge did not answer in time
+    Timeout,
+    // Unexpected reply, or a response code other than 200
+    ProtocolError(String),
+    // The bridge did not confirm the activation of the named door or actuator
+    NotConfirmed(String),
 }
 
 impl Display for ViperError {
@@ -27,12 +48,23 @@ impl Display for ViperError {
         match self {
             ViperError::IOError(io_error) => write!(f, "{}", io_error),
             ViperError::JSONError(json_error) => write!(f, "{}", json_error),
+            ViperError::ChannelError(channel) => write!(f, "Channel {} refused", channel),
+            ViperError::AuthError { code } => write!(f, "Authorization failed ({})", code),
+            ViperError::NotFound(what) => write!(f, "Not found: {}", what),
+            ViperError::Timeout => write!(f, "Timed out waiting for the bridge"),
+            ViperError::ProtocolError(message) => write!(f, "Protocol error: {}", message),
+            ViperError::NotConfirmed(name) => write!(f, "{} not confirmed by the bridge", name),
         }
     }
 }
 
+impl std::error::Error for ViperError {}
+
 impl From<io::Error> for ViperError {
     fn from(error: io::Error) -> Self {
-        ViperError::IOError(error)
+        match error.kind() {
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => ViperError::Timeout,
+            _ => ViperError::IOError(error),
+        }
     }
 }
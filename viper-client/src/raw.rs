@@ -0,0 +1,59 @@
+use crate::JSONResult;
+use crate::client::ViperClient;
+use crate::command::Command;
+
+// A channel opened with `ViperClient::raw_channel`, sending and reading the
+// frames as they are. Meant to experiment with undocumented ICONA messages:
+// this API is unstable and may change with the protocol support.
+pub struct RawChannel<'a> {
+    client: &'a mut ViperClient,
+    control: [u8; 2],
+    closed: bool,
+}
+
+impl<'a> RawChannel<'a> {
+    pub(crate) fn new(client: &'a mut ViperClient, control: [u8; 2]) -> RawChannel<'a> {
+        RawChannel {
+            client,
+            control,
+            closed: false,
+        }
+    }
+
+    // Bytes 4 and 5 of the header of the frames on this channel
+    pub fn control(&self) -> &[u8; 2] {
+        &self.control
+    }
+
+    // Send the body, prefixed with the header of the channel
+    pub fn send(&mut self, body: &[u8]) -> JSONResult<()> {
+        self.client
+            .stream
+            .write(&Command::make(body, &self.control))?;
+        Ok(())
+    }
+
+    // Body of the next frame received on this channel, the frames of the other
+    // channels are kept for them
+    pub fn read(&mut self) -> JSONResult<Vec<u8>> {
+        Ok(self.client.stream.read_on(&self.control)?)
+    }
+
+    pub fn close(mut self) -> JSONResult<()> {
+        self.closed = true;
+        self.client
+            .stream
+            .execute_on(&self.control, &Command::close(&self.control))?;
+        self.client.stream.discard(&self.control);
+        Ok(())
+    }
+}
+
+impl Drop for RawChannel<'_> {
+    fn drop(&mut self) {
+        if !self.closed {
+            let _ = self.client.stream.write(&Command::close(&self.control));
+            self.client.stream.discard(&self.control);
+        }
+    }
+}
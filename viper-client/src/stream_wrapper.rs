@@ -1,39 +1,143 @@
+use crate::client::ViperClientConfig;
 use crate::command::Command;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::io;
 use std::io::prelude::*;
-use std::net::{Shutdown, TcpStream};
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
 use std::time::Duration;
 
-const TIMEOUT: u64 = 1000;
-
 type ByteResult = Result<Vec<u8>, io::Error>;
 
+// Control of the frames not addressed to a channel, like the replies to the
+// opening of a channel. They go to whoever is reading.
+const UNADDRESSED: [u8; 2] = [0, 0];
+
+// Bytes before the body of a frame, with its length and control
+const HEADER_LENGTH: usize = 8;
+
+// The connection to the bridge. Several channels can be open on it at once,
+// e.g. the CTPP channel of `listen_events` while a door is opened: frames are
+// routed by control and queued until their channel is read. Reading and
+// writing need `&mut self`, so the channels are served in turn by a single
+// owner, not from several threads at the same time.
 pub struct StreamWrapper {
     stream: TcpStream,
+    ip: String,
+    config: ViperClientConfig,
+    // Frames received for a channel while reading for another one, by control
+    pending: HashMap<[u8; 2], VecDeque<Vec<u8>>>,
+    // Controls of the channels currently open, which must not be reused
+    open: HashSet<[u8; 2]>,
+    // Start of a frame received before a read timed out, completed by the next read
+    incoming: Vec<u8>,
 }
 
 impl StreamWrapper {
-    pub fn new(ip: String) -> StreamWrapper {
-        let stream = TcpStream::connect(ip).expect("Doorbell unavailable");
+    pub fn open(ip: String, config: ViperClientConfig) -> Result<StreamWrapper, io::Error> {
+        let stream = StreamWrapper::connect(&ip, &config)?;
 
-        stream
-            .set_read_timeout(Some(Duration::from_millis(TIMEOUT)))
-            .unwrap();
+        Ok(StreamWrapper {
+            stream,
+            ip,
+            config,
+            pending: HashMap::new(),
+            open: HashSet::new(),
+            incoming: Vec::new(),
+        })
+    }
 
-        stream
-            .set_write_timeout(Some(Duration::from_millis(TIMEOUT)))
-            .unwrap();
+    fn connect(ip: &str, config: &ViperClientConfig) -> Result<TcpStream, io::Error> {
+        let mut last_error = io::Error::new(io::ErrorKind::NotFound, "No address to connect to");
+        for addr in ip.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, config.connect_timeout) {
+                Ok(stream) => {
+                    stream.set_read_timeout(Some(config.read_timeout))?;
+                    stream.set_write_timeout(Some(config.read_timeout))?;
+                    return Ok(stream);
+                }
+                Err(e) => last_error = e,
+            }
+        }
+        Err(last_error)
+    }
+
+    // ip:port of the bridge
+    pub fn address(&self) -> &str {
+        &self.ip
+    }
 
-        StreamWrapper { stream }
+    pub fn config(&self) -> &ViperClientConfig {
+        &self.config
     }
 
+    // Replace the connection, e.g. after the bridge dropped it.
+    // Channels opened on the old connection are gone and must be opened again.
+    pub fn reconnect(&mut self) -> Result<(), io::Error> {
+        let _ = self.stream.shutdown(Shutdown::Both);
+        self.stream = StreamWrapper::connect(&self.ip, &self.config)?;
+        self.pending.clear();
+        self.open.clear();
+        self.incoming.clear();
+        Ok(())
+    }
+
+    // Send a request and read the reply on the channel whose control is in
+    // the header of the request
     pub fn execute(&mut self, b: &[u8]) -> ByteResult {
-        match self.write(b) {
-            Ok(_) => self.read(),
-            Err(e) => Err(e),
+        self.execute_on(&[b[4], b[5]], b)
+    }
+
+    // Like `execute`, for requests that carry the control of their channel in
+    // the body, like the opening and closing of the channel
+    pub fn execute_on(&mut self, control: &[u8; 2], b: &[u8]) -> ByteResult {
+        self.write(b)?;
+        self.read_on(control)
+    }
+
+    // Next frame of the channel with the given control. Frames of the other
+    // open channels are queued until their channel is read.
+    pub fn read_on(&mut self, control: &[u8; 2]) -> ByteResult {
+        if let Some(frame) = self.pending.get_mut(control).and_then(VecDeque::pop_front) {
+            return Ok(frame);
+        }
+        loop {
+            let (frame_control, frame) = self.read_frame()?;
+            if frame_control == *control || frame_control == UNADDRESSED {
+                return Ok(frame);
+            }
+            self.pending
+                .entry(frame_control)
+                .or_default()
+                .push_back(frame);
         }
     }
 
+    // Remember that a channel was opened with this control
+    pub fn opened(&mut self, control: &[u8; 2]) {
+        self.open.insert(*control);
+    }
+
+    pub fn is_open(&self, control: &[u8; 2]) -> bool {
+        self.open.contains(control)
+    }
+
+    // Drop the frames queued for a channel once it is closed, its control can
+    // be used again
+    pub fn discard(&mut self, control: &[u8; 2]) {
+        self.pending.remove(control);
+        self.open.remove(control);
+    }
+
+    // Reads block until data arrives when disabled, e.g. to wait for events
+    pub fn set_read_timeout(&mut self, enabled: bool) -> Result<(), io::Error> {
+        self.set_timeout(enabled.then_some(self.config.read_timeout))
+    }
+
+    // Reads fail with a timeout after the given time, block when `None`
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) -> Result<(), io::Error> {
+        self.stream.set_read_timeout(timeout)
+    }
+
     pub fn die(&mut self) {
         self.stream
             .shutdown(Shutdown::Both)
@@ -44,14 +148,32 @@ impl StreamWrapper {
         self.stream.write(b)
     }
 
-    pub fn read(&mut self) -> ByteResult {
-        let mut head = [0; 8];
-        self.stream.read_exact(&mut head)?;
-        let buffer_size = Command::buffer_length(head[2], head[3]);
+    // Next frame on the connection, with the control of its channel
+    fn read_frame(&mut self) -> Result<([u8; 2], Vec<u8>), io::Error> {
+        self.fill(HEADER_LENGTH)?;
+        let buffer_size = Command::buffer_length(self.incoming[2], self.incoming[3]);
+        self.fill(HEADER_LENGTH + buffer_size)?;
 
-        let mut buf = vec![0; buffer_size];
-        self.stream.read_exact(&mut buf)?;
-        Ok(buf)
+        let buf = self.incoming.split_off(HEADER_LENGTH);
+        let control = [self.incoming[4], self.incoming[5]];
+        self.incoming.clear();
+        Ok((control, buf))
+    }
+
+    // Read until `len` bytes are buffered. Unlike `read_exact`, the bytes
+    // already read are kept when the read times out.
+    fn fill(&mut self, len: usize) -> Result<(), io::Error> {
+        let mut chunk = [0; 1024];
+        while self.incoming.len() < len {
+            let missing = (len - self.incoming.len()).min(chunk.len());
+            match self.stream.read(&mut chunk[..missing]) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => self.incoming.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
     }
 }
 
@@ -66,7 +188,9 @@ mod tests {
     #[test]
     fn test_execute() {
         let listener = SimpleTcpListener::new("127.0.0.1:3333");
-        let mut client = StreamWrapper::new(String::from("127.0.0.1:3333"));
+        let mut client =
+            StreamWrapper::open(String::from("127.0.0.1:3333"), ViperClientConfig::default())
+                .unwrap();
 
         thread::spawn(move || listener.echo());
 
@@ -78,7 +202,9 @@ mod tests {
     #[test]
     fn test_make_command() {
         let listener = SimpleTcpListener::new("127.0.0.1:3334");
-        let mut client = StreamWrapper::new(String::from("127.0.0.1:3334"));
+        let mut client =
+            StreamWrapper::open(String::from("127.0.0.1:3334"), ViperClientConfig::default())
+                .unwrap();
 
         thread::spawn(move || listener.echo());
 
@@ -91,7 +217,9 @@ mod tests {
     #[test]
     fn test_make_uat_command() {
         let listener = SimpleTcpListener::new("127.0.0.1:3335");
-        let mut client = StreamWrapper::new(String::from("127.0.0.1:3335"));
+        let mut client =
+            StreamWrapper::open(String::from("127.0.0.1:3335"), ViperClientConfig::default())
+                .unwrap();
 
         thread::spawn(move || listener.echo());
 
@@ -99,4 +227,30 @@ mod tests {
         let r = client.execute(&aut).unwrap();
         assert_eq!(r.len(), 83);
     }
+
+    #[test]
+    fn test_read_after_timeout() {
+        let listener = SimpleTcpListener::new("127.0.0.1:3357");
+        let config = ViperClientConfig {
+            read_timeout: Duration::from_millis(100),
+            ..ViperClientConfig::default()
+        };
+        let mut client = StreamWrapper::open(String::from("127.0.0.1:3357"), config).unwrap();
+
+        // The frame arrives in two parts, the read times out in between
+        let server = thread::spawn(move || {
+            let mut stream = listener.accept();
+            let frame = Command::make(&[65, 65], &[1, 0]);
+            stream.write_all(&frame[..5]).unwrap();
+            thread::sleep(Duration::from_millis(300));
+            stream.write_all(&frame[5..]).unwrap();
+        });
+
+        assert_eq!(
+            client.read_on(&[1, 0]).unwrap_err().kind(),
+            io::ErrorKind::WouldBlock
+        );
+        server.join().unwrap();
+        assert_eq!(client.read_on(&[1, 0]).unwrap(), b"AA");
+    }
 }
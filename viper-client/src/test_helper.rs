@@ -1,7 +1,7 @@
 use crate::command::Command;
 use std::io;
 use std::io::prelude::*;
-use std::net::TcpListener;
+use std::net::{TcpListener, TcpStream};
 
 pub struct SimpleTcpListener {
     listener: TcpListener,
@@ -14,6 +14,41 @@ impl SimpleTcpListener {
         }
     }
 
+    pub fn accept(&self) -> TcpStream {
+        self.listener.accept().unwrap().0
+    }
+
+    pub fn read_frame(stream: &mut TcpStream) -> Vec<u8> {
+        SimpleTcpListener::read_request(stream).1
+    }
+
+    // Read a frame, with the control of the channel it was sent on
+    pub fn read_request(stream: &mut TcpStream) -> ([u8; 2], Vec<u8>) {
+        let mut head = [0; 8];
+        stream.read_exact(&mut head).unwrap();
+        let mut buf = vec![0; Command::buffer_length(head[2], head[3])];
+        stream.read_exact(&mut buf).unwrap();
+        ([head[4], head[5]], buf)
+    }
+
+    // Accept a connection and answer the opening and handshake of a CTPP
+    // channel, returning the control of the channel
+    pub fn accept_ctpp(&self) -> (TcpStream, [u8; 2]) {
+        let mut stream = self.accept();
+        let open = SimpleTcpListener::read_frame(&mut stream); // Opening the channel
+        let control = [open[12], open[13]];
+        stream
+            .write_all(&Command::make(&[0xcd, 0xab, 0x02, 0x00], &[0, 0]))
+            .unwrap();
+        let hs = SimpleTcpListener::read_frame(&mut stream);
+        let mask = &hs[2..6];
+        let confirm = [0x60, 0x18, mask[0] + 0x80, mask[1], mask[3], mask[2] + 1];
+        stream.write_all(&Command::make(&confirm, &[0, 0])).unwrap();
+        SimpleTcpListener::read_frame(&mut stream); // Acks of the handshake
+        SimpleTcpListener::read_frame(&mut stream);
+        (stream, control)
+    }
+
     pub fn echo(&self) -> io::Result<()> {
         let (mut socket, _addr) = self.listener.accept().unwrap();
         let mut head = [0; 8];
@@ -0,0 +1,128 @@
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use serde::Serialize;
+use tracing::{debug, warn};
+
+use crate::command_response::{AddressBookEntry, VipResponse, find_station};
+use crate::events::ViperEvent;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Body posted to the webhook for each event
+#[derive(Serialize, Debug)]
+struct WebhookPayload<'a> {
+    #[serde(flatten)]
+    event: &'a ViperEvent,
+    // Name of the door station the event comes from, when in the address book
+    station: Option<&'a str>,
+    // Apartment the events are received for
+    apt_address: &'a str,
+    // Unix timestamp, in seconds
+    timestamp: u64,
+}
+
+// Posts the events of `ViperClient::listen_events` as JSON to a URL
+pub(crate) struct Webhook {
+    url: String,
+    apt_address: String,
+    stations: Vec<AddressBookEntry>,
+}
+
+impl Webhook {
+    pub(crate) fn new(url: String, vip: &VipResponse) -> Webhook {
+        Webhook {
+            url,
+            apt_address: vip.apt_address.clone(),
+            stations: vip.user_parameters.stations(),
+        }
+    }
+
+    // Post the event from a background thread, so the listener is not delayed
+    pub(crate) fn notify(&self, event: &ViperEvent) {
+        let url = self.url.clone();
+        let body = self.payload(event);
+        thread::spawn(move || match post(&url, body) {
+            Ok(()) => debug!("Posted event to {}", url),
+            Err(e) => warn!("Failed to post event to {}: {}", url, e),
+        });
+    }
+
+    fn payload(&self, event: &ViperEvent) -> String {
+        let station = find_station(&self.stations, event.source()).map(|s| s.name.as_str());
+        let timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        serde_json::to_string(&WebhookPayload {
+            event,
+            station,
+            apt_address: &self.apt_address,
+            timestamp,
+        })
+        .unwrap()
+    }
+}
+
+fn post(url: &str, body: String) -> Result<(), reqwest::Error> {
+    reqwest::blocking::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()?
+        .post(url)
+        .header("Content-Type", "application/json")
+        .body(body)
+        .send()?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::prelude::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_post() {
+        let listener = TcpListener::bind("127.0.0.1:3348").unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _addr) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            // Read until the end of the JSON body
+            while !request.ends_with(b"}") {
+                let n = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                .unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        let webhook = Webhook {
+            url: String::from("http://127.0.0.1:3348/ring"),
+            apt_address: String::from("SB000006"),
+            stations: vec![AddressBookEntry {
+                id: String::from("1"),
+                name: String::from("Entrance"),
+                apt_address: String::from("SB000001"),
+                output_index: None,
+            }],
+        };
+        let event = ViperEvent::IncomingCall {
+            from: String::from("SB0000011"),
+        };
+        post(&webhook.url, webhook.payload(&event)).unwrap();
+
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /ring HTTP/1.1"));
+        let body = &request[request.find("\r\n\r\n").unwrap() + 4..];
+        let json: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(json["event"], "incoming_call");
+        assert_eq!(json["from"], "SB0000011");
+        assert_eq!(json["station"], "Entrance");
+        assert_eq!(json["apt_address"], "SB000006");
+    }
+}